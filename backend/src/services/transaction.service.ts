@@ -5,6 +5,8 @@ import {
   SystemProgram,
   Transaction,
   TransactionInstruction,
+  TransactionMessage,
+  VersionedTransaction,
   SYSVAR_RENT_PUBKEY,
 } from "@solana/web3.js";
 import {
@@ -36,6 +38,7 @@ export class TransactionService {
   private serverKeypair: Keypair;
   private uploadService: UploadService;
   private platformFeeWallet: PublicKey;
+  private configPda: PublicKey;
 
   constructor() {
     this.connection = getConnection();
@@ -74,6 +77,12 @@ export class TransactionService {
       process.env.PLATFORM_FEE_WALLET || this.serverKeypair.publicKey.toString()
     );
 
+    // Singleton GlobalConfig holding fees and the fee wallet
+    [this.configPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("config")],
+      PROGRAM_ID
+    );
+
     this.uploadService = new UploadService();
 
     console.log("✅ Transaction Service initialized");
@@ -88,6 +97,96 @@ export class TransactionService {
     return buffer;
   }
 
+  // Prices a trade with the program's quote_buy / quote_sell view instruction, so what
+  // we show always matches what the trade will charge or pay out
+  private async quoteTrade(
+    marketPubkey: PublicKey,
+    payer: PublicKey,
+    amount: number,
+    isBuy: boolean
+  ) {
+    const instruction = new TransactionInstruction({
+      keys: [
+        { pubkey: marketPubkey, isSigner: false, isWritable: false },
+        { pubkey: this.configPda, isSigner: false, isWritable: false },
+      ],
+      programId: PROGRAM_ID,
+      data: Buffer.concat([
+        isBuy ? DISCRIMINATORS.quoteBuy : DISCRIMINATORS.quoteSell,
+        this.serializeU64(amount),
+      ]),
+    });
+
+    const { blockhash } = await this.connection.getLatestBlockhash();
+    const message = new TransactionMessage({
+      payerKey: payer,
+      recentBlockhash: blockhash,
+      instructions: [instruction],
+    }).compileToV0Message();
+    const simulation = await this.connection.simulateTransaction(
+      new VersionedTransaction(message),
+      { sigVerify: false, replaceRecentBlockhash: true }
+    );
+
+    const returnData = simulation.value.returnData;
+    if (simulation.value.err || !returnData) {
+      throw new Error(`Quote failed: ${JSON.stringify(simulation.value.err)}`);
+    }
+
+    // TradeQuote: six little-endian u64s
+    const quote = Buffer.from(returnData.data[0], "base64");
+    return {
+      tokens: Number(quote.readBigUInt64LE(0)),
+      totalCost: Number(quote.readBigUInt64LE(8)),
+      platformFee: Number(quote.readBigUInt64LE(16)),
+      creatorFee: Number(quote.readBigUInt64LE(24)),
+      netLamports: Number(quote.readBigUInt64LE(32)),
+      priceAfter: Number(quote.readBigUInt64LE(40)),
+    };
+  }
+
+  // Reads the TradeExecuted event a confirmed buy or sell logged, so the database records
+  // exactly what the program charged or paid out
+  private tradeFromLogs(logs: string[] | null | undefined) {
+    const prefix = "Program data: ";
+    for (const log of logs ?? []) {
+      if (!log.startsWith(prefix)) continue;
+      const data = Buffer.from(log.slice(prefix.length), "base64");
+      if (!data.subarray(0, 8).equals(DISCRIMINATORS.tradeExecuted)) continue;
+
+      // Skip the market, then the buyer and seller options
+      let offset = 8 + 32;
+      for (let i = 0; i < 2; i++) {
+        offset += data[offset] === 1 ? 33 : 1;
+      }
+      const readU64 = () => {
+        const value = Number(data.readBigUInt64LE(offset));
+        offset += 8;
+        return value;
+      };
+
+      const tokens = readU64();
+      const price = readU64();
+      const totalCost = readU64();
+      const platformFee = readU64();
+      const creatorFee = readU64();
+      // Unrounded fee, platform and creator shares (u128 each), the trade kind, then the
+      // sequence number and supply before the trade
+      offset += 3 * 16 + 1 + 8 + 8;
+      const circulatingSupplyAfter = readU64();
+
+      return {
+        tokens,
+        price,
+        totalCost,
+        platformFee,
+        creatorFee,
+        circulatingSupplyAfter,
+      };
+    }
+    throw new Error("No TradeExecuted event in the transaction logs");
+  }
+
   private serializeString(str: string): Buffer {
     const stringBytes = Buffer.from(str, "utf8");
     const lengthBuffer = Buffer.alloc(4);
@@ -275,60 +374,6 @@ export class TransactionService {
     }
   }
 
-  private calculateBondingCurvePrice(circulatingSupply: number): number {
-    const BASE_PRICE = 1_000_000; // 0.001 SOL in lamports
-    const K_FACTOR = 5;
-    const SCALE_FACTOR = 10_000;
-
-    if (circulatingSupply === 0) {
-      return BASE_PRICE;
-    }
-
-    // ✅ CRITICAL FIX: Use integer division like Rust (no floating point)
-    const exponent = Math.floor((circulatingSupply * K_FACTOR) / SCALE_FACTOR);
-
-    let price: number;
-
-    if (exponent < 10) {
-      // ✅ Match Rust exactly: integer arithmetic only
-      const multiplier =
-        10000 +
-        exponent * 10000 +
-        Math.floor((exponent * exponent * 5000) / 10000);
-      price = Math.floor((BASE_PRICE * multiplier) / 10000);
-    } else {
-      // ✅ Use same power calculation as Rust
-      const cappedExponent = Math.min(exponent, 20);
-      price = Math.floor(BASE_PRICE * Math.pow(2, cappedExponent));
-    }
-
-    const finalPrice = Math.max(price, BASE_PRICE);
-
-    // ✅ Add logging for debugging
-    console.log(`🔢 Price calculation for supply ${circulatingSupply}:`, {
-      exponent,
-      price,
-      finalPrice,
-    });
-
-    return finalPrice;
-  }
-
-  private calculateBuyCost(fromSupply: number, toSupply: number): number {
-    let totalCost = 0;
-
-    for (let i = fromSupply; i < toSupply; i++) {
-      const price = this.calculateBondingCurvePrice(i);
-      totalCost += price;
-    }
-
-    return Math.floor(totalCost);
-  }
-
-  private calculateSellValue(fromSupply: number, toSupply: number): number {
-    return this.calculateBuyCost(toSupply, fromSupply);
-  }
-
   private async saveTransactionToDb(
     signature: string,
     marketAddress: string,
@@ -468,17 +513,18 @@ export class TransactionService {
       console.log("  Circulating Supply:", circulatingSupply);
       console.log("  Current Price:", Number(currentPrice), "lamports");
 
-      // ✅ Calculate cost using bonding curve
-      const totalCost = this.calculateBuyCost(
-        circulatingSupply,
-        circulatingSupply + amount
+      // Quote on-chain so cost and fees match what buy_tokens charges
+      const quote = await this.quoteTrade(
+        marketPubkey,
+        userPubkey,
+        amount,
+        true
       );
-
-      // Calculate fees (1% total)
-      const totalFee = Math.floor(totalCost * 0.01);
-      const platformFee = Math.floor(totalFee * 0.7); // 70%
-      const creatorFee = Math.floor(totalFee * 0.3); // 30%
-      const totalWithFees = totalCost + totalFee;
+      const totalCost = quote.totalCost;
+      const platformFee = quote.platformFee;
+      const creatorFee = quote.creatorFee;
+      const totalFee = platformFee + creatorFee;
+      const totalWithFees = quote.netLamports;
 
       console.log("💰 Bonding Curve Calculation:");
       console.log("  Total Cost:", totalCost, "lamports");
@@ -529,11 +575,15 @@ export class TransactionService {
         transaction.add(createAtaIx);
       }
 
+      // Cap what the program may charge (cost + fees) with a 20% safety margin
+      const maxCost = Math.ceil(totalWithFees * 1.2);
+
       // Build buy instruction
       const discriminator = DISCRIMINATORS.buyTokens;
       const instructionData = Buffer.concat([
         discriminator,
         this.serializeU64(amount),
+        this.serializeU64(maxCost),
       ]);
 
       // ✅ NEW: Add creator wallet to accounts
@@ -546,6 +596,7 @@ export class TransactionService {
         { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
         { pubkey: treasury, isSigner: false, isWritable: true },
         { pubkey: userTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: this.configPda, isSigner: false, isWritable: false },
         { pubkey: this.platformFeeWallet, isSigner: false, isWritable: true },
         { pubkey: creatorWallet, isSigner: false, isWritable: true }, // ✅ NEW
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -631,7 +682,7 @@ export class TransactionService {
 
       // Derive PDAs
       const [mintAuthority] = PublicKey.findProgramAddressSync(
-        [Buffer.from("mint-authority")],
+        [Buffer.from("mint-authority"), mintKeypair.publicKey.toBuffer()],
         PROGRAM_ID
       );
 
@@ -667,6 +718,11 @@ export class TransactionService {
         this.serializeString(name),
         this.serializeString(symbol),
         this.serializeString(metadataUri),
+        Buffer.from([0]), // curve_type: Exponential
+        Buffer.from([0]), // max_price_lamports: None, the program's default cap
+        Buffer.from([0]), // graduation_threshold: None, the program's default
+        Buffer.from([0]), // creator_allocation: None
+        Buffer.from([0]), // curve_growth: None, the program's default rate
       ]);
 
       // Build instruction with USER as first account (fee payer)
@@ -683,6 +739,7 @@ export class TransactionService {
         { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
         { pubkey: treasury, isSigner: false, isWritable: true },
         { pubkey: metadataPDA, isSigner: false, isWritable: true },
+        { pubkey: this.configPda, isSigner: false, isWritable: false },
         { pubkey: this.platformFeeWallet, isSigner: false, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -693,6 +750,10 @@ export class TransactionService {
         },
         { pubkey: METADATA_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+        // vesting_schedule and vesting_vault: optional accounts, passed as the program ID
+        // when there is no creator allocation
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
       ];
 
       const instruction = new TransactionInstruction({
@@ -843,10 +904,20 @@ export class TransactionService {
         console.log("✅ Token account created");
       }
 
+      // Same 20% margin over the quoted cost (cost + fees) as user buys
+      const quote = await this.quoteTrade(
+        marketPubkey,
+        this.serverKeypair.publicKey,
+        amount,
+        true
+      );
+      const maxCost = Math.ceil(quote.netLamports * 1.2);
+
       const discriminator = DISCRIMINATORS.buyTokens;
       const instructionData = Buffer.concat([
         discriminator,
         this.serializeU64(amount),
+        this.serializeU64(maxCost),
       ]);
 
       const keys = [
@@ -860,6 +931,7 @@ export class TransactionService {
         { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
         { pubkey: treasury, isSigner: false, isWritable: true },
         { pubkey: destinationTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: this.configPda, isSigner: false, isWritable: false },
         { pubkey: this.platformFeeWallet, isSigner: false, isWritable: true },
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
@@ -898,9 +970,11 @@ export class TransactionService {
 
       console.log("✅ Tokens purchased successfully!");
 
-      // Calculate costs
-      const totalCost = Number(currentPriceData) * amount;
-      const platformFee = Math.floor(totalCost * 0.01);
+      const tx = await this.connection.getTransaction(signature, {
+        commitment: "finalized",
+        maxSupportedTransactionVersion: 0,
+      });
+      const trade = this.tradeFromLogs(tx?.meta?.logMessages);
 
       // ✅ SAVE BUY TRANSACTION TO DATABASE
       await this.saveTransactionToDb(
@@ -908,17 +982,20 @@ export class TransactionService {
         marketAddress,
         "BUY",
         destinationWallet,
-        amount,
-        Number(currentPriceData),
-        totalCost,
-        platformFee
+        trade.tokens,
+        trade.price,
+        trade.totalCost,
+        trade.platformFee,
+        trade.creatorFee
       );
 
       return {
         signature,
         destinationWallet,
-        amount,
-        platformFee: "1%",
+        amount: trade.tokens,
+        totalCost: trade.totalCost,
+        platformFee: trade.platformFee,
+        creatorFee: trade.creatorFee,
         tokenAccount: destinationTokenAccount.toString(),
         explorerUrl: `https://solscan.io/tx/${signature}?cluster=devnet`,
       };
@@ -969,27 +1046,13 @@ export class TransactionService {
         throw new Error("Market not found in database");
       }
 
-      // ✅ Use database circulating supply for calculation
-      const circulatingSupply = Number(marketDb.circulatingSupply);
-
-      console.log("📊 Market state before sell:");
-      console.log("  Circulating Supply:", circulatingSupply);
-
-      // ✅ CRITICAL FIX: Calculate sell value using bonding curve
-      const totalValue = this.calculateSellValue(
-        circulatingSupply,
-        circulatingSupply - amount
-      );
-
-      // ✅ Calculate new price after sell (supply decreased)
-      const currentPrice = this.calculateBondingCurvePrice(
-        circulatingSupply - amount
-      );
-
-      // ✅ Calculate fees with 70/30 split
-      const totalFee = Math.floor(totalValue * 0.01);
-      const platformFee = Math.floor(totalFee * 0.7); // 70%
-      const creatorFee = totalFee - platformFee; // 30% (avoid rounding issues)
+      // What the program actually paid out, from its TradeExecuted event
+      const trade = this.tradeFromLogs(tx.meta.logMessages);
+      const totalValue = trade.totalCost;
+      const currentPrice = trade.price;
+      const platformFee = trade.platformFee;
+      const creatorFee = trade.creatorFee;
+      const totalFee = platformFee + creatorFee;
 
       console.log("💰 Sell transaction details:");
       console.log(
@@ -1004,23 +1067,23 @@ export class TransactionService {
         "lamports",
         `(${(currentPrice / 1e9).toFixed(6)} SOL)`
       );
-      console.log("  Total Fee (1%):", totalFee, "lamports");
-      console.log("  Platform Fee (70%):", platformFee, "lamports");
-      console.log("  Creator Fee (30%):", creatorFee, "lamports");
+      console.log("  Total Fee:", totalFee, "lamports");
+      console.log("  Platform Fee:", platformFee, "lamports");
+      console.log("  Creator Fee:", creatorFee, "lamports");
       console.log("  User Received:", totalValue - totalFee, "lamports");
 
       // ✅ CRITICAL: Update circulating supply in database (this was missing!)
       await prisma.market.update({
         where: { publicKey: marketAddress },
         data: {
-          circulatingSupply: { decrement: BigInt(amount) }, // Decrease supply
+          circulatingSupply: BigInt(trade.circulatingSupplyAfter),
           currentPrice: BigInt(currentPrice), // Update to new price
           updatedAt: new Date(),
         },
       });
 
       console.log("✅ Market updated:");
-      console.log("  New Circulating Supply:", circulatingSupply - amount);
+      console.log("  New Circulating Supply:", trade.circulatingSupplyAfter);
       console.log("  New Current Price:", currentPrice);
 
       // ✅ Save sell transaction with fee split
@@ -1029,7 +1092,7 @@ export class TransactionService {
         marketAddress,
         "SELL",
         userWallet,
-        amount,
+        trade.tokens,
         currentPrice,
         totalValue,
         platformFee,
@@ -1048,7 +1111,7 @@ export class TransactionService {
         creatorFee,
         userReceived: totalValue - totalFee,
         userReceivedSOL: ((totalValue - totalFee) / 1e9).toFixed(6),
-        newCirculatingSupply: circulatingSupply - amount,
+        newCirculatingSupply: trade.circulatingSupplyAfter,
         newPrice: currentPrice,
         message: "Sell transaction confirmed and saved to database",
       };
@@ -1116,16 +1179,18 @@ export class TransactionService {
         );
       }
 
-      // ✅ Calculate sell value using bonding curve (matches Rust)
-      const totalValue = this.calculateSellValue(
-        circulatingSupply,
-        circulatingSupply - amount
+      // Quote on-chain so proceeds and fees match what sell_tokens pays out
+      const quote = await this.quoteTrade(
+        marketPubkey,
+        userPubkey,
+        amount,
+        false
       );
-
-      const totalFee = Math.floor(totalValue * 0.01);
-      const platformFee = Math.floor(totalFee * 0.7);
-      const creatorFee = totalFee - platformFee; // ✅ Ensure no rounding issues
-      const userReceives = totalValue - totalFee;
+      const totalValue = quote.totalCost;
+      const platformFee = quote.platformFee;
+      const creatorFee = quote.creatorFee;
+      const userReceives = quote.netLamports;
+      const totalFee = totalValue - userReceives;
 
       // ✅ CRITICAL: Use provided minReceiveLamports OR calculate with safety margin
       const minReceive =
@@ -1201,6 +1266,7 @@ export class TransactionService {
         { pubkey: escrowTokenAccount, isSigner: false, isWritable: true },
         { pubkey: treasury, isSigner: false, isWritable: true },
         { pubkey: userTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: this.configPda, isSigner: false, isWritable: false },
         { pubkey: this.platformFeeWallet, isSigner: false, isWritable: true },
         { pubkey: creatorWallet, isSigner: false, isWritable: true },
         { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
@@ -1286,27 +1352,18 @@ export class TransactionService {
         throw new Error("Market not found");
       }
 
-      const circulatingSupply = Number(marketDb.circulatingSupply);
-
-      // Calculate cost using bonding curve
-      const totalCost = this.calculateBuyCost(
-        circulatingSupply,
-        circulatingSupply + amount
-      );
-
-      const currentPrice = Math.floor(
-        this.calculateBondingCurvePrice(circulatingSupply + amount)
-      );
-
-      const totalFee = Math.floor(totalCost * 0.01);
-      const platformFee = Math.floor(totalFee * 0.7);
-      const creatorFee = Math.floor(totalFee * 0.3);
+      // What the program actually charged, from its TradeExecuted event
+      const trade = this.tradeFromLogs(tx.meta.logMessages);
+      const totalCost = trade.totalCost;
+      const currentPrice = trade.price;
+      const platformFee = trade.platformFee;
+      const creatorFee = trade.creatorFee;
 
       // ✅ Update circulating supply
       await prisma.market.update({
         where: { publicKey: marketAddress },
         data: {
-          circulatingSupply: { increment: BigInt(amount) },
+          circulatingSupply: BigInt(trade.circulatingSupplyAfter),
           currentPrice: BigInt(currentPrice),
         },
       });
@@ -1317,7 +1374,7 @@ export class TransactionService {
         marketAddress,
         "BUY",
         userWallet,
-        amount,
+        trade.tokens,
         currentPrice,
         totalCost,
         platformFee,
@@ -7,12 +7,21 @@ export class Discriminators {
     const hash = createHash("sha256").update(preimage).digest();
     return hash.slice(0, 8);
   }
+
+  static event(name: string): Buffer {
+    const preimage = `event:${name}`;
+    const hash = createHash("sha256").update(preimage).digest();
+    return hash.slice(0, 8);
+  }
 }
 
 export const DISCRIMINATORS = {
   createMarket: Discriminators.instruction("create_market"),
   buyTokens: Discriminators.instruction("buy_tokens"),
   sellTokens: Discriminators.instruction("sell_tokens"),
+  quoteBuy: Discriminators.instruction("quote_buy"),
+  quoteSell: Discriminators.instruction("quote_sell"),
+  tradeExecuted: Discriminators.event("TradeExecuted"),
 };
 
 console.log("📝 Instruction Discriminators:");
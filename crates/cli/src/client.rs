@@ -0,0 +1,210 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+use anyhow::{anyhow, bail, Context, Result};
+use celebrity_exchange_sdk::events::parse_logs;
+use celebrity_exchange_sdk::program::{GlobalConfig, Market, Pool, VestingSchedule};
+use celebrity_exchange_sdk::{pda, state, ID};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::config::{
+    RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::path::{Path, PathBuf};
+
+use crate::display;
+
+/// A pool account with what it holds.
+pub struct PoolState {
+    pub address: Pubkey,
+    pub pool: Pool,
+    /// Lamports above the SOL vault's rent-exempt minimum
+    pub sol_reserve: u64,
+    pub token_reserve: u64,
+    /// LP tokens in circulation plus the pool's locked liquidity
+    pub lp_supply: u64,
+}
+
+/// RPC access plus the signer and send mode chosen on the command line.
+pub struct Client {
+    rpc: RpcClient,
+    keypair_path: PathBuf,
+    dry_run: bool,
+}
+
+impl Client {
+    pub fn new(rpc_url: &str, keypair: Option<&Path>, dry_run: bool) -> Result<Self> {
+        let keypair_path = match keypair {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let home = std::env::var_os("HOME").context("HOME is not set; pass --keypair")?;
+                PathBuf::from(home).join(".config/solana/id.json")
+            }
+        };
+
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
+            keypair_path,
+            dry_run,
+        })
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    // Read lazily, so read-only commands work without a keypair file
+    pub fn signer(&self) -> Result<Keypair> {
+        read_keypair_file(&self.keypair_path)
+            .map_err(|e| anyhow!("reading keypair {}: {e}", self.keypair_path.display()))
+    }
+
+    pub fn config(&self) -> Result<GlobalConfig> {
+        let data = self
+            .rpc
+            .get_account_data(&pda::config())
+            .context("fetching the global config; has it been initialized?")?;
+        Ok(state::decode_config(&data)?)
+    }
+
+    /// The market at `address`, or the market of `address` taken as a mint.
+    pub fn market(&self, address: &Pubkey) -> Result<(Pubkey, Market)> {
+        for candidate in [*address, pda::market(address)] {
+            if let Ok(account) = self.rpc.get_account(&candidate) {
+                if account.owner == ID {
+                    if let Ok(market) = state::decode_market(&account.data) {
+                        return Ok((candidate, market));
+                    }
+                    // Shown as `migrate_mint_authority` will rewrite it
+                    if let Ok(legacy) = state::decode_legacy_market(&account.data) {
+                        return Ok((candidate, legacy.upgrade()));
+                    }
+                }
+            }
+        }
+
+        bail!("{address} is neither a market nor the mint of one")
+    }
+
+    /// The creator vesting schedule of the market at `market`.
+    pub fn vesting_schedule(&self, market: &Pubkey) -> Result<VestingSchedule> {
+        let data = self
+            .rpc
+            .get_account_data(&pda::vesting_schedule(market))
+            .context("fetching the vesting schedule; was the market created with an allocation?")?;
+        Ok(state::decode_vesting_schedule(&data)?)
+    }
+
+    /// The pool at `address`, or the pool of `address` taken as a mint, with its reserves.
+    pub fn pool(&self, address: &Pubkey) -> Result<PoolState> {
+        for candidate in [*address, pda::pool(address)] {
+            if let Ok(account) = self.rpc.get_account(&candidate) {
+                if account.owner == ID {
+                    if let Ok(pool) = state::decode_pool(&account.data) {
+                        return self.pool_state(candidate, pool);
+                    }
+                }
+            }
+        }
+
+        bail!("{address} is neither a pool nor the mint of one")
+    }
+
+    fn pool_state(&self, address: Pubkey, pool: Pool) -> Result<PoolState> {
+        let rent = self.rpc.get_minimum_balance_for_rent_exemption(0)?;
+        let sol_reserve = self.rpc.get_balance(&pool.sol_vault)?.saturating_sub(rent);
+        let token_reserve: u64 = self
+            .rpc
+            .get_token_account_balance(&pool.token_vault)?
+            .amount
+            .parse()?;
+        let lp_minted: u64 = self.rpc.get_token_supply(&pool.lp_mint)?.amount.parse()?;
+
+        Ok(PoolState {
+            address,
+            sol_reserve,
+            token_reserve,
+            lp_supply: lp_minted + pool.locked_liquidity,
+            pool,
+        })
+    }
+
+    /// Every market account of the program.
+    pub fn markets(&self) -> Result<Vec<(Pubkey, Market)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                0,
+                Market::DISCRIMINATOR.to_vec(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                commitment: Some(self.rpc.commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        let accounts = self.rpc.get_program_accounts_with_config(&ID, config)?;
+        accounts
+            .into_iter()
+            .map(|(address, account)| Ok((address, state::decode_market(&account.data)?)))
+            .collect()
+    }
+
+    /// Signs `instructions` with `payer` and any `signers`, then sends them, or only
+    /// simulates them under `--dry-run`.
+    pub fn execute(
+        &self,
+        payer: &Keypair,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<()> {
+        let blockhash = self.rpc.get_latest_blockhash()?;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        if !self.dry_run {
+            let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+            println!("Signature: {signature}");
+            return Ok(());
+        }
+
+        let simulation = self
+            .rpc
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: true,
+                    commitment: Some(self.rpc.commitment()),
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )?
+            .value;
+
+        let logs = simulation.logs.unwrap_or_default();
+        for log in &logs {
+            println!("  {log}");
+        }
+        for event in parse_logs(&logs) {
+            display::event(&event);
+        }
+        if let Some(units) = simulation.units_consumed {
+            println!("Compute units: {units}");
+        }
+        match simulation.err {
+            Some(err) => bail!("simulation failed: {err}"),
+            None => {
+                println!("Dry run: simulation succeeded, nothing was sent");
+                Ok(())
+            }
+        }
+    }
+}
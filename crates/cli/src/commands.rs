@@ -0,0 +1,447 @@
+use anyhow::{anyhow, bail, Result};
+use celebrity_exchange_sdk::program::{
+    self, instruction, ConfigParams, CurveGrowth, CurveType, GraduationThreshold, TradeKind,
+    TradingMode, VestingParams,
+};
+use celebrity_exchange_sdk::{instructions, math, pda, state};
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+
+use crate::client::Client;
+use crate::{
+    display, AdminCommand, Command, CreateArgs, Curve, FeeArgs, Mode, PoolCommand, Side, TradeArgs,
+};
+
+const DAY: i64 = 24 * 60 * 60;
+const DEFAULT_MIN_FEE: u64 = 5_000; // Lamports, `init-config` without --min-fee
+
+pub fn run(client: &Client, command: Command) -> Result<()> {
+    match command {
+        Command::Create(args) => create(client, args),
+        Command::Buy(args) => buy(client, args),
+        Command::Sell(args) => sell(client, args),
+        Command::Quote {
+            side,
+            market,
+            amount,
+        } => {
+            let (_, market) = client.market(&market)?;
+            let quote = quote(&market, &client.config()?, side, amount)?;
+            display::quote(side, &quote);
+            Ok(())
+        }
+        Command::List => {
+            let mut markets = client.markets()?;
+            markets.sort_by(|a, b| a.1.symbol.cmp(&b.1.symbol));
+            display::market_table(&markets);
+            Ok(())
+        }
+        Command::Inspect { market } => {
+            let (address, market) = client.market(&market)?;
+            display::market(&address, &market);
+            Ok(())
+        }
+        Command::Verify { market } => {
+            let (address, market) = client.market(&market)?;
+            let report = market.solvency(
+                client.rpc().get_balance(&market.treasury)?,
+                client.rpc().get_minimum_balance_for_rent_exemption(0)?,
+            )?;
+            display::solvency(&address, &report);
+            if report.shortfall > 0 {
+                bail!("treasury is short by {}", display::sol(report.shortfall));
+            }
+            Ok(())
+        }
+        Command::Config => {
+            display::config(&pda::config(), &client.config()?);
+            Ok(())
+        }
+        Command::Graduate { market } => {
+            let payer = client.signer()?;
+            let (_, market) = client.market(&market)?;
+            let ix = instructions::graduate_market(payer.pubkey(), market.mint);
+            client.execute(&payer, &[ix], &[])
+        }
+        Command::Vesting { market } => {
+            let (address, market) = client.market(&market)?;
+            let schedule = client.vesting_schedule(&address)?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs() as i64;
+            display::vesting_schedule(&market, &schedule, now);
+            Ok(())
+        }
+        Command::Claim { market } => {
+            let beneficiary = client.signer()?;
+            let (_, market) = client.market(&market)?;
+            let ixs = [
+                instructions::create_user_token_account(
+                    beneficiary.pubkey(),
+                    beneficiary.pubkey(),
+                    market.mint,
+                ),
+                instructions::claim_vested(beneficiary.pubkey(), market.mint),
+            ];
+            client.execute(&beneficiary, &ixs, &[])
+        }
+        Command::Close { market } => {
+            let authority = client.signer()?;
+            let (address, _) = client.market(&market)?;
+            let ix = instructions::close_market(authority.pubkey(), address);
+            client.execute(&authority, &[ix], &[])
+        }
+        Command::Redeem { market, amount } => {
+            let user = client.signer()?;
+            let (address, market) = client.market(&market)?;
+            let accounts = instructions::redeem_accounts(user.pubkey(), address, &market);
+            let ix = instructions::redeem_tokens(accounts, amount);
+            client.execute(&user, &[ix], &[])
+        }
+        Command::FinalizeClose { market } => {
+            let payer = client.signer()?;
+            let (address, market) = client.market(&market)?;
+            let ix = instructions::finalize_close(address, &market);
+            client.execute(&payer, &[ix], &[])
+        }
+        Command::Pool(command) => pool(client, command),
+        Command::UpdateMetadata(args) => {
+            let owner = client.signer()?;
+            let (address, market) = client.market(&args.market)?;
+            let ix = instructions::update_market_metadata(
+                owner.pubkey(),
+                address,
+                market.mint,
+                instruction::UpdateMarketMetadata {
+                    name: args.name,
+                    symbol: args.symbol,
+                    uri: args.uri,
+                    lock: args.lock,
+                },
+            );
+            client.execute(&owner, &[ix], &[])
+        }
+        Command::Admin(command) => admin(client, command),
+    }
+}
+
+fn create(client: &Client, args: CreateArgs) -> Result<()> {
+    let payer = client.signer()?;
+    let mint = match &args.mint_keypair {
+        Some(path) => read_keypair_file(path)
+            .map_err(|e| anyhow!("reading mint keypair {}: {e}", path.display()))?,
+        None => Keypair::new(),
+    };
+    let config = client.config()?;
+
+    let ix = instructions::create_market(
+        payer.pubkey(),
+        mint.pubkey(),
+        config.fee_wallet,
+        instruction::CreateMarket {
+            initial_price_lamports: args.initial_price,
+            initial_supply: args.supply,
+            name: args.name,
+            symbol: args.symbol,
+            uri: args.uri,
+            curve_type: args.curve.into(),
+            max_price_lamports: args.max_price,
+            graduation_threshold: match (args.graduate_at_supply, args.graduate_at_market_cap) {
+                (Some(supply), _) => Some(GraduationThreshold::SoldSupply(supply)),
+                (_, Some(cap)) => Some(GraduationThreshold::MarketCap(cap)),
+                (None, None) => None,
+            },
+            creator_allocation: match (args.creator_allocation, args.vesting_days) {
+                (Some(amount), Some(days)) => Some(VestingParams {
+                    amount,
+                    cliff_seconds: args.cliff_days * DAY,
+                    duration_seconds: days * DAY,
+                }),
+                _ => None,
+            },
+            curve_growth: match (args.growth_k, args.growth_scale, args.virtual_token_reserve) {
+                (Some(k_factor), Some(scale_factor), _) => Some(CurveGrowth::Rate {
+                    k_factor,
+                    scale_factor,
+                }),
+                (_, _, Some(token_reserve)) => Some(CurveGrowth::VirtualReserves { token_reserve }),
+                _ => None,
+            },
+        },
+    );
+
+    println!("Mint:     {}", mint.pubkey());
+    println!("Market:   {}", pda::market(&mint.pubkey()));
+    println!("Creation fee: {}", display::sol(config.creation_fee));
+    client.execute(&payer, &[ix], &[&mint])
+}
+
+fn buy(client: &Client, args: TradeArgs) -> Result<()> {
+    let user = client.signer()?;
+    let (address, market) = client.market(&args.market)?;
+    let config = client.config()?;
+
+    let quote = quote(&market, &config, Side::Buy, args.amount)?;
+    let max_cost = with_slippage(quote.net_lamports, args.slippage_bps, true);
+    display::quote(Side::Buy, &quote);
+    println!("Max cost:     {}", display::sol(max_cost));
+
+    let accounts =
+        instructions::buy_sell_accounts(user.pubkey(), address, &market, config.fee_wallet);
+    let ixs = [
+        instructions::create_user_token_account(user.pubkey(), user.pubkey(), market.mint),
+        instructions::buy_tokens(accounts, args.amount, max_cost),
+    ];
+    client.execute(&user, &ixs, &[])
+}
+
+fn sell(client: &Client, args: TradeArgs) -> Result<()> {
+    let user = client.signer()?;
+    let (address, market) = client.market(&args.market)?;
+    let config = client.config()?;
+
+    let quote = quote(&market, &config, Side::Sell, args.amount)?;
+    let min_receive = with_slippage(quote.net_lamports, args.slippage_bps, false);
+    display::quote(Side::Sell, &quote);
+    println!("Min receive:  {}", display::sol(min_receive));
+
+    let accounts =
+        instructions::buy_sell_accounts(user.pubkey(), address, &market, config.fee_wallet);
+    let ix = instructions::sell_tokens(accounts, args.amount, min_receive);
+    client.execute(&user, &[ix], &[])
+}
+
+fn pool(client: &Client, command: PoolCommand) -> Result<()> {
+    match command {
+        PoolCommand::Init { mint, sol, tokens } => {
+            let creator = client.signer()?;
+            let ix = instructions::initialize_pool(creator.pubkey(), mint, sol, tokens);
+            println!("Pool:     {}", pda::pool(&mint));
+            client.execute(&creator, &[ix], &[])
+        }
+        PoolCommand::Inspect { pool } => {
+            display::pool(&client.pool(&pool)?);
+            Ok(())
+        }
+        PoolCommand::Swap {
+            side,
+            pool,
+            amount,
+            slippage_bps,
+        } => {
+            if amount == 0 {
+                bail!("amount must be greater than zero");
+            }
+            let user = client.signer()?;
+            let pool = client.pool(&pool)?;
+            let config = client.config()?;
+
+            let kind = match side {
+                Side::Buy => TradeKind::Buy,
+                Side::Sell => TradeKind::Sell,
+            };
+            let quote =
+                state::quote_swap(&config, kind, amount, pool.sol_reserve, pool.token_reserve)
+                    .map_err(|e| anyhow!("pricing the swap: {e}"))?;
+            let min_out = with_slippage(quote.amount_out, slippage_bps, false);
+            display::swap_quote(side, &quote);
+            println!("Min out:      {min_out}");
+
+            let accounts = instructions::swap_accounts(
+                user.pubkey(),
+                pool.address,
+                &pool.pool,
+                config.fee_wallet,
+            );
+            let ixs = [
+                instructions::create_user_token_account(
+                    user.pubkey(),
+                    user.pubkey(),
+                    pool.pool.mint,
+                ),
+                instructions::swap(accounts, kind, amount, min_out),
+            ];
+            client.execute(&user, &ixs, &[])
+        }
+        PoolCommand::AddLiquidity(args) => {
+            let user = client.signer()?;
+            let pool = client.pool(&args.pool)?;
+            let deposit = |reserve| {
+                math::deposit_for_liquidity(args.liquidity, reserve, pool.lp_supply)
+                    .map_err(|e| anyhow!("pricing the deposit: {e}"))
+            };
+            let sol = deposit(pool.sol_reserve)?;
+            let tokens = deposit(pool.token_reserve)?;
+            println!("Deposit:      {} and {tokens} tokens", display::sol(sol));
+
+            let accounts =
+                instructions::liquidity_accounts(user.pubkey(), pool.address, &pool.pool);
+            let ixs = [
+                instructions::create_user_token_account(
+                    user.pubkey(),
+                    user.pubkey(),
+                    pool.pool.lp_mint,
+                ),
+                instructions::add_liquidity(
+                    accounts,
+                    args.liquidity,
+                    with_slippage(sol, args.slippage_bps, true),
+                    with_slippage(tokens, args.slippage_bps, true),
+                ),
+            ];
+            client.execute(&user, &ixs, &[])
+        }
+        PoolCommand::RemoveLiquidity(args) => {
+            let user = client.signer()?;
+            let pool = client.pool(&args.pool)?;
+            let withdrawal = |reserve| {
+                math::withdrawal_for_liquidity(args.liquidity, reserve, pool.lp_supply)
+                    .map_err(|e| anyhow!("pricing the withdrawal: {e}"))
+            };
+            let sol = withdrawal(pool.sol_reserve)?;
+            let tokens = withdrawal(pool.token_reserve)?;
+            println!("Withdraw:     {} and {tokens} tokens", display::sol(sol));
+
+            let accounts =
+                instructions::liquidity_accounts(user.pubkey(), pool.address, &pool.pool);
+            let ix = instructions::remove_liquidity(
+                accounts,
+                args.liquidity,
+                with_slippage(sol, args.slippage_bps, false),
+                with_slippage(tokens, args.slippage_bps, false),
+            );
+            client.execute(&user, &[ix], &[])
+        }
+    }
+}
+
+fn admin(client: &Client, command: AdminCommand) -> Result<()> {
+    let admin = client.signer()?;
+    let ix = match command {
+        AdminCommand::InitConfig {
+            admin: new_admin,
+            fees,
+        } => {
+            let missing = |flag: &str| anyhow!("--{flag} is required to initialize the config");
+            let params = ConfigParams {
+                fee_wallet: fees.fee_wallet.ok_or_else(|| missing("fee-wallet"))?,
+                creation_fee: fees.creation_fee.ok_or_else(|| missing("creation-fee"))?,
+                transaction_fee_bps: fees
+                    .transaction_fee_bps
+                    .ok_or_else(|| missing("transaction-fee-bps"))?,
+                platform_fee_share_bps: fees
+                    .platform_fee_share_bps
+                    .ok_or_else(|| missing("platform-fee-share-bps"))?,
+                creator_fee_share_bps: fees
+                    .creator_fee_share_bps
+                    .ok_or_else(|| missing("creator-fee-share-bps"))?,
+                min_fee: fees.min_fee.unwrap_or(DEFAULT_MIN_FEE),
+            };
+            instructions::initialize_config(
+                admin.pubkey(),
+                new_admin.unwrap_or(admin.pubkey()),
+                params,
+            )
+        }
+        AdminCommand::UpdateConfig { fees } => {
+            let params = merge_fees(&client.config()?, fees);
+            instructions::update_config(admin.pubkey(), params)
+        }
+        AdminCommand::ProposeAdmin { new_admin } => {
+            instructions::propose_admin(admin.pubkey(), new_admin)
+        }
+        AdminCommand::AcceptAdmin => instructions::accept_admin(admin.pubkey()),
+        AdminCommand::SetGlobalMode { mode } => {
+            instructions::set_global_trading_mode(admin.pubkey(), mode.into())
+        }
+        AdminCommand::SetMarketMode { market, mode } => {
+            let (address, _) = client.market(&market)?;
+            instructions::set_market_trading_mode(admin.pubkey(), address, mode.into())
+        }
+        AdminCommand::MigrateMintAuthority { market } => {
+            let (address, market) = client.market(&market)?;
+            instructions::migrate_mint_authority(admin.pubkey(), address, market.mint)
+        }
+    };
+
+    client.execute(&admin, &[ix], &[])
+}
+
+fn quote(
+    market: &program::Market,
+    config: &program::GlobalConfig,
+    side: Side,
+    amount: u64,
+) -> Result<math::Quote> {
+    if amount == 0 {
+        bail!("amount must be greater than zero");
+    }
+    let quote = match side {
+        Side::Buy => {
+            let available = market.curve_tokens_left();
+            if amount > available {
+                bail!("only {available} tokens are left to buy");
+            }
+            state::quote_buy(market, config, amount)
+        }
+        Side::Sell => {
+            if amount > market.circulating_supply {
+                bail!(
+                    "only {} tokens are in circulation",
+                    market.circulating_supply
+                );
+            }
+            state::quote_sell(market, config, amount)
+        }
+    };
+
+    quote.map_err(|e| anyhow!("pricing the trade: {e}"))
+}
+
+/// Widens a quoted amount by `slippage_bps`: up for a maximum cost, down for a minimum payout.
+pub(crate) fn with_slippage(lamports: u64, slippage_bps: u64, up: bool) -> u64 {
+    let bps = math::BPS_DENOMINATOR as u128;
+    let factor = if up {
+        bps + slippage_bps as u128
+    } else {
+        bps.saturating_sub(slippage_bps as u128)
+    };
+    u64::try_from(lamports as u128 * factor / bps).unwrap_or(u64::MAX)
+}
+
+fn merge_fees(config: &program::GlobalConfig, fees: FeeArgs) -> ConfigParams {
+    ConfigParams {
+        fee_wallet: fees.fee_wallet.unwrap_or(config.fee_wallet),
+        creation_fee: fees.creation_fee.unwrap_or(config.creation_fee),
+        transaction_fee_bps: fees
+            .transaction_fee_bps
+            .unwrap_or(config.transaction_fee_bps),
+        platform_fee_share_bps: fees
+            .platform_fee_share_bps
+            .unwrap_or(config.platform_fee_share_bps),
+        creator_fee_share_bps: fees
+            .creator_fee_share_bps
+            .unwrap_or(config.creator_fee_share_bps),
+        min_fee: fees.min_fee.unwrap_or(config.min_fee),
+    }
+}
+
+impl From<Curve> for CurveType {
+    fn from(curve: Curve) -> Self {
+        match curve {
+            Curve::Exponential => CurveType::Exponential,
+            Curve::Linear => CurveType::Linear,
+            Curve::Sigmoid => CurveType::Sigmoid,
+            Curve::ConstantProduct => CurveType::ConstantProduct,
+        }
+    }
+}
+
+impl From<Mode> for TradingMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Active => TradingMode::Active,
+            Mode::SellOnly => TradingMode::SellOnly,
+            Mode::Paused => TradingMode::Paused,
+        }
+    }
+}
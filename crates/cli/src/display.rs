@@ -0,0 +1,265 @@
+use anchor_lang::prelude::Pubkey;
+use celebrity_exchange_sdk::events::ExchangeEvent;
+use celebrity_exchange_sdk::math;
+use celebrity_exchange_sdk::program::{
+    GlobalConfig, GraduationThreshold, Market, MarketStatus, SolvencyReport, TradeKind,
+    VestingSchedule, CLOSE_GRACE_PERIOD,
+};
+
+use crate::{PoolState, Side};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+/// `lamports` as SOL with all nine decimals, so nothing is rounded away.
+pub fn sol(lamports: u64) -> String {
+    format!(
+        "{}.{:09} SOL",
+        lamports / LAMPORTS_PER_SOL,
+        lamports % LAMPORTS_PER_SOL
+    )
+}
+
+pub fn quote(side: Side, quote: &math::Quote) {
+    let (cost_label, net_label) = match side {
+        Side::Buy => ("Curve cost:  ", "Total charge:"),
+        Side::Sell => ("Curve value: ", "You receive: "),
+    };
+    println!("Tokens:       {}", quote.tokens);
+    println!("{cost_label} {}", sol(quote.total_cost));
+    println!("Platform fee: {}", sol(quote.platform_fee));
+    println!("Creator fee:  {}", sol(quote.creator_fee));
+    println!("{net_label} {}", sol(quote.net_lamports));
+    println!("Price after:  {}", sol(quote.price_after));
+}
+
+pub fn swap_quote(side: Side, quote: &math::SwapQuote) {
+    match side {
+        Side::Buy => {
+            println!("You pay:      {}", sol(quote.amount_in));
+            println!("Tokens out:   {}", quote.amount_out);
+        }
+        Side::Sell => {
+            println!("Tokens in:    {}", quote.amount_in);
+            println!("You receive:  {}", sol(quote.amount_out));
+        }
+    }
+    println!("Platform fee: {}", sol(quote.platform_fee));
+    println!("Creator fee:  {}", sol(quote.creator_fee));
+}
+
+pub fn pool(state: &PoolState) {
+    let pool = &state.pool;
+    println!("Pool:             {}", state.address);
+    match pool.market {
+        Some(market) => println!("Market:           {market}"),
+        None => println!("Market:           none"),
+    }
+    println!("Mint:             {}", pool.mint);
+    println!("Creator:          {}", pool.creator);
+    println!("Token vault:      {}", pool.token_vault);
+    println!("SOL vault:        {}", pool.sol_vault);
+    println!("LP mint:          {}", pool.lp_mint);
+    println!("SOL reserve:      {}", sol(state.sol_reserve));
+    println!("Token reserve:    {}", state.token_reserve);
+    if let Some(price) = state.sol_reserve.checked_div(state.token_reserve) {
+        println!("Spot price:       {}", sol(price));
+    }
+    println!("LP supply:        {}", state.lp_supply);
+    println!("Locked liquidity: {}", pool.locked_liquidity);
+}
+
+pub fn market(address: &Pubkey, market: &Market) {
+    println!("Market:             {address}");
+    println!("Name:               {}", market.name);
+    println!("Symbol:             {}", market.symbol);
+    println!("URI:                {}", market.uri);
+    println!("Owner:              {}", market.owner);
+    println!("Mint:               {}", market.mint);
+    println!("Escrow:             {}", market.escrow);
+    println!("Treasury:           {}", market.treasury);
+    println!("Current price:      {}", sol(market.current_price));
+    println!("Total supply:       {}", market.total_supply);
+    println!("Circulating supply: {}", market.circulating_supply);
+    if market.creator_allocation > 0 {
+        println!("Creator allocation: {}", market.creator_allocation);
+    }
+    println!("Trades:             {}", market.trade_count);
+    println!("Curve:              {:?}", market.curve.curve_type);
+    println!("  Base price:       {}", sol(market.curve.base_price));
+    println!("  Max price:        {}", sol(market.curve.max_price));
+    println!(
+        "  Growth:           {} / {}",
+        market.curve.k_factor, market.curve.scale_factor
+    );
+    println!("Trading mode:       {:?}", market.trading_mode);
+    println!("Metadata locked:    {}", market.metadata_locked);
+    match market.graduation {
+        GraduationThreshold::SoldSupply(supply) => {
+            println!("Graduates at:       {supply} tokens sold")
+        }
+        GraduationThreshold::MarketCap(cap) => {
+            println!("Graduates at:       {} market cap", sol(cap))
+        }
+    }
+    println!("Status:             {:?}", market.status);
+    if market.status == MarketStatus::Closing {
+        println!(
+            "Redeemable until:   {} (unix time)",
+            market.closing_started_at + CLOSE_GRACE_PERIOD
+        );
+    }
+}
+
+pub fn vesting_schedule(market: &Market, schedule: &VestingSchedule, now: i64) {
+    println!("Market:      {}", schedule.market);
+    println!("Symbol:      {}", market.symbol);
+    println!("Beneficiary: {}", schedule.beneficiary);
+    println!("Vault:       {}", schedule.vault);
+    println!("Allocation:  {}", schedule.total_amount);
+    println!("Claimed:     {}", schedule.claimed_amount);
+    let vested = schedule.vested(now).saturating_sub(schedule.claimed_amount);
+    if market.status == MarketStatus::Graduated {
+        println!("Claimable:   {vested}");
+    } else {
+        println!("Claimable:   0 ({vested} vested, released once the market graduates)");
+    }
+    println!("Starts:      {} (unix time)", schedule.start_time);
+    println!("Cliff:       {} (unix time)", schedule.cliff_time);
+    println!("Ends:        {} (unix time)", schedule.end_time);
+}
+
+pub fn solvency(address: &Pubkey, report: &SolvencyReport) {
+    println!("Market:        {address}");
+    println!("Treasury:      {}", sol(report.treasury_balance));
+    println!("Rent reserve:  {}", sol(report.rent_reserve));
+    println!("Curve reserve: {}", sol(report.curve_reserve));
+    println!("Shortfall:     {}", sol(report.shortfall));
+}
+
+pub fn market_table(markets: &[(Pubkey, Market)]) {
+    println!(
+        "{:<44}  {:<10}  {:>22}  {:>25}  {:<15}  MODE",
+        "MARKET", "SYMBOL", "PRICE", "CIRCULATING / TOTAL", "CURVE"
+    );
+    for (address, market) in markets {
+        println!(
+            "{:<44}  {:<10}  {:>22}  {:>25}  {:<15}  {:?}",
+            address.to_string(),
+            market.symbol,
+            sol(market.current_price),
+            format!("{} / {}", market.circulating_supply, market.total_supply),
+            format!("{:?}", market.curve.curve_type),
+            market.trading_mode,
+        );
+    }
+    println!("{} market(s)", markets.len());
+}
+
+pub fn config(address: &Pubkey, config: &GlobalConfig) {
+    println!("Config:             {address}");
+    println!("Admin:              {}", config.admin);
+    match config.pending_admin {
+        Some(pending) => println!("Pending admin:      {pending}"),
+        None => println!("Pending admin:      none"),
+    }
+    println!("Fee wallet:         {}", config.fee_wallet);
+    println!("Creation fee:       {}", sol(config.creation_fee));
+    println!("Transaction fee:    {} bps", config.transaction_fee_bps);
+    println!("  Platform share:   {} bps", config.platform_fee_share_bps);
+    println!("  Creator share:    {} bps", config.creator_fee_share_bps);
+    println!("  Minimum:          {}", sol(config.min_fee));
+    println!("Trading mode:       {:?}", config.trading_mode);
+}
+
+pub fn event(event: &ExchangeEvent) {
+    match event {
+        ExchangeEvent::MarketCreated(event) => println!(
+            "Event: MarketCreated {} ({}) market {} mint {} supply {} at {}",
+            event.name,
+            event.symbol,
+            event.market,
+            event.mint,
+            event.initial_supply,
+            sol(event.initial_price)
+        ),
+        ExchangeEvent::TradeExecuted(event) => {
+            let kind = match event.kind {
+                TradeKind::Buy => "buy",
+                TradeKind::Sell => "sell",
+            };
+            println!(
+                "Event: TradeExecuted #{} {kind} of {} tokens on {} for {} (fees {} + {}), price now {}, supply {} -> {}",
+                event.sequence,
+                event.tokens,
+                event.market,
+                sol(event.total_cost),
+                sol(event.platform_fee),
+                sol(event.creator_fee),
+                sol(event.price),
+                event.circulating_supply_before,
+                event.circulating_supply_after
+            );
+        }
+        ExchangeEvent::MarketGraduated(event) => println!(
+            "Event: MarketGraduated #{} {} into pool {} with {} and {} tokens",
+            event.sequence,
+            event.market,
+            event.pool,
+            sol(event.sol_reserve),
+            event.token_reserve
+        ),
+        ExchangeEvent::MarketClosing(event) => println!(
+            "Event: MarketClosing #{} {} by {}, {} tokens to redeem against {}",
+            event.sequence,
+            event.market,
+            event.initiated_by,
+            event.circulating_supply,
+            sol(event.treasury_balance)
+        ),
+        ExchangeEvent::TokensRedeemed(event) => println!(
+            "Event: TokensRedeemed #{} {} tokens on {} for {}, supply {} -> {}",
+            event.sequence,
+            event.tokens,
+            event.market,
+            sol(event.lamports),
+            event.circulating_supply_before,
+            event.circulating_supply_after
+        ),
+        ExchangeEvent::MarketClosed(event) => println!(
+            "Event: MarketClosed #{} {}, {} returned to the owner, {} tokens unredeemed",
+            event.sequence,
+            event.market,
+            sol(event.lamports_returned),
+            event.unredeemed_supply
+        ),
+        ExchangeEvent::MarketPaused(event) => match (event.market, event.sequence) {
+            (Some(market), Some(sequence)) => {
+                println!(
+                    "Event: MarketPaused #{sequence} {market} ({:?})",
+                    event.mode
+                )
+            }
+            _ => println!("Event: MarketPaused every market ({:?})", event.mode),
+        },
+        ExchangeEvent::MarketResumed(event) => match (event.market, event.sequence) {
+            (Some(market), Some(sequence)) => println!("Event: MarketResumed #{sequence} {market}"),
+            _ => println!("Event: MarketResumed every market"),
+        },
+        ExchangeEvent::MintAuthorityMigrated(event) => println!(
+            "Event: MintAuthorityMigrated #{} {} mint {} to authority {}, treasury {}",
+            event.sequence,
+            event.market,
+            event.mint,
+            event.mint_authority,
+            sol(event.treasury_balance)
+        ),
+        ExchangeEvent::MarketMetadataUpdated(event) => println!(
+            "Event: MarketMetadataUpdated #{} {} to {} ({}){}",
+            event.sequence,
+            event.market,
+            event.name,
+            event.symbol,
+            if event.locked { ", locked" } else { "" }
+        ),
+    }
+}
@@ -0,0 +1,319 @@
+//! `celebrity-exchange`: create, trade, inspect and administer markets from a terminal.
+//!
+//! Every command that writes builds its instructions with `celebrity-exchange-sdk`, signs
+//! with a keypair file and either sends the transaction or, with `--dry-run`, only
+//! simulates it and prints the logs and events it would produce.
+
+mod client;
+mod commands;
+mod display;
+
+use anchor_lang::prelude::Pubkey;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+pub use client::{Client, PoolState};
+
+pub const DEFAULT_RPC_URL: &str = "https://api.devnet.solana.com";
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "celebrity-exchange",
+    version,
+    about = "Operate celebrity exchange markets"
+)]
+pub struct Cli {
+    /// JSON RPC endpoint of the cluster
+    #[arg(
+        short = 'u',
+        long,
+        global = true,
+        env = "CELEBRITY_EXCHANGE_RPC_URL",
+        default_value = DEFAULT_RPC_URL
+    )]
+    pub rpc_url: String,
+
+    /// Keypair file that signs and pays [default: ~/.config/solana/id.json]
+    #[arg(short = 'k', long, global = true, env = "CELEBRITY_EXCHANGE_KEYPAIR")]
+    pub keypair: Option<PathBuf>,
+
+    /// Simulate transactions and print their logs instead of sending them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a market around a freshly generated mint
+    Create(CreateArgs),
+    /// Buy tokens, paying at most the quoted charge plus slippage
+    Buy(TradeArgs),
+    /// Sell tokens, receiving at least the quoted proceeds minus slippage
+    Sell(TradeArgs),
+    /// Price a trade against the market as it stands, without sending anything
+    Quote {
+        side: Side,
+        /// Market or mint address
+        market: Pubkey,
+        amount: u64,
+    },
+    /// List every market of the program
+    List,
+    /// Print a decoded market account
+    Inspect {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Check that a market's treasury covers rent and buying back every circulating token
+    Verify {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Print the global config
+    Config,
+    /// Move a market that reached its graduation threshold into its pool
+    Graduate {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Print a market's creator vesting schedule and what is claimable now
+    Vesting {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Claim the vested part of your creator allocation
+    Claim {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Freeze a market for good so holders can redeem (market owner or admin)
+    Close {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Burn tokens of a closing market for their share of its treasury
+    Redeem {
+        /// Market or mint address
+        market: Pubkey,
+        /// Number of tokens
+        amount: u64,
+    },
+    /// Close a market's escrow after the grace period, and the rest once every token is redeemed
+    FinalizeClose {
+        /// Market or mint address
+        market: Pubkey,
+    },
+    /// Open, trade in and provide liquidity to constant-product pools
+    #[command(subcommand)]
+    Pool(PoolCommand),
+    /// Change a market's name, symbol or URI (market owner only)
+    UpdateMetadata(UpdateMetadataArgs),
+    /// Instructions restricted to the config admin
+    #[command(subcommand)]
+    Admin(AdminCommand),
+}
+
+#[derive(Args, Debug)]
+pub struct CreateArgs {
+    #[arg(long)]
+    pub name: String,
+    #[arg(long)]
+    pub symbol: String,
+    /// Off-chain metadata JSON
+    #[arg(long)]
+    pub uri: String,
+    /// Starting price per token in lamports
+    #[arg(long)]
+    pub initial_price: u64,
+    /// Tokens minted into escrow
+    #[arg(long)]
+    pub supply: u64,
+    #[arg(long, value_enum, default_value_t = Curve::Exponential)]
+    pub curve: Curve,
+    /// Price cap in lamports [default: initial price × 2^20]
+    #[arg(long)]
+    pub max_price: Option<u64>,
+    /// Growth rate numerator; the curve climbs at k / scale per token [default: 5]
+    #[arg(
+        long,
+        requires = "growth_scale",
+        conflicts_with = "virtual_token_reserve"
+    )]
+    pub growth_k: Option<u64>,
+    /// Growth rate denominator [default: 10000]
+    #[arg(long, requires = "growth_k")]
+    pub growth_scale: Option<u64>,
+    /// Tokens in the virtual pool of a constant-product curve [default: 2000]
+    #[arg(long)]
+    pub virtual_token_reserve: Option<u64>,
+    /// Graduate to a pool once this many tokens have sold [default: 80% of the supply]
+    #[arg(long, conflicts_with = "graduate_at_market_cap")]
+    pub graduate_at_supply: Option<u64>,
+    /// Graduate to a pool once price × circulating supply reaches this many lamports
+    #[arg(long)]
+    pub graduate_at_market_cap: Option<u64>,
+    /// Tokens of the supply reserved for you and released on a vesting schedule
+    #[arg(long, requires = "vesting_days")]
+    pub creator_allocation: Option<u64>,
+    /// Days before any of the allocation is released
+    #[arg(long, default_value_t = 0, requires = "creator_allocation")]
+    pub cliff_days: i64,
+    /// Days until the whole allocation is released
+    #[arg(long, requires = "creator_allocation")]
+    pub vesting_days: Option<i64>,
+    /// Keypair file for the new mint [default: a fresh keypair]
+    #[arg(long)]
+    pub mint_keypair: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct TradeArgs {
+    /// Market or mint address
+    pub market: Pubkey,
+    /// Number of tokens
+    pub amount: u64,
+    /// Tolerated price movement between quote and execution, in basis points
+    #[arg(long, default_value_t = 100)]
+    pub slippage_bps: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct UpdateMetadataArgs {
+    /// Market or mint address
+    pub market: Pubkey,
+    #[arg(long)]
+    pub name: String,
+    #[arg(long)]
+    pub symbol: String,
+    #[arg(long)]
+    pub uri: String,
+    /// Make the metadata immutable from now on
+    #[arg(long)]
+    pub lock: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PoolCommand {
+    /// Open a pool for a token that backs no market, seeded from your wallet
+    Init {
+        mint: Pubkey,
+        /// Lamports to deposit
+        #[arg(long)]
+        sol: u64,
+        /// Tokens to deposit
+        #[arg(long)]
+        tokens: u64,
+    },
+    /// Print a pool and its reserves
+    Inspect {
+        /// Pool or mint address
+        pool: Pubkey,
+    },
+    /// Swap lamports for tokens or tokens for lamports
+    Swap {
+        side: Side,
+        /// Pool or mint address
+        pool: Pubkey,
+        /// Lamports to spend on a buy, tokens to sell on a sell
+        amount: u64,
+        /// Tolerated price movement between quote and execution, in basis points
+        #[arg(long, default_value_t = 100)]
+        slippage_bps: u64,
+    },
+    /// Deposit lamports and tokens in the pool's ratio for LP tokens
+    AddLiquidity(LiquidityArgs),
+    /// Burn LP tokens for their share of the reserves
+    RemoveLiquidity(LiquidityArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct LiquidityArgs {
+    /// Pool or mint address
+    pub pool: Pubkey,
+    /// LP tokens to mint or burn
+    pub liquidity: u64,
+    /// Tolerated price movement between quote and execution, in basis points
+    #[arg(long, default_value_t = 100)]
+    pub slippage_bps: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AdminCommand {
+    /// Create the global config (program upgrade authority only)
+    InitConfig {
+        /// Admin of the new config [default: the signer]
+        #[arg(long)]
+        admin: Option<Pubkey>,
+        #[command(flatten)]
+        fees: FeeArgs,
+    },
+    /// Change fee settings; anything left out keeps its current value
+    UpdateConfig {
+        #[command(flatten)]
+        fees: FeeArgs,
+    },
+    /// Nominate a new admin, who then runs `accept-admin`
+    ProposeAdmin { new_admin: Pubkey },
+    /// Take over as admin after being proposed
+    AcceptAdmin,
+    /// Restrict trading on every market
+    SetGlobalMode { mode: Mode },
+    /// Restrict trading on one market
+    SetMarketMode {
+        /// Market or mint address
+        market: Pubkey,
+        mode: Mode,
+    },
+    /// Hand a legacy market's mint to its per-market authority, topping up its treasury
+    MigrateMintAuthority {
+        /// Market or mint address
+        market: Pubkey,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct FeeArgs {
+    #[arg(long)]
+    pub fee_wallet: Option<Pubkey>,
+    /// Lamports charged to create a market
+    #[arg(long)]
+    pub creation_fee: Option<u64>,
+    #[arg(long)]
+    pub transaction_fee_bps: Option<u64>,
+    #[arg(long)]
+    pub platform_fee_share_bps: Option<u64>,
+    #[arg(long)]
+    pub creator_fee_share_bps: Option<u64>,
+    /// Lamports every trade pays at least while fees are on [init default: 5000]
+    #[arg(long)]
+    pub min_fee: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Exponential,
+    Linear,
+    Sigmoid,
+    ConstantProduct,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Active,
+    SellOnly,
+    Paused,
+}
+
+pub fn run(cli: Cli) -> anyhow::Result<()> {
+    let client = Client::new(&cli.rpc_url, cli.keypair.as_deref(), cli.dry_run)?;
+    commands::run(&client, cli.command)
+}
@@ -0,0 +1,5 @@
+use clap::Parser;
+
+fn main() -> anyhow::Result<()> {
+    celebrity_exchange_cli::run(celebrity_exchange_cli::Cli::parse())
+}
@@ -0,0 +1,227 @@
+use anchor_lang::prelude::Pubkey;
+use celebrity_exchange_cli::{
+    AdminCommand, Cli, Command, Curve, Mode, PoolCommand, Side, DEFAULT_RPC_URL,
+};
+use clap::{CommandFactory, Parser};
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("celebrity-exchange").chain(args.iter().copied())).unwrap()
+}
+
+#[test]
+fn command_definitions_are_consistent() {
+    Cli::command().debug_assert();
+}
+
+#[test]
+fn global_options_apply_after_the_subcommand() {
+    let mint = Pubkey::new_unique();
+    let cli = parse(&[
+        "buy",
+        &mint.to_string(),
+        "250",
+        "--slippage-bps",
+        "50",
+        "--dry-run",
+        "-u",
+        "http://127.0.0.1:8899",
+        "--keypair",
+        "ops.json",
+    ]);
+
+    assert!(cli.dry_run);
+    assert_eq!(cli.rpc_url, "http://127.0.0.1:8899");
+    assert_eq!(cli.keypair, Some(PathBuf::from("ops.json")));
+    match cli.command {
+        Command::Buy(args) => {
+            assert_eq!(args.market, mint);
+            assert_eq!(args.amount, 250);
+            assert_eq!(args.slippage_bps, 50);
+        }
+        other => panic!("parsed {other:?}"),
+    }
+}
+
+#[test]
+fn parses_create_quote_and_admin_commands() {
+    let cli = parse(&[
+        "create",
+        "--name",
+        "Celebrity",
+        "--symbol",
+        "CELEB",
+        "--uri",
+        "https://example.com/celebrity.json",
+        "--initial-price",
+        "1000000",
+        "--supply",
+        "1000000",
+        "--curve",
+        "constant-product",
+    ]);
+    assert!(!cli.dry_run);
+    assert_eq!(cli.rpc_url, DEFAULT_RPC_URL);
+    match cli.command {
+        Command::Create(args) => {
+            assert_eq!(args.curve, Curve::ConstantProduct);
+            assert_eq!(args.max_price, None);
+            assert_eq!(args.mint_keypair, None);
+        }
+        other => panic!("parsed {other:?}"),
+    }
+
+    let market = Pubkey::new_unique();
+    let cli = parse(&["quote", "sell", &market.to_string(), "10"]);
+    assert!(matches!(
+        cli.command,
+        Command::Quote { side: Side::Sell, market: m, amount: 10 } if m == market
+    ));
+
+    let cli = parse(&["admin", "set-market-mode", &market.to_string(), "sell-only"]);
+    assert!(matches!(
+        cli.command,
+        Command::Admin(AdminCommand::SetMarketMode { market: m, mode: Mode::SellOnly }) if m == market
+    ));
+}
+
+#[test]
+fn parses_pool_commands() {
+    let mint = Pubkey::new_unique();
+    let cli = parse(&[
+        "pool",
+        "swap",
+        "sell",
+        &mint.to_string(),
+        "250",
+        "--slippage-bps",
+        "50",
+    ]);
+    assert!(matches!(
+        cli.command,
+        Command::Pool(PoolCommand::Swap { side: Side::Sell, pool, amount: 250, slippage_bps: 50 }) if pool == mint
+    ));
+
+    let cli = parse(&["pool", "remove-liquidity", &mint.to_string(), "1000"]);
+    match cli.command {
+        Command::Pool(PoolCommand::RemoveLiquidity(args)) => {
+            assert_eq!(args.pool, mint);
+            assert_eq!(args.liquidity, 1_000);
+            assert_eq!(args.slippage_bps, 100);
+        }
+        other => panic!("parsed {other:?}"),
+    }
+
+    let cli = parse(&[
+        "pool",
+        "init",
+        &mint.to_string(),
+        "--sol",
+        "1000000000",
+        "--tokens",
+        "500",
+    ]);
+    assert!(matches!(
+        cli.command,
+        Command::Pool(PoolCommand::Init { mint: m, sol: 1_000_000_000, tokens: 500 }) if m == mint
+    ));
+}
+
+#[test]
+fn parses_creator_allocation() {
+    let create = [
+        "create",
+        "--name",
+        "Celebrity",
+        "--symbol",
+        "CELEB",
+        "--uri",
+        "https://example.com/celebrity.json",
+        "--initial-price",
+        "1000000",
+        "--supply",
+        "1000000",
+        "--creator-allocation",
+        "50000",
+    ];
+    let cli = parse(
+        &[
+            &create[..],
+            &["--cliff-days", "90", "--vesting-days", "365"],
+        ]
+        .concat(),
+    );
+    match cli.command {
+        Command::Create(args) => {
+            assert_eq!(args.creator_allocation, Some(50_000));
+            assert_eq!(args.cliff_days, 90);
+            assert_eq!(args.vesting_days, Some(365));
+        }
+        other => panic!("parsed {other:?}"),
+    }
+
+    // An allocation needs a schedule to vest on
+    let args = std::iter::once("celebrity-exchange").chain(create);
+    assert!(Cli::try_parse_from(args).is_err());
+}
+
+#[test]
+fn parses_curve_growth() {
+    let create = [
+        "create",
+        "--name",
+        "Celebrity",
+        "--symbol",
+        "CELEB",
+        "--uri",
+        "https://example.com/celebrity.json",
+        "--initial-price",
+        "1000000",
+        "--supply",
+        "1000000",
+    ];
+    let cli = parse(
+        &[
+            &create[..],
+            &["--growth-k", "1", "--growth-scale", "500000"],
+        ]
+        .concat(),
+    );
+    match cli.command {
+        Command::Create(args) => {
+            assert_eq!(args.growth_k, Some(1));
+            assert_eq!(args.growth_scale, Some(500_000));
+            assert_eq!(args.virtual_token_reserve, None);
+        }
+        other => panic!("parsed {other:?}"),
+    }
+
+    // A rate needs both halves, and a constant-product pool takes one or the other
+    for extra in [
+        &["--growth-k", "1"][..],
+        &[
+            "--growth-k",
+            "1",
+            "--growth-scale",
+            "2",
+            "--virtual-token-reserve",
+            "2000000",
+        ],
+    ] {
+        let args = std::iter::once("celebrity-exchange").chain(create.iter().chain(extra).copied());
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+}
+
+#[test]
+fn rejects_malformed_arguments() {
+    for args in [
+        &["buy", "not-a-pubkey", "1"][..],
+        &["sell", &Pubkey::new_unique().to_string(), "-5"],
+        &["admin", "set-global-mode", "halted"],
+        &["create", "--name", "Celebrity"],
+    ] {
+        let args = std::iter::once("celebrity-exchange").chain(args.iter().copied());
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! Replays decoded events into per-market state.
+
+use anchor_lang::prelude::Pubkey;
+use celebrity_exchange_sdk::events::{parse_logs, ExchangeEvent};
+use celebrity_exchange_sdk::program::{CurveType, Market, MarketCreated, TradeExecuted, TradeKind};
+use std::collections::HashMap;
+
+/// A transaction touching the program, as fetched from the cluster.
+#[derive(Clone, Debug)]
+pub struct IndexedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    /// Failed transactions still log the events emitted before they failed
+    pub failed: bool,
+    pub logs: Vec<String>,
+}
+
+/// A market as rebuilt from its events.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarketState {
+    pub market: Pubkey,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub curve_type: CurveType,
+    pub base_price: u64,
+    pub k_factor: u64,
+    pub scale_factor: u64,
+    pub max_price: u64,
+    pub total_supply: u64,
+    pub circulating_supply: u64,
+    pub current_price: u64,
+    /// Sequence number of the last event replayed
+    pub trade_count: u64,
+    pub treasury_balance: u64,
+    /// Curve cost of every buy plus curve value of every sale, in lamports
+    pub volume: u64,
+    pub platform_fees: u64,
+    pub creator_fees: u64,
+    pub created_slot: u64,
+    pub updated_slot: u64,
+}
+
+impl MarketState {
+    fn created(event: &MarketCreated, slot: u64) -> Self {
+        Self {
+            market: event.market,
+            mint: event.mint,
+            owner: event.owner,
+            name: event.name.clone(),
+            symbol: event.symbol.clone(),
+            uri: event.uri.clone(),
+            curve_type: event.curve_type,
+            base_price: event.base_price,
+            k_factor: event.k_factor,
+            scale_factor: event.scale_factor,
+            max_price: event.max_price,
+            total_supply: event.initial_supply,
+            circulating_supply: event.circulating_supply,
+            current_price: event.initial_price,
+            trade_count: event.sequence,
+            treasury_balance: event.treasury_balance,
+            volume: 0,
+            platform_fees: 0,
+            creator_fees: 0,
+            created_slot: slot,
+            updated_slot: slot,
+        }
+    }
+}
+
+/// One `TradeExecuted`, keyed by its transaction and position within it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TradeRecord {
+    pub signature: String,
+    pub event_index: u32,
+    pub slot: u64,
+    pub market: Pubkey,
+    pub kind: TradeKind,
+    pub trader: Pubkey,
+    pub tokens: u64,
+    pub price: u64,
+    pub total_cost: u64,
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    pub sequence: u64,
+    pub circulating_supply_before: u64,
+    pub circulating_supply_after: u64,
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+/// Where an event after `MarketCreated` left its market. Every one carries its sequence
+/// number; supply and treasury are `None` for events that leave them alone.
+struct Step<'a> {
+    signature: &'a str,
+    slot: u64,
+    market: Pubkey,
+    sequence: u64,
+    /// Before and after
+    circulating_supply: Option<(u64, u64)>,
+    treasury_balance: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GapKind {
+    /// A trade on a market whose `MarketCreated` was never seen
+    UnknownMarket,
+    /// The event's sequence number skipped past the next one expected
+    MissedTrades { expected: u64, received: u64 },
+    /// The supply an event started from differs from the replayed supply
+    SupplyMismatch { replayed: u64, reported: u64 },
+    /// The on-chain trade count differs from the number of trades replayed
+    TradeCountMismatch { replayed: u64, on_chain: u64 },
+}
+
+/// Evidence that events were missed, recorded instead of silently diverging.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gap {
+    pub market: Pubkey,
+    pub slot: u64,
+    pub signature: Option<String>,
+    pub kind: GapKind,
+}
+
+/// Everything one transaction changed, written to the sink in a single commit.
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    pub signature: String,
+    pub slot: u64,
+    pub markets: Vec<MarketState>,
+    pub trades: Vec<TradeRecord>,
+    pub gaps: Vec<Gap>,
+}
+
+/// Per-market state, updated event by event.
+#[derive(Default)]
+pub struct Replayer {
+    markets: HashMap<Pubkey, MarketState>,
+}
+
+impl Replayer {
+    pub fn new(markets: impl IntoIterator<Item = MarketState>) -> Self {
+        Self {
+            markets: markets.into_iter().map(|m| (m.market, m)).collect(),
+        }
+    }
+
+    pub fn market(&self, market: &Pubkey) -> Option<&MarketState> {
+        self.markets.get(market)
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = &MarketState> {
+        self.markets.values()
+    }
+
+    /// Applies every event of `transaction` and returns what changed.
+    pub fn apply(&mut self, transaction: &IndexedTransaction) -> Batch {
+        let mut batch = Batch {
+            signature: transaction.signature.clone(),
+            slot: transaction.slot,
+            ..Batch::default()
+        };
+        if transaction.failed {
+            return batch;
+        }
+
+        let mut touched = Vec::new();
+        for (index, event) in parse_logs(&transaction.logs).into_iter().enumerate() {
+            match event {
+                // A market account is only ever initialized once, so a second
+                // `MarketCreated` is this transaction being replayed again
+                ExchangeEvent::MarketCreated(event)
+                    if !self.markets.contains_key(&event.market) =>
+                {
+                    let state = MarketState::created(&event, transaction.slot);
+                    touched.push(state.market);
+                    self.markets.insert(state.market, state);
+                }
+                ExchangeEvent::MarketCreated(_) => {}
+                ExchangeEvent::TradeExecuted(event) => {
+                    let trade = trade_record(transaction, index as u32, &event);
+                    if self.apply_trade(&trade, &mut batch.gaps) {
+                        touched.push(trade.market);
+                        batch.trades.push(trade);
+                    }
+                }
+                event => {
+                    let Some(step) = lifecycle_step(transaction, &event) else {
+                        continue;
+                    };
+                    if !self.advance(&step, &mut batch.gaps) {
+                        continue;
+                    }
+                    if let (ExchangeEvent::MarketMetadataUpdated(event), Some(state)) =
+                        (&event, self.markets.get_mut(&step.market))
+                    {
+                        state.name = event.name.clone();
+                        state.symbol = event.symbol.clone();
+                        state.uri = event.uri.clone();
+                    }
+                    touched.push(step.market);
+                }
+            }
+        }
+
+        touched.sort();
+        touched.dedup();
+        batch.markets = touched
+            .iter()
+            .filter_map(|market| self.markets.get(market).cloned())
+            .collect();
+        batch
+    }
+
+    /// Applies `trade` unless its sequence number shows it was applied already.
+    fn apply_trade(&mut self, trade: &TradeRecord, gaps: &mut Vec<Gap>) -> bool {
+        let step = Step {
+            signature: &trade.signature,
+            slot: trade.slot,
+            market: trade.market,
+            sequence: trade.sequence,
+            circulating_supply: Some((
+                trade.circulating_supply_before,
+                trade.circulating_supply_after,
+            )),
+            treasury_balance: Some(trade.treasury_balance),
+        };
+        if !self.advance(&step, gaps) {
+            return false;
+        }
+
+        if let Some(state) = self.markets.get_mut(&trade.market) {
+            state.current_price = trade.price;
+            state.volume = state.volume.saturating_add(trade.total_cost);
+            state.platform_fees = state.platform_fees.saturating_add(trade.platform_fee);
+            state.creator_fees = state.creator_fees.saturating_add(trade.creator_fee);
+        }
+        true
+    }
+
+    /// Moves the market to where `step` left it, unless its sequence number shows it was
+    /// applied already. The event's own supply and sequence win over the replay, so state
+    /// resynchronizes after a gap instead of carrying the error forward.
+    fn advance(&mut self, step: &Step, gaps: &mut Vec<Gap>) -> bool {
+        let mut gap = |kind| {
+            gaps.push(Gap {
+                market: step.market,
+                slot: step.slot,
+                signature: Some(step.signature.to_string()),
+                kind,
+            })
+        };
+        let Some(state) = self.markets.get_mut(&step.market) else {
+            gap(GapKind::UnknownMarket);
+            return true;
+        };
+        if step.sequence <= state.trade_count {
+            return false;
+        }
+
+        let expected = state.trade_count + 1;
+        if step.sequence != expected {
+            gap(GapKind::MissedTrades {
+                expected,
+                received: step.sequence,
+            });
+        } else if let Some((before, _)) = step.circulating_supply {
+            if before != state.circulating_supply {
+                gap(GapKind::SupplyMismatch {
+                    replayed: state.circulating_supply,
+                    reported: before,
+                });
+            }
+        }
+
+        if let Some((_, after)) = step.circulating_supply {
+            state.circulating_supply = after;
+        }
+        if let Some(treasury_balance) = step.treasury_balance {
+            state.treasury_balance = treasury_balance;
+        }
+        state.trade_count = step.sequence;
+        state.updated_slot = step.slot;
+        true
+    }
+
+    /// Compares a replayed market with its on-chain account. The account's `trade_count`
+    /// is the sequence number of its latest trade, so a difference means trades at the
+    /// head of the stream have not been seen.
+    pub fn reconcile(&self, address: &Pubkey, on_chain: &Market, slot: u64) -> Option<Gap> {
+        let replayed = self.markets.get(address).map_or(0, |m| m.trade_count);
+        (replayed != on_chain.trade_count).then_some(Gap {
+            market: *address,
+            slot,
+            signature: None,
+            kind: GapKind::TradeCountMismatch {
+                replayed,
+                on_chain: on_chain.trade_count,
+            },
+        })
+    }
+}
+
+fn trade_record(
+    transaction: &IndexedTransaction,
+    index: u32,
+    event: &TradeExecuted,
+) -> TradeRecord {
+    TradeRecord {
+        signature: transaction.signature.clone(),
+        event_index: index,
+        slot: transaction.slot,
+        market: event.market,
+        kind: event.kind,
+        trader: event.buyer.or(event.seller).unwrap_or_default(),
+        tokens: event.tokens,
+        price: event.price,
+        total_cost: event.total_cost,
+        platform_fee: event.platform_fee,
+        creator_fee: event.creator_fee,
+        sequence: event.sequence,
+        circulating_supply_before: event.circulating_supply_before,
+        circulating_supply_after: event.circulating_supply_after,
+        treasury_balance: event.treasury_balance,
+        timestamp: event.timestamp,
+    }
+}
+
+// Graduation moves no tokens, and closing only freezes the supply; pausing, migrating
+// and renaming only take a sequence number. `None` for `MarketCreated` and
+// `TradeExecuted`, which replay on their own, and for the global trading switch
+fn lifecycle_step<'a>(
+    transaction: &'a IndexedTransaction,
+    event: &ExchangeEvent,
+) -> Option<Step<'a>> {
+    let (market, sequence, circulating_supply, treasury_balance) = match event {
+        ExchangeEvent::MarketGraduated(e) => (
+            e.market,
+            e.sequence,
+            Some((e.circulating_supply, e.circulating_supply)),
+            Some(e.treasury_balance),
+        ),
+        ExchangeEvent::MarketClosing(e) => (
+            e.market,
+            e.sequence,
+            Some((e.circulating_supply, e.circulating_supply)),
+            Some(e.treasury_balance),
+        ),
+        ExchangeEvent::TokensRedeemed(e) => (
+            e.market,
+            e.sequence,
+            Some((e.circulating_supply_before, e.circulating_supply_after)),
+            Some(e.treasury_balance),
+        ),
+        ExchangeEvent::MarketClosed(e) => (
+            e.market,
+            e.sequence,
+            Some((e.unredeemed_supply, e.unredeemed_supply)),
+            Some(e.treasury_balance),
+        ),
+        ExchangeEvent::MarketPaused(e) => (e.market?, e.sequence?, None, None),
+        ExchangeEvent::MarketResumed(e) => (e.market?, e.sequence?, None, None),
+        ExchangeEvent::MintAuthorityMigrated(e) => {
+            (e.market, e.sequence, None, Some(e.treasury_balance))
+        }
+        ExchangeEvent::MarketMetadataUpdated(e) => (e.market, e.sequence, None, None),
+        ExchangeEvent::MarketCreated(_) | ExchangeEvent::TradeExecuted(_) => return None,
+    };
+    Some(Step {
+        signature: &transaction.signature,
+        slot: transaction.slot,
+        market,
+        sequence,
+        circulating_supply,
+        treasury_balance,
+    })
+}
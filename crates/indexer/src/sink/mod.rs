@@ -0,0 +1,156 @@
+//! Where replayed state goes. SQLite backs tests and local runs, Postgres production;
+//! both keep the same five tables, prefixed so they sit beside the backend's schema:
+//!
+//! - `indexer_markets`: one row per market, overwritten as trades replay
+//! - `indexer_trades`: one row per `TradeExecuted`, keyed by signature and event index
+//! - `indexer_gaps`: every inconsistency the replay detected
+//! - `indexer_transactions`: every signature committed, so none is applied twice
+//! - `indexer_cursor`: the newest transaction committed, to resume from
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PostgresSink;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteSink;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{anyhow, bail, Result};
+use celebrity_exchange_sdk::program::{CurveType, TradeKind};
+
+use crate::replay::{Batch, Gap, GapKind, MarketState};
+
+/// The newest transaction whose batch has been committed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub signature: String,
+    pub slot: u64,
+}
+
+pub trait Sink {
+    /// Every market replayed so far, so a restarted indexer carries on where it stopped.
+    fn load_markets(&mut self) -> Result<Vec<MarketState>>;
+
+    fn cursor(&mut self) -> Result<Option<Cursor>>;
+
+    /// Writes one transaction's markets, trades and gaps and advances the cursor to it,
+    /// all or nothing. Committing the same batch twice changes nothing.
+    fn commit(&mut self, batch: &Batch) -> Result<()>;
+
+    /// Records a gap found outside any transaction, such as by reconciliation.
+    fn record_gap(&mut self, gap: &Gap) -> Result<()>;
+
+    fn gaps(&mut self) -> Result<Vec<Gap>>;
+}
+
+/// Opens the sink a database URL names: `sqlite:<path>`, `sqlite::memory:` or
+/// `postgres://...`.
+pub fn open(database_url: &str) -> Result<Box<dyn Sink>> {
+    if let Some(path) = database_url.strip_prefix("sqlite:") {
+        #[cfg(feature = "sqlite")]
+        return Ok(Box::new(match path {
+            ":memory:" => SqliteSink::open_in_memory()?,
+            path => SqliteSink::open(path.trim_start_matches("//"))?,
+        }));
+        #[cfg(not(feature = "sqlite"))]
+        bail!("built without the `sqlite` feature ({path})");
+    }
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        #[cfg(feature = "postgres")]
+        return Ok(Box::new(PostgresSink::connect(database_url)?));
+        #[cfg(not(feature = "postgres"))]
+        bail!("built without the `postgres` feature");
+    }
+
+    bail!("unsupported database URL {database_url}; expected sqlite: or postgres://")
+}
+
+// Both databases store integers as signed 64-bit
+pub(crate) fn to_db(value: u64) -> Result<i64> {
+    i64::try_from(value).map_err(|_| anyhow!("{value} does not fit a BIGINT column"))
+}
+
+pub(crate) fn from_db(value: i64) -> Result<u64> {
+    u64::try_from(value).map_err(|_| anyhow!("negative value {value} in an unsigned column"))
+}
+
+pub(crate) fn parse_pubkey(value: &str) -> Result<Pubkey> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid public key {value}"))
+}
+
+pub(crate) fn curve_type_name(curve_type: CurveType) -> &'static str {
+    match curve_type {
+        CurveType::Exponential => "exponential",
+        CurveType::Linear => "linear",
+        CurveType::Sigmoid => "sigmoid",
+        CurveType::ConstantProduct => "constant_product",
+    }
+}
+
+pub(crate) fn parse_curve_type(name: &str) -> Result<CurveType> {
+    Ok(match name {
+        "exponential" => CurveType::Exponential,
+        "linear" => CurveType::Linear,
+        "sigmoid" => CurveType::Sigmoid,
+        "constant_product" => CurveType::ConstantProduct,
+        other => bail!("unknown curve type {other}"),
+    })
+}
+
+pub(crate) fn trade_kind_name(kind: TradeKind) -> &'static str {
+    match kind {
+        TradeKind::Buy => "buy",
+        TradeKind::Sell => "sell",
+    }
+}
+
+/// A gap kind as its `kind` column and the two values stored beside it.
+pub(crate) fn gap_columns(kind: &GapKind) -> Result<(&'static str, Option<i64>, Option<i64>)> {
+    Ok(match *kind {
+        GapKind::UnknownMarket => ("unknown_market", None, None),
+        GapKind::MissedTrades { expected, received } => (
+            "missed_trades",
+            Some(to_db(expected)?),
+            Some(to_db(received)?),
+        ),
+        GapKind::SupplyMismatch { replayed, reported } => (
+            "supply_mismatch",
+            Some(to_db(replayed)?),
+            Some(to_db(reported)?),
+        ),
+        GapKind::TradeCountMismatch { replayed, on_chain } => (
+            "trade_count_mismatch",
+            Some(to_db(replayed)?),
+            Some(to_db(on_chain)?),
+        ),
+    })
+}
+
+pub(crate) fn parse_gap_kind(
+    kind: &str,
+    first: Option<i64>,
+    second: Option<i64>,
+) -> Result<GapKind> {
+    let value = |v: Option<i64>| from_db(v.ok_or_else(|| anyhow!("{kind} gap without values"))?);
+    Ok(match kind {
+        "unknown_market" => GapKind::UnknownMarket,
+        "missed_trades" => GapKind::MissedTrades {
+            expected: value(first)?,
+            received: value(second)?,
+        },
+        "supply_mismatch" => GapKind::SupplyMismatch {
+            replayed: value(first)?,
+            reported: value(second)?,
+        },
+        "trade_count_mismatch" => GapKind::TradeCountMismatch {
+            replayed: value(first)?,
+            on_chain: value(second)?,
+        },
+        other => bail!("unknown gap kind {other}"),
+    })
+}
@@ -0,0 +1,274 @@
+use anyhow::Result;
+use postgres::{Client, GenericClient, NoTls, Row};
+
+use super::{
+    curve_type_name, from_db, gap_columns, parse_curve_type, parse_gap_kind, parse_pubkey, to_db,
+    trade_kind_name, Cursor, Sink,
+};
+use crate::replay::{Batch, Gap, MarketState};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS indexer_markets (
+    market             TEXT PRIMARY KEY,
+    mint               TEXT NOT NULL,
+    owner              TEXT NOT NULL,
+    name               TEXT NOT NULL,
+    symbol             TEXT NOT NULL,
+    uri                TEXT NOT NULL,
+    curve_type         TEXT NOT NULL,
+    base_price         BIGINT NOT NULL,
+    k_factor           BIGINT NOT NULL,
+    scale_factor       BIGINT NOT NULL,
+    max_price          BIGINT NOT NULL,
+    total_supply       BIGINT NOT NULL,
+    circulating_supply BIGINT NOT NULL,
+    current_price      BIGINT NOT NULL,
+    trade_count        BIGINT NOT NULL,
+    volume             BIGINT NOT NULL,
+    platform_fees      BIGINT NOT NULL,
+    creator_fees       BIGINT NOT NULL,
+    created_slot       BIGINT NOT NULL,
+    updated_slot       BIGINT NOT NULL,
+    treasury_balance   BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS indexer_trades (
+    signature                 TEXT NOT NULL,
+    event_index               BIGINT NOT NULL,
+    slot                      BIGINT NOT NULL,
+    market                    TEXT NOT NULL,
+    kind                      TEXT NOT NULL,
+    trader                    TEXT NOT NULL,
+    tokens                    BIGINT NOT NULL,
+    price                     BIGINT NOT NULL,
+    total_cost                BIGINT NOT NULL,
+    platform_fee              BIGINT NOT NULL,
+    creator_fee               BIGINT NOT NULL,
+    sequence                  BIGINT NOT NULL,
+    circulating_supply_before BIGINT NOT NULL,
+    circulating_supply_after  BIGINT NOT NULL,
+    treasury_balance          BIGINT NOT NULL,
+    timestamp                 BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS indexer_trades_sequence ON indexer_trades (market, sequence);
+CREATE TABLE IF NOT EXISTS indexer_gaps (
+    id        BIGSERIAL PRIMARY KEY,
+    market    TEXT NOT NULL,
+    slot      BIGINT NOT NULL,
+    signature TEXT,
+    kind      TEXT NOT NULL,
+    first     BIGINT,
+    second    BIGINT
+);
+CREATE TABLE IF NOT EXISTS indexer_transactions (
+    signature TEXT PRIMARY KEY,
+    slot      BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS indexer_cursor (
+    id        INTEGER PRIMARY KEY CHECK (id = 1),
+    signature TEXT NOT NULL,
+    slot      BIGINT NOT NULL
+);
+";
+
+pub struct PostgresSink {
+    client: Client,
+}
+
+impl PostgresSink {
+    pub fn connect(url: &str) -> Result<Self> {
+        let mut client = Client::connect(url, NoTls)?;
+        client.batch_execute(SCHEMA)?;
+        Ok(Self { client })
+    }
+}
+
+fn market_from_row(row: &Row) -> Result<MarketState> {
+    let int = |index: usize| -> Result<u64> { from_db(row.try_get(index)?) };
+    Ok(MarketState {
+        market: parse_pubkey(row.try_get(0)?)?,
+        mint: parse_pubkey(row.try_get(1)?)?,
+        owner: parse_pubkey(row.try_get(2)?)?,
+        name: row.try_get(3)?,
+        symbol: row.try_get(4)?,
+        uri: row.try_get(5)?,
+        curve_type: parse_curve_type(row.try_get(6)?)?,
+        base_price: int(7)?,
+        k_factor: int(8)?,
+        scale_factor: int(9)?,
+        max_price: int(10)?,
+        total_supply: int(11)?,
+        circulating_supply: int(12)?,
+        current_price: int(13)?,
+        trade_count: int(14)?,
+        volume: int(15)?,
+        platform_fees: int(16)?,
+        creator_fees: int(17)?,
+        created_slot: int(18)?,
+        updated_slot: int(19)?,
+        treasury_balance: int(20)?,
+    })
+}
+
+fn insert_gap(client: &mut impl GenericClient, gap: &Gap) -> Result<()> {
+    let (kind, first, second) = gap_columns(&gap.kind)?;
+    client.execute(
+        "INSERT INTO indexer_gaps (market, slot, signature, kind, first, second)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        &[
+            &gap.market.to_string(),
+            &to_db(gap.slot)?,
+            &gap.signature,
+            &kind,
+            &first,
+            &second,
+        ],
+    )?;
+    Ok(())
+}
+
+impl Sink for PostgresSink {
+    fn load_markets(&mut self) -> Result<Vec<MarketState>> {
+        self.client
+            .query(
+                "SELECT market, mint, owner, name, symbol, uri, curve_type, base_price, k_factor,
+                        scale_factor, max_price, total_supply, circulating_supply, current_price,
+                        trade_count, volume, platform_fees, creator_fees, created_slot, updated_slot,
+                    treasury_balance
+                 FROM indexer_markets ORDER BY created_slot, market",
+                &[],
+            )?
+            .iter()
+            .map(market_from_row)
+            .collect()
+    }
+
+    fn cursor(&mut self) -> Result<Option<Cursor>> {
+        self.client
+            .query_opt(
+                "SELECT signature, slot FROM indexer_cursor WHERE id = 1",
+                &[],
+            )?
+            .map(|row| {
+                Ok(Cursor {
+                    signature: row.try_get(0)?,
+                    slot: from_db(row.try_get(1)?)?,
+                })
+            })
+            .transpose()
+    }
+
+    fn commit(&mut self, batch: &Batch) -> Result<()> {
+        let mut transaction = self.client.transaction()?;
+        // A transaction seen before has already been written in full
+        let first_time = transaction.execute(
+            "INSERT INTO indexer_transactions (signature, slot) VALUES ($1, $2)
+             ON CONFLICT (signature) DO NOTHING",
+            &[&batch.signature, &to_db(batch.slot)?],
+        )? == 1;
+        if !first_time {
+            return Ok(());
+        }
+
+        for market in &batch.markets {
+            transaction.execute(
+                "INSERT INTO indexer_markets VALUES
+                     ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+                      $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+                 ON CONFLICT (market) DO UPDATE SET
+                     name = excluded.name,
+                     symbol = excluded.symbol,
+                     uri = excluded.uri,
+                     circulating_supply = excluded.circulating_supply,
+                     current_price = excluded.current_price,
+                     trade_count = excluded.trade_count,
+                     volume = excluded.volume,
+                     platform_fees = excluded.platform_fees,
+                     creator_fees = excluded.creator_fees,
+                     updated_slot = excluded.updated_slot,
+                     treasury_balance = excluded.treasury_balance",
+                &[
+                    &market.market.to_string(),
+                    &market.mint.to_string(),
+                    &market.owner.to_string(),
+                    &market.name,
+                    &market.symbol,
+                    &market.uri,
+                    &curve_type_name(market.curve_type),
+                    &to_db(market.base_price)?,
+                    &to_db(market.k_factor)?,
+                    &to_db(market.scale_factor)?,
+                    &to_db(market.max_price)?,
+                    &to_db(market.total_supply)?,
+                    &to_db(market.circulating_supply)?,
+                    &to_db(market.current_price)?,
+                    &to_db(market.trade_count)?,
+                    &to_db(market.volume)?,
+                    &to_db(market.platform_fees)?,
+                    &to_db(market.creator_fees)?,
+                    &to_db(market.created_slot)?,
+                    &to_db(market.updated_slot)?,
+                    &to_db(market.treasury_balance)?,
+                ],
+            )?;
+        }
+        for trade in &batch.trades {
+            transaction.execute(
+                "INSERT INTO indexer_trades VALUES
+                     ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                      $13, $14, $15, $16)",
+                &[
+                    &trade.signature,
+                    &i64::from(trade.event_index),
+                    &to_db(trade.slot)?,
+                    &trade.market.to_string(),
+                    &trade_kind_name(trade.kind),
+                    &trade.trader.to_string(),
+                    &to_db(trade.tokens)?,
+                    &to_db(trade.price)?,
+                    &to_db(trade.total_cost)?,
+                    &to_db(trade.platform_fee)?,
+                    &to_db(trade.creator_fee)?,
+                    &to_db(trade.sequence)?,
+                    &to_db(trade.circulating_supply_before)?,
+                    &to_db(trade.circulating_supply_after)?,
+                    &to_db(trade.treasury_balance)?,
+                    &trade.timestamp,
+                ],
+            )?;
+        }
+        for gap in &batch.gaps {
+            insert_gap(&mut transaction, gap)?;
+        }
+
+        transaction.execute(
+            "INSERT INTO indexer_cursor (id, signature, slot) VALUES (1, $1, $2)
+             ON CONFLICT (id) DO UPDATE SET signature = excluded.signature, slot = excluded.slot",
+            &[&batch.signature, &to_db(batch.slot)?],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn record_gap(&mut self, gap: &Gap) -> Result<()> {
+        insert_gap(&mut self.client, gap)
+    }
+
+    fn gaps(&mut self) -> Result<Vec<Gap>> {
+        self.client
+            .query(
+                "SELECT market, slot, signature, kind, first, second FROM indexer_gaps ORDER BY id",
+                &[],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(Gap {
+                    market: parse_pubkey(row.try_get(0)?)?,
+                    slot: from_db(row.try_get(1)?)?,
+                    signature: row.try_get(2)?,
+                    kind: parse_gap_kind(row.try_get(3)?, row.try_get(4)?, row.try_get(5)?)?,
+                })
+            })
+            .collect()
+    }
+}
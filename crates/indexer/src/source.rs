@@ -0,0 +1,91 @@
+//! Fetches the program's transactions from an RPC node.
+
+use anyhow::{Context, Result};
+use celebrity_exchange_sdk::ID;
+use solana_rpc_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::UiTransactionEncoding;
+
+use crate::replay::IndexedTransaction;
+use crate::sink::Cursor;
+
+// The most signatures getSignaturesForAddress returns per call
+const PAGE_SIZE: usize = 1000;
+
+pub struct RpcSource {
+    rpc: RpcClient,
+}
+
+impl RpcSource {
+    pub fn new(rpc_url: &str) -> Self {
+        Self {
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
+        }
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    /// Signatures of every transaction naming the program since `cursor`, oldest first,
+    /// or its whole history without one.
+    pub fn signatures_since(&self, cursor: Option<&Cursor>) -> Result<Vec<Signature>> {
+        let until = cursor
+            .map(|c| c.signature.parse::<Signature>())
+            .transpose()
+            .context("invalid cursor signature")?;
+
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let page = self.rpc.get_signatures_for_address_with_config(
+                &ID,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(PAGE_SIZE),
+                    commitment: Some(self.rpc.commitment()),
+                },
+            )?;
+            let last_page = page.len() < PAGE_SIZE;
+            for status in page {
+                signatures.push(status.signature.parse::<Signature>()?);
+            }
+            if last_page {
+                break;
+            }
+            before = signatures.last().copied();
+        }
+
+        // Pages run newest first
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    pub fn transaction(&self, signature: &Signature) -> Result<IndexedTransaction> {
+        let transaction = self
+            .rpc
+            .get_transaction_with_config(
+                signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(self.rpc.commitment()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .with_context(|| format!("fetching transaction {signature}"))?;
+        let meta = transaction
+            .transaction
+            .meta
+            .with_context(|| format!("transaction {signature} has no status meta"))?;
+
+        Ok(IndexedTransaction {
+            signature: signature.to_string(),
+            slot: transaction.slot,
+            failed: meta.err.is_some(),
+            logs: Option::from(meta.log_messages).unwrap_or_default(),
+        })
+    }
+}
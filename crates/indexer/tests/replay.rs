@@ -0,0 +1,543 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Event;
+use base64::Engine;
+use celebrity_exchange_indexer::replay::{GapKind, IndexedTransaction, Replayer};
+use celebrity_exchange_indexer::sink::{self, Cursor, Sink, SqliteSink};
+use celebrity_exchange_indexer::Indexer;
+use celebrity_exchange_sdk::program::{
+    CurveParams, CurveType, GraduationThreshold, Market, MarketClosed, MarketClosing,
+    MarketCreated, MarketMetadataUpdated, MarketPaused, MarketResumed, MarketStatus,
+    MintAuthorityMigrated, TokensRedeemed, TradeExecuted, TradeKind, TradingMode, FEE_PRECISION,
+};
+use celebrity_exchange_sdk::ID;
+
+const TREASURY_RENT: u64 = 890_880;
+
+fn program_data(event: &impl Event) -> String {
+    format!(
+        "Program data: {}",
+        base64::engine::general_purpose::STANDARD.encode(event.data())
+    )
+}
+
+fn transaction(signature: &str, slot: u64, events: &[String]) -> IndexedTransaction {
+    let mut logs = vec![format!("Program {ID} invoke [1]")];
+    logs.extend(events.iter().cloned());
+    logs.push(format!("Program {ID} success"));
+    IndexedTransaction {
+        signature: signature.to_string(),
+        slot,
+        failed: false,
+        logs,
+    }
+}
+
+/// Emits one market's events the way the program would: consecutive sequence numbers,
+/// each trade starting from the supply the last one left.
+struct Stream {
+    market: Pubkey,
+    sequence: u64,
+    supply: u64,
+    treasury: u64,
+}
+
+impl Stream {
+    fn new() -> Self {
+        Self {
+            market: Pubkey::new_unique(),
+            sequence: 0,
+            supply: 0,
+            treasury: TREASURY_RENT,
+        }
+    }
+
+    fn created(&self) -> String {
+        program_data(&MarketCreated {
+            market: self.market,
+            owner: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            initial_price: 1_000_000,
+            initial_supply: 1_000,
+            curve_type: CurveType::Linear,
+            base_price: 1_000_000,
+            k_factor: 5,
+            scale_factor: 10_000,
+            max_price: 2_000_000,
+            name: "Celebrity".to_string(),
+            symbol: "CELEB".to_string(),
+            uri: "https://example.com/celebrity.json".to_string(),
+            sequence: 0,
+            circulating_supply: 0,
+            treasury_balance: TREASURY_RENT,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn trade(&mut self, kind: TradeKind, tokens: u64, price: u64) -> String {
+        let trader = Pubkey::new_unique();
+        let before = self.supply;
+        let value = 1_000_000 * tokens;
+        match kind {
+            TradeKind::Buy => {
+                self.supply += tokens;
+                self.treasury += value;
+            }
+            TradeKind::Sell => {
+                self.supply -= tokens;
+                self.treasury = self.treasury.saturating_sub(value);
+            }
+        }
+        self.sequence += 1;
+
+        program_data(&TradeExecuted {
+            market: self.market,
+            buyer: (kind == TradeKind::Buy).then_some(trader),
+            seller: (kind == TradeKind::Sell).then_some(trader),
+            tokens,
+            price,
+            total_cost: value,
+            platform_fee: 700 * tokens,
+            creator_fee: 300 * tokens,
+            fee_unrounded: 1_000 * u128::from(tokens) * FEE_PRECISION,
+            platform_fee_unrounded: 700 * u128::from(tokens) * FEE_PRECISION,
+            creator_fee_unrounded: 300 * u128::from(tokens) * FEE_PRECISION,
+            kind,
+            sequence: self.sequence,
+            circulating_supply_before: before,
+            circulating_supply_after: self.supply,
+            treasury_balance: self.treasury,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn closing(&mut self) -> String {
+        self.sequence += 1;
+        program_data(&MarketClosing {
+            market: self.market,
+            initiated_by: Pubkey::new_unique(),
+            sequence: self.sequence,
+            circulating_supply: self.supply,
+            treasury_balance: self.treasury,
+            grace_period_ends_at: 1_700_604_800,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn redeemed(&mut self, tokens: u64) -> String {
+        let before = self.supply;
+        let lamports = (self.treasury - TREASURY_RENT) * tokens / before;
+        self.supply -= tokens;
+        self.treasury -= lamports;
+        self.sequence += 1;
+        program_data(&TokensRedeemed {
+            market: self.market,
+            holder: Pubkey::new_unique(),
+            tokens,
+            lamports,
+            sequence: self.sequence,
+            circulating_supply_before: before,
+            circulating_supply_after: self.supply,
+            treasury_balance: self.treasury,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn paused(&mut self) -> String {
+        self.sequence += 1;
+        program_data(&MarketPaused {
+            market: Some(self.market),
+            sequence: Some(self.sequence),
+            mode: TradingMode::Paused,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn resumed(&mut self) -> String {
+        self.sequence += 1;
+        program_data(&MarketResumed {
+            market: Some(self.market),
+            sequence: Some(self.sequence),
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn migrated(&mut self, top_up: u64) -> String {
+        self.treasury += top_up;
+        self.sequence += 1;
+        program_data(&MintAuthorityMigrated {
+            market: self.market,
+            mint: Pubkey::new_unique(),
+            mint_authority: Pubkey::new_unique(),
+            sequence: self.sequence,
+            treasury_balance: self.treasury,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn renamed(&mut self, name: &str) -> String {
+        self.sequence += 1;
+        program_data(&MarketMetadataUpdated {
+            market: self.market,
+            sequence: self.sequence,
+            name: name.to_string(),
+            symbol: "NEW".to_string(),
+            uri: "https://example.com/new.json".to_string(),
+            locked: false,
+            timestamp: 1_700_000_000,
+        })
+    }
+
+    fn closed(&mut self) -> String {
+        self.sequence += 1;
+        program_data(&MarketClosed {
+            market: self.market,
+            owner: Pubkey::new_unique(),
+            sequence: self.sequence,
+            unredeemed_supply: self.supply,
+            treasury_balance: self.treasury,
+            lamports_returned: 2_039_280,
+            timestamp: 1_700_000_000,
+        })
+    }
+}
+
+fn in_memory() -> Indexer {
+    Indexer::new(Box::new(SqliteSink::open_in_memory().unwrap())).unwrap()
+}
+
+#[test]
+fn replays_supply_price_and_fees() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+
+    indexer
+        .process(&transaction("create", 10, &[stream.created()]))
+        .unwrap();
+    let buys = [
+        stream.trade(TradeKind::Buy, 40, 1_200_000),
+        stream.trade(TradeKind::Buy, 10, 1_250_000),
+    ];
+    indexer.process(&transaction("buys", 11, &buys)).unwrap();
+    let sell = stream.trade(TradeKind::Sell, 15, 1_175_000);
+    let touched = indexer.process(&transaction("sell", 12, &[sell])).unwrap();
+    assert_eq!(touched, vec![market]);
+
+    let markets = indexer.sink().load_markets().unwrap();
+    assert_eq!(markets.len(), 1);
+    let state = &markets[0];
+    assert_eq!(indexer.replayer().market(&market), Some(state));
+    assert_eq!(state.circulating_supply, 35);
+    assert_eq!(state.current_price, 1_175_000);
+    assert_eq!(state.trade_count, 3);
+    assert_eq!(state.treasury_balance, TREASURY_RENT + 35_000_000);
+    assert_eq!(state.volume, 65_000_000);
+    assert_eq!(state.platform_fees, 700 * 65);
+    assert_eq!(state.creator_fees, 300 * 65);
+    assert_eq!((state.created_slot, state.updated_slot), (10, 12));
+    assert_eq!(
+        indexer.sink().cursor().unwrap(),
+        Some(Cursor {
+            signature: "sell".to_string(),
+            slot: 12
+        })
+    );
+    assert!(indexer.sink().gaps().unwrap().is_empty());
+}
+
+#[test]
+fn sequence_numbers_reveal_missed_trades() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+
+    indexer
+        .process(&transaction("create", 1, &[stream.created()]))
+        .unwrap();
+    let first = stream.trade(TradeKind::Buy, 10, 1);
+    indexer.process(&transaction("first", 2, &[first])).unwrap();
+    // Sequences 2 and 3 never reach the indexer
+    stream.trade(TradeKind::Buy, 20, 1);
+    stream.trade(TradeKind::Sell, 5, 1);
+    let fourth = stream.trade(TradeKind::Buy, 1, 1);
+    indexer
+        .process(&transaction("fourth", 5, &[fourth]))
+        .unwrap();
+
+    let gaps = indexer.sink().gaps().unwrap();
+    assert_eq!(gaps.len(), 1);
+    assert_eq!(gaps[0].market, market);
+    assert_eq!(gaps[0].signature.as_deref(), Some("fourth"));
+    assert_eq!(
+        gaps[0].kind,
+        GapKind::MissedTrades {
+            expected: 2,
+            received: 4
+        }
+    );
+
+    // The event's own supply and sequence resynchronize the market
+    let state = indexer.replayer().market(&market).unwrap();
+    assert_eq!((state.trade_count, state.circulating_supply), (4, 26));
+    let fifth = stream.trade(TradeKind::Sell, 6, 1);
+    indexer.process(&transaction("fifth", 6, &[fifth])).unwrap();
+    assert_eq!(indexer.sink().gaps().unwrap().len(), 1);
+}
+
+#[test]
+fn replays_redemptions_after_a_market_closes() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+
+    let events = [stream.created(), stream.trade(TradeKind::Buy, 100, 1)];
+    indexer.process(&transaction("create", 1, &events)).unwrap();
+    let closing = stream.closing();
+    indexer
+        .process(&transaction("close", 2, &[closing]))
+        .unwrap();
+    let redeemed = stream.redeemed(40);
+    indexer
+        .process(&transaction("redeem", 3, &[redeemed]))
+        .unwrap();
+
+    let state = indexer.replayer().market(&market).unwrap();
+    assert_eq!((state.trade_count, state.circulating_supply), (3, 60));
+    assert_eq!(state.treasury_balance, stream.treasury);
+    assert_eq!(state.updated_slot, 3);
+    // Redemptions are not trades
+    assert_eq!(state.volume, 100_000_000);
+
+    // A redemption that never arrived shows up like a missed trade
+    stream.redeemed(10);
+    let closed = stream.closed();
+    indexer
+        .process(&transaction("finalize", 5, &[closed]))
+        .unwrap();
+    assert_eq!(
+        indexer.sink().gaps().unwrap()[0].kind,
+        GapKind::MissedTrades {
+            expected: 4,
+            received: 5
+        }
+    );
+    let state = indexer.replayer().market(&market).unwrap();
+    assert_eq!((state.trade_count, state.circulating_supply), (5, 50));
+}
+
+#[test]
+fn replays_pauses_migrations_and_metadata_updates() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+
+    let events = [stream.created(), stream.trade(TradeKind::Buy, 10, 1)];
+    indexer.process(&transaction("create", 1, &events)).unwrap();
+    // The global switch belongs to no market and takes no sequence number
+    let global = program_data(&MarketPaused {
+        market: None,
+        sequence: None,
+        mode: TradingMode::Paused,
+        timestamp: 1_700_000_000,
+    });
+    let events = [global, stream.paused(), stream.resumed()];
+    indexer.process(&transaction("pause", 2, &events)).unwrap();
+    let migrated = stream.migrated(5_000);
+    indexer
+        .process(&transaction("migrate", 3, &[migrated]))
+        .unwrap();
+    let renamed = stream.renamed("Renamed");
+    let touched = indexer
+        .process(&transaction("rename", 4, &[renamed]))
+        .unwrap();
+    assert_eq!(touched, vec![market]);
+
+    let state = indexer.replayer().market(&market).unwrap().clone();
+    assert_eq!((state.trade_count, state.circulating_supply), (5, 10));
+    assert_eq!(state.treasury_balance, stream.treasury);
+    assert_eq!(state.name, "Renamed");
+    assert_eq!(state.symbol, "NEW");
+    assert_eq!(state.updated_slot, 4);
+    // Only trades count toward volume
+    assert_eq!(state.volume, 10_000_000);
+    assert_eq!(indexer.sink().load_markets().unwrap(), vec![state]);
+    assert!(indexer.sink().gaps().unwrap().is_empty());
+
+    // A missed rename shows up like a missed trade
+    stream.renamed("Missed");
+    let trade = stream.trade(TradeKind::Sell, 4, 1);
+    indexer.process(&transaction("sell", 6, &[trade])).unwrap();
+    assert_eq!(
+        indexer.sink().gaps().unwrap()[0].kind,
+        GapKind::MissedTrades {
+            expected: 6,
+            received: 7
+        }
+    );
+}
+
+#[test]
+fn records_trades_that_cannot_be_replayed_as_gaps() {
+    let mut known = Stream::new();
+    let mut orphan = Stream::new();
+    let mut indexer = in_memory();
+
+    indexer
+        .process(&transaction("create", 1, &[known.created()]))
+        .unwrap();
+    let trade = orphan.trade(TradeKind::Buy, 5, 1);
+    indexer
+        .process(&transaction("orphan", 2, &[trade]))
+        .unwrap();
+
+    // A trade starting from a supply the replay never reached
+    known.supply = 7;
+    let trade = known.trade(TradeKind::Sell, 5, 1);
+    indexer
+        .process(&transaction("drifted", 3, &[trade]))
+        .unwrap();
+
+    let gaps = indexer.sink().gaps().unwrap();
+    let kinds: Vec<_> = gaps.iter().map(|g| (g.market, g.kind.clone())).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (orphan.market, GapKind::UnknownMarket),
+            (
+                known.market,
+                GapKind::SupplyMismatch {
+                    replayed: 0,
+                    reported: 7
+                }
+            ),
+        ]
+    );
+    assert_eq!(gaps[1].signature.as_deref(), Some("drifted"));
+    assert_eq!(
+        indexer
+            .replayer()
+            .market(&known.market)
+            .unwrap()
+            .circulating_supply,
+        2
+    );
+}
+
+#[test]
+fn reconciliation_flags_trades_not_yet_seen() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+    let trade = stream.trade(TradeKind::Buy, 5, 1);
+    indexer
+        .process(&transaction("create", 1, &[stream.created(), trade]))
+        .unwrap();
+
+    let mut account = Market {
+        owner: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        escrow: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        current_price: 1,
+        total_supply: 1_000,
+        circulating_supply: 5,
+        trade_count: 1,
+        curve: CurveParams {
+            curve_type: CurveType::Linear,
+            base_price: 1_000_000,
+            k_factor: 5,
+            scale_factor: 10_000,
+            max_price: 2_000_000,
+        },
+        trading_mode: TradingMode::Active,
+        metadata_locked: false,
+        name: "Celebrity".to_string(),
+        symbol: "CELEB".to_string(),
+        uri: String::new(),
+        graduation: GraduationThreshold::SoldSupply(800),
+        status: MarketStatus::Active,
+        closing_started_at: 0,
+        creator_allocation: 0,
+        escrow_closed: false,
+    };
+    assert!(indexer
+        .reconcile([(market, account.clone())], 2)
+        .unwrap()
+        .is_empty());
+
+    account.trade_count = 3;
+    let gaps = indexer.reconcile([(market, account)], 4).unwrap();
+    assert_eq!(
+        gaps[0].kind,
+        GapKind::TradeCountMismatch {
+            replayed: 1,
+            on_chain: 3
+        }
+    );
+    assert_eq!(indexer.sink().gaps().unwrap(), gaps);
+}
+
+#[test]
+fn failed_and_repeated_transactions_change_nothing() {
+    let mut stream = Stream::new();
+    let market = stream.market;
+    let mut indexer = in_memory();
+
+    let create = transaction("create", 1, &[stream.created()]);
+    let buy = transaction("buy", 2, &[stream.trade(TradeKind::Buy, 5, 1)]);
+    let mut failed = transaction("failed", 3, &[stream.trade(TradeKind::Buy, 50, 1)]);
+    failed.failed = true;
+
+    for tx in [&create, &buy, &failed, &buy] {
+        indexer.process(tx).unwrap();
+    }
+    let state = indexer.replayer().market(&market).unwrap().clone();
+    assert_eq!((state.circulating_supply, state.trade_count), (5, 1));
+    assert!(indexer.sink().gaps().unwrap().is_empty());
+
+    // A sink handed the same batches twice ends up where it did after the first time
+    let mut replayer = Replayer::default();
+    let batches = [&create, &buy].map(|tx| replayer.apply(tx));
+    let mut sink = SqliteSink::open_in_memory().unwrap();
+    for batch in batches.iter().chain(&batches) {
+        sink.commit(batch).unwrap();
+    }
+    assert_eq!(sink.load_markets().unwrap(), vec![state]);
+}
+
+#[test]
+fn resumes_from_a_database_file() {
+    let path = std::env::temp_dir().join(format!("indexer-{}.sqlite", Pubkey::new_unique()));
+    let url = format!("sqlite:{}", path.display());
+    let mut stream = Stream::new();
+    let market = stream.market;
+
+    {
+        let mut indexer = Indexer::new(sink::open(&url).unwrap()).unwrap();
+        let events = [
+            stream.created(),
+            stream.trade(TradeKind::Buy, 20, 1_100_000),
+        ];
+        indexer.process(&transaction("first", 7, &events)).unwrap();
+    }
+
+    let mut indexer = Indexer::new(sink::open(&url).unwrap()).unwrap();
+    assert_eq!(indexer.sink().cursor().unwrap().unwrap().signature, "first");
+    let sell = stream.trade(TradeKind::Sell, 5, 1_050_000);
+    indexer.process(&transaction("second", 8, &[sell])).unwrap();
+
+    let state = indexer.replayer().market(&market).unwrap();
+    assert_eq!(state.circulating_supply, 15);
+    assert_eq!(state.trade_count, 2);
+    assert_eq!(state.current_price, 1_050_000);
+    assert!(indexer.sink().gaps().unwrap().is_empty());
+
+    drop(indexer);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rejects_unknown_database_urls() {
+    assert!(sink::open("mysql://localhost/exchange").is_err());
+    assert!(sink::open("sqlite::memory:").is_ok());
+}
@@ -0,0 +1,374 @@
+//! Bonding curves.
+//!
+//! Every curve type prices a trade by the integral of its spot price between the supply
+//! before and after it, so splitting a trade never changes what it costs and the treasury
+//! always holds what the outstanding supply sells back for. All of them start at
+//! base_price, never rise above max_price, and dispatch through `BondingCurve`.
+
+use crate::fixed_point::{exp_wad, isqrt, ln_wad, mul_div, WAD};
+use crate::{MathError, Result};
+
+/// Shape of a market's bonding curve. Every type starts at `base_price`, grows at a rate
+/// set by `k_factor / scale_factor` and stops at `max_price`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveType {
+    /// base_price × e^(K × S / SCALE_FACTOR)
+    Exponential,
+    /// base_price × (1 + K × S / SCALE_FACTOR); flat presale pricing with `max_price == base_price`
+    Linear,
+    /// Eases from base_price to max_price over the first SCALE_FACTOR / K tokens
+    Sigmoid,
+    /// Virtual x*y=k pool of SCALE_FACTOR / K tokens priced at base_price each
+    ConstantProduct,
+}
+
+impl CurveType {
+    pub fn curve(self) -> &'static dyn BondingCurve {
+        match self {
+            CurveType::Exponential => &ExponentialCurve,
+            CurveType::Linear => &LinearCurve,
+            CurveType::Sigmoid => &SigmoidCurve,
+            CurveType::ConstantProduct => &ConstantProductCurve,
+        }
+    }
+}
+
+/// Bonding curve parameters, fixed when a market is created.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurveParams {
+    pub curve_type: CurveType,
+    pub base_price: u64,
+    pub k_factor: u64,
+    pub scale_factor: u64,
+    /// The price never rises above this; equal to `base_price` for a flat curve.
+    pub max_price: u64,
+}
+
+impl CurveParams {
+    pub fn validate(&self) -> Result<()> {
+        if self.base_price == 0
+            || self.k_factor == 0
+            || self.scale_factor == 0
+            || self.max_price < self.base_price
+        {
+            return Err(MathError::InvalidCurveParams);
+        }
+
+        self.curve_type
+            .curve()
+            .validate(self)
+            .map_err(|_| MathError::InvalidCurveParams)
+    }
+}
+
+pub fn calculate_current_price(circulating_supply: u64, curve: &CurveParams) -> Result<u64> {
+    curve.curve_type.curve().price(curve, circulating_supply)
+}
+
+pub fn calculate_buy_cost(from_supply: u64, to_supply: u64, curve: &CurveParams) -> Result<u64> {
+    // Integrate price curve from from_supply to to_supply
+    let bonding_curve = curve.curve_type.curve();
+    let cost = bonding_curve
+        .integral(curve, to_supply)?
+        .checked_sub(bonding_curve.integral(curve, from_supply)?)
+        .ok_or(MathError::Overflow)?;
+
+    u64::try_from(cost).map_err(|_| MathError::Overflow)
+}
+
+pub fn calculate_sell_value(from_supply: u64, to_supply: u64, curve: &CurveParams) -> Result<u64> {
+    // Same as buy cost but in reverse
+    calculate_buy_cost(to_supply, from_supply, curve)
+}
+
+/// Pricing of one `CurveType`. A new curve only needs an implementation of this and a
+/// variant; trade handlers and price lookups go through `CurveParams` and never name a curve.
+pub trait BondingCurve {
+    /// Type-specific checks on top of the ones every curve shares.
+    fn validate(&self, params: &CurveParams) -> Result<()>;
+
+    /// Spot price in lamports with `supply` tokens in circulation.
+    fn price(&self, params: &CurveParams, supply: u64) -> Result<u64>;
+
+    /// Lamports needed to buy the first `supply` tokens. Must never decrease as `supply`
+    /// grows, since a trade costs the difference of two of these.
+    fn integral(&self, params: &CurveParams, supply: u64) -> Result<u128>;
+}
+
+// P(S) = min(base_price × e^(K × S / SCALE_FACTOR), max_price), evaluated in 18-decimal
+// fixed point. `exp_wad` and `ln_wad` stay within a relative error of 1e-16, so a price
+// is off by at most one lamport of rounding plus 1e-15 of its value.
+pub struct ExponentialCurve;
+
+impl ExponentialCurve {
+    // Exponent K × S / SCALE_FACTOR in WAD; None when it overflows, far past any cap
+    fn exponent_wad(params: &CurveParams, supply: u64) -> Option<u128> {
+        (supply as u128 * params.k_factor as u128)
+            .checked_mul(WAD)
+            .map(|scaled| scaled / params.scale_factor as u128)
+    }
+
+    // Integral of the uncapped curve from 0 to the supply at `exponent`:
+    // base_price × SCALE_FACTOR / K × (e^exponent - 1)
+    fn exponential_integral(params: &CurveParams, exponent: u128) -> Result<u128> {
+        let growth = exp_wad(exponent).ok_or(MathError::Overflow)? - WAD;
+        let scaled = (params.base_price as u128)
+            .checked_mul(growth)
+            .ok_or(MathError::Overflow)?
+            / params.k_factor as u128;
+
+        Ok(scaled
+            .checked_mul(params.scale_factor as u128)
+            .ok_or(MathError::Overflow)?
+            / WAD)
+    }
+
+    fn cap(params: &CurveParams) -> Result<CurveCap> {
+        let ratio = (params.max_price as u128)
+            .checked_mul(WAD)
+            .ok_or(MathError::Overflow)?
+            / params.base_price as u128;
+        let exponent = ln_wad(ratio)?;
+        let integral = Self::exponential_integral(params, exponent)?;
+
+        // max_price × supply at the cap, with the supply still in WAD
+        let supply = exponent
+            .checked_mul(params.scale_factor as u128)
+            .ok_or(MathError::Overflow)?
+            / params.k_factor as u128;
+        let max_price = params.max_price as u128;
+        let offset = (supply / WAD)
+            .checked_mul(max_price)
+            .and_then(|whole| whole.checked_add(supply % WAD * max_price / WAD))
+            .ok_or(MathError::Overflow)?;
+
+        Ok(CurveCap {
+            exponent,
+            integral,
+            offset,
+        })
+    }
+}
+
+impl BondingCurve for ExponentialCurve {
+    fn validate(&self, params: &CurveParams) -> Result<()> {
+        // The integral up to the cap must be representable, and past it only grows linearly
+        Self::cap(params).map(|_| ())
+    }
+
+    fn price(&self, params: &CurveParams, supply: u64) -> Result<u64> {
+        let cap = Self::cap(params)?;
+
+        match Self::exponent_wad(params, supply) {
+            Some(exponent) if exponent < cap.exponent => {
+                let growth = exp_wad(exponent).ok_or(MathError::Overflow)?;
+                let price = (params.base_price as u128)
+                    .checked_mul(growth)
+                    .ok_or(MathError::Overflow)?
+                    / WAD;
+                Ok(price.min(params.max_price as u128) as u64)
+            }
+            _ => Ok(params.max_price),
+        }
+    }
+
+    // Exponential up to the cap, then flat at max_price. Both pieces round down and meet
+    // without a step back.
+    fn integral(&self, params: &CurveParams, supply: u64) -> Result<u128> {
+        let cap = Self::cap(params)?;
+
+        match Self::exponent_wad(params, supply) {
+            Some(exponent) if exponent < cap.exponent => {
+                Self::exponential_integral(params, exponent)
+            }
+            _ => (params.max_price as u128)
+                .checked_mul(supply as u128)
+                .and_then(|flat| flat.checked_sub(cap.offset))
+                .and_then(|flat| flat.checked_add(cap.integral))
+                .ok_or(MathError::Overflow),
+        }
+    }
+}
+
+// Where the exponential part of a curve meets its price cap
+struct CurveCap {
+    exponent: u128,
+    integral: u128,
+    offset: u128,
+}
+
+// P(S) = min(base_price × (1 + K × S / SCALE_FACTOR), max_price). Prices and integrals
+// are exact fractions rounded down once, so both follow the real curve to the lamport.
+pub struct LinearCurve;
+
+impl LinearCurve {
+    // Last supply whose price is still at or below max_price
+    fn cap_supply(params: &CurveParams) -> u64 {
+        let headroom = params.scale_factor as u128 * (params.max_price - params.base_price) as u128;
+        let cap = headroom / (params.base_price as u128 * params.k_factor as u128);
+        u64::try_from(cap).unwrap_or(u64::MAX)
+    }
+}
+
+impl BondingCurve for LinearCurve {
+    fn validate(&self, _params: &CurveParams) -> Result<()> {
+        Ok(())
+    }
+
+    fn price(&self, params: &CurveParams, supply: u64) -> Result<u64> {
+        if supply > Self::cap_supply(params) {
+            return Ok(params.max_price);
+        }
+
+        let scale = params.scale_factor as u128;
+        let growth = scale + params.k_factor as u128 * supply as u128;
+        let price = mul_div(params.base_price as u128, growth, scale).ok_or(MathError::Overflow)?;
+        Ok(price as u64)
+    }
+
+    // base_price × S × (2 × SCALE_FACTOR + K × S) / (2 × SCALE_FACTOR) up to the cap,
+    // then max_price per token
+    fn integral(&self, params: &CurveParams, supply: u64) -> Result<u128> {
+        let ramp = supply.min(Self::cap_supply(params));
+        let scale = 2 * params.scale_factor as u128;
+        let growth = (params.k_factor as u128 * ramp as u128)
+            .checked_add(scale)
+            .ok_or(MathError::Overflow)?;
+        let ramp_integral = mul_div(params.base_price as u128 * ramp as u128, growth, scale)
+            .ok_or(MathError::Overflow)?;
+
+        (params.max_price as u128 * (supply - ramp) as u128)
+            .checked_add(ramp_integral)
+            .ok_or(MathError::Overflow)
+    }
+}
+
+// Smoothstep from base_price to max_price over the first SCALE_FACTOR / K tokens:
+// P(S) = base_price + (max_price - base_price) × (3t² - 2t³) with t = min(K × S / SCALE_FACTOR, 1).
+// Flat at both ends, so early buyers pay close to the presale price and late ones
+// close to the cap. Exact fractions again, which is what bounds SCALE_FACTOR.
+pub struct SigmoidCurve;
+
+impl SigmoidCurve {
+    // SCALE_FACTOR⁴ stays below 2^124, so every intermediate fits a u128
+    pub const MAX_SCALE_FACTOR: u64 = 1 << 31;
+}
+
+impl BondingCurve for SigmoidCurve {
+    fn validate(&self, params: &CurveParams) -> Result<()> {
+        if params.scale_factor > Self::MAX_SCALE_FACTOR {
+            return Err(MathError::InvalidCurveParams);
+        }
+        // A ramp shorter than one token would be a step, not a curve
+        if params.k_factor > params.scale_factor {
+            return Err(MathError::InvalidCurveParams);
+        }
+
+        Ok(())
+    }
+
+    fn price(&self, params: &CurveParams, supply: u64) -> Result<u64> {
+        let t = params.k_factor as u128 * supply as u128;
+        let scale = params.scale_factor as u128;
+        if t >= scale {
+            return Ok(params.max_price);
+        }
+
+        let rise = (params.max_price - params.base_price) as u128;
+        let eased = mul_div(rise, t * t * (3 * scale - 2 * t), scale * scale * scale)
+            .ok_or(MathError::Overflow)?;
+        Ok(params.base_price + eased as u64)
+    }
+
+    // base_price × S plus the rise integrated over the ramp, t³ × (2 × SCALE_FACTOR - t) /
+    // (2 × K × SCALE_FACTOR³), and max_price - base_price per token past it
+    fn integral(&self, params: &CurveParams, supply: u64) -> Result<u128> {
+        let k = params.k_factor as u128;
+        let t = k * supply as u128;
+        let scale = params.scale_factor as u128;
+        let rise = (params.max_price - params.base_price) as u128;
+
+        let eased = if t <= scale {
+            mul_div(
+                rise,
+                t * t * t * (2 * scale - t),
+                2 * k * scale * scale * scale,
+            )
+            .ok_or(MathError::Overflow)?
+        } else {
+            // The ramp averages half the rise: subtract its missing half, rounded up
+            rise * supply as u128 - (rise * scale).div_ceil(2 * k)
+        };
+
+        (params.base_price as u128 * supply as u128)
+            .checked_add(eased)
+            .ok_or(MathError::Overflow)
+    }
+}
+
+// A virtual x*y=k pool of SCALE_FACTOR / K tokens against base_price lamports for each:
+// P(S) = base_price / (1 - K × S / SCALE_FACTOR)², and the first S tokens cost
+// base_price × S / (1 - K × S / SCALE_FACTOR), both exact fractions rounded down.
+// The pool price runs off to infinity, so past max_price the curve turns flat.
+pub struct ConstantProductCurve;
+
+impl ConstantProductCurve {
+    // Last supply whose price is still at or below max_price: the virtual reserve left,
+    // SCALE_FACTOR - K × S, must stay above SCALE_FACTOR × √(base_price / max_price)
+    fn cap_supply(params: &CurveParams) -> u64 {
+        let scale = params.scale_factor as u128;
+        // At most scale², since base_price <= max_price
+        let min_reserve_squared = mul_div(
+            params.base_price as u128,
+            scale * scale,
+            params.max_price as u128,
+        )
+        .unwrap_or(scale * scale);
+        // Strictly above the floored bound, so the price at the cap never exceeds max_price
+        let min_reserve = isqrt(min_reserve_squared) + 1;
+
+        (scale.saturating_sub(min_reserve) / params.k_factor as u128) as u64
+    }
+
+    // Virtual token reserve left after `supply` tokens, in units of 1 / K
+    fn reserve(params: &CurveParams, supply: u64) -> u128 {
+        params.scale_factor as u128 - params.k_factor as u128 * supply as u128
+    }
+}
+
+impl BondingCurve for ConstantProductCurve {
+    fn validate(&self, params: &CurveParams) -> Result<()> {
+        // The virtual pool has to hold at least one token
+        if params.k_factor > params.scale_factor {
+            return Err(MathError::InvalidCurveParams);
+        }
+
+        Ok(())
+    }
+
+    fn price(&self, params: &CurveParams, supply: u64) -> Result<u64> {
+        if supply > Self::cap_supply(params) {
+            return Ok(params.max_price);
+        }
+
+        let scale = params.scale_factor as u128;
+        let reserve = Self::reserve(params, supply);
+        let price = mul_div(params.base_price as u128, scale * scale, reserve * reserve)
+            .ok_or(MathError::Overflow)?;
+        Ok(price as u64)
+    }
+
+    fn integral(&self, params: &CurveParams, supply: u64) -> Result<u128> {
+        let ramp = supply.min(Self::cap_supply(params));
+        let ramp_integral = mul_div(
+            params.base_price as u128 * ramp as u128,
+            params.scale_factor as u128,
+            Self::reserve(params, ramp),
+        )
+        .ok_or(MathError::Overflow)?;
+
+        (params.max_price as u128 * (supply - ramp) as u128)
+            .checked_add(ramp_integral)
+            .ok_or(MathError::Overflow)
+    }
+}
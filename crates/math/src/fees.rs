@@ -0,0 +1,89 @@
+//! Trading fees and how they split between the platform and the market creator.
+//!
+//! A trade's fee is `amount × transaction_fee_bps / BPS_DENOMINATOR` rounded down, raised
+//! to `min_fee` and never more than the trade itself. The creator's share of it is
+//! rounded down and the platform takes the rest, so the two shares always add up to
+//! exactly the fee charged and no lamport is left over on either side of a trade.
+
+use crate::{MathError, Result};
+
+pub const BPS_DENOMINATOR: u64 = 10_000; // 100% in basis points
+
+/// Unrounded fees are counted in 1 / FEE_PRECISION lamports, fine enough that a fee
+/// and both of its shares are exact integers.
+pub const FEE_PRECISION: u128 = (BPS_DENOMINATOR as u128) * (BPS_DENOMINATOR as u128);
+
+/// The fee settings of the exchange's global config that a trade is priced with.
+/// The two shares must add up to `BPS_DENOMINATOR`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub transaction_fee_bps: u64,
+    pub platform_fee_share_bps: u64,
+    pub creator_fee_share_bps: u64,
+    /// Lamports charged on any trade too small for its percentage fee to reach this.
+    /// No minimum applies while `transaction_fee_bps` is zero.
+    pub min_fee: u64,
+}
+
+impl FeeSchedule {
+    pub fn validate(&self) -> Result<()> {
+        match self
+            .platform_fee_share_bps
+            .checked_add(self.creator_fee_share_bps)
+        {
+            Some(BPS_DENOMINATOR) => Ok(()),
+            _ => Err(MathError::InvalidFeeSchedule),
+        }
+    }
+}
+
+/// A trade's fee and its shares before any rounding, in 1 / FEE_PRECISION lamports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExactFees {
+    pub total: u128,
+    pub platform: u128,
+    pub creator: u128,
+}
+
+// floor(amount × bps / BPS_DENOMINATOR)
+fn apply_bps(amount: u64, bps: u64) -> Result<u64> {
+    let value = (amount as u128)
+        .checked_mul(bps as u128)
+        .ok_or(MathError::Overflow)?
+        / BPS_DENOMINATOR as u128;
+
+    u64::try_from(value).map_err(|_| MathError::Overflow)
+}
+
+/// Returns (total_fee, platform_fee, creator_fee) for a trade of `amount` lamports;
+/// `platform_fee + creator_fee == total_fee` always holds.
+pub fn calculate_fees(amount: u64, fees: &FeeSchedule) -> Result<(u64, u64, u64)> {
+    fees.validate()?;
+    if fees.transaction_fee_bps == 0 {
+        return Ok((0, 0, 0));
+    }
+
+    let total_fee = apply_bps(amount, fees.transaction_fee_bps)?
+        .max(fees.min_fee)
+        .min(amount);
+    let creator_fee = apply_bps(total_fee, fees.creator_fee_share_bps)?;
+    let platform_fee = total_fee - creator_fee;
+
+    Ok((total_fee, platform_fee, creator_fee))
+}
+
+/// The fee on `amount` lamports and its shares as percentages alone would set them,
+/// before rounding, the minimum fee or the cap apply.
+pub fn exact_fees(amount: u64, fees: &FeeSchedule) -> Result<ExactFees> {
+    fees.validate()?;
+    let scaled = (amount as u128)
+        .checked_mul(fees.transaction_fee_bps as u128)
+        .ok_or(MathError::Overflow)?;
+    let share = |bps: u64| scaled.checked_mul(bps as u128).ok_or(MathError::Overflow);
+
+    Ok(ExactFees {
+        total: share(BPS_DENOMINATOR)?,
+        platform: share(fees.platform_fee_share_bps)?,
+        creator: share(fees.creator_fee_share_bps)?,
+    })
+}
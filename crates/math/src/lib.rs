@@ -0,0 +1,51 @@
+//! Bonding curve, fee, quote, pool and vesting math for the celebrity exchange.
+//!
+//! The on-chain program prices every trade with this crate, so an off-chain client that
+//! quotes through it gets the same lamports, bit for bit. It is `no_std` and has no
+//! dependencies, so it builds for SBF, wasm and native targets alike.
+
+#![no_std]
+
+pub mod curve;
+pub mod fees;
+pub mod fixed_point;
+pub mod pool;
+pub mod quote;
+pub mod vesting;
+
+pub use curve::{
+    calculate_buy_cost, calculate_current_price, calculate_sell_value, BondingCurve,
+    ConstantProductCurve, CurveParams, CurveType, ExponentialCurve, LinearCurve, SigmoidCurve,
+};
+pub use fees::{
+    calculate_fees, exact_fees, ExactFees, FeeSchedule, BPS_DENOMINATOR, FEE_PRECISION,
+};
+pub use fixed_point::{exp_wad, ln_wad, WAD};
+pub use pool::{
+    deposit_for_liquidity, initial_liquidity, quote_swap_buy, quote_swap_sell, swap_output,
+    withdrawal_for_liquidity, SwapQuote,
+};
+pub use quote::{calculate_buy_charge, max_tokens_for_budget, quote_buy, quote_sell, Quote};
+pub use vesting::vested_amount;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    /// A result or intermediate value does not fit its integer type
+    Overflow,
+    /// The curve parameters describe no valid curve of their type
+    InvalidCurveParams,
+    /// The platform and creator shares do not add up to the whole fee
+    InvalidFeeSchedule,
+}
+
+impl core::fmt::Display for MathError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MathError::Overflow => f.write_str("math overflow"),
+            MathError::InvalidCurveParams => f.write_str("invalid bonding curve parameters"),
+            MathError::InvalidFeeSchedule => f.write_str("fee shares do not add up to 100%"),
+        }
+    }
+}
+
+pub type Result<T> = core::result::Result<T, MathError>;
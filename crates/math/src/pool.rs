@@ -0,0 +1,100 @@
+//! Constant-product (x × y = k) pool math for tokens that trade outside a curve.
+//!
+//! A pool holds SOL and one token. Liquidity is measured in units of
+//! √(sol × tokens), so it grows with the pool and does not depend on the price.
+//! Every amount is rounded in the pool's favour: swaps and withdrawals round what
+//! leaves it down, deposits round what enters it up, so k never shrinks.
+
+use crate::fees::{calculate_fees, FeeSchedule};
+use crate::fixed_point::isqrt;
+use crate::{MathError, Result};
+
+/// Liquidity units a pool opened with these reserves starts out with: √(sol × tokens),
+/// rounded down.
+pub fn initial_liquidity(sol_reserve: u64, token_reserve: u64) -> u64 {
+    // √(u64::MAX²) < 2^64, so the root always fits
+    isqrt(sol_reserve as u128 * token_reserve as u128) as u64
+}
+
+/// What `amount_in` buys out of `reserve_out` once it joins `reserve_in`:
+/// reserve_out × amount_in / (reserve_in + amount_in), rounded down.
+pub fn swap_output(amount_in: u64, reserve_in: u64, reserve_out: u64) -> u64 {
+    let denominator = reserve_in as u128 + amount_in as u128;
+    if denominator == 0 {
+        return 0;
+    }
+    // Never more than reserve_out, so it fits
+    (reserve_out as u128 * amount_in as u128 / denominator) as u64
+}
+
+/// Share of `reserve` that `liquidity` out of `lp_supply` units must deposit, rounded up.
+pub fn deposit_for_liquidity(liquidity: u64, reserve: u64, lp_supply: u64) -> Result<u64> {
+    if lp_supply == 0 {
+        return Err(MathError::Overflow);
+    }
+    let value = (liquidity as u128 * reserve as u128).div_ceil(lp_supply as u128);
+    u64::try_from(value).map_err(|_| MathError::Overflow)
+}
+
+/// Share of `reserve` that burning `liquidity` out of `lp_supply` units withdraws,
+/// rounded down.
+pub fn withdrawal_for_liquidity(liquidity: u64, reserve: u64, lp_supply: u64) -> Result<u64> {
+    if liquidity > lp_supply {
+        return Err(MathError::Overflow);
+    }
+    // liquidity ≤ lp_supply, so the share fits in `reserve`
+    Ok((liquidity as u128 * reserve as u128 / lp_supply as u128) as u64)
+}
+
+/// A swap priced against a pool's reserves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapQuote {
+    /// Lamports on a buy, tokens on a sell
+    pub amount_in: u64,
+    /// Tokens on a buy; on a sell, the lamports the seller receives after fees
+    pub amount_out: u64,
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    /// Lamports added to the pool's SOL reserve on a buy, or taken out of it on a sell
+    pub sol_reserve_change: u64,
+}
+
+/// Buying tokens with `sol_in` lamports. The fees come out of `sol_in` and only the
+/// rest is swapped.
+pub fn quote_swap_buy(
+    sol_in: u64,
+    sol_reserve: u64,
+    token_reserve: u64,
+    fees: &FeeSchedule,
+) -> Result<SwapQuote> {
+    let (total_fee, platform_fee, creator_fee) = calculate_fees(sol_in, fees)?;
+    // The fee never exceeds the amount it is taken from
+    let sol_reserve_change = sol_in - total_fee;
+
+    Ok(SwapQuote {
+        amount_in: sol_in,
+        amount_out: swap_output(sol_reserve_change, sol_reserve, token_reserve),
+        platform_fee,
+        creator_fee,
+        sol_reserve_change,
+    })
+}
+
+/// Selling `tokens_in` for SOL. The fees come out of the lamports the swap releases.
+pub fn quote_swap_sell(
+    tokens_in: u64,
+    sol_reserve: u64,
+    token_reserve: u64,
+    fees: &FeeSchedule,
+) -> Result<SwapQuote> {
+    let sol_from_pool = swap_output(tokens_in, token_reserve, sol_reserve);
+    let (total_fee, platform_fee, creator_fee) = calculate_fees(sol_from_pool, fees)?;
+
+    Ok(SwapQuote {
+        amount_in: tokens_in,
+        amount_out: sol_from_pool - total_fee,
+        platform_fee,
+        creator_fee,
+        sol_reserve_change: sol_from_pool,
+    })
+}
@@ -0,0 +1,141 @@
+//! Whole trades: curve cost or value plus fees, exactly as the program settles them.
+
+use crate::curve::{
+    calculate_buy_cost, calculate_current_price, calculate_sell_value, CurveParams,
+};
+use crate::fees::{calculate_fees, FeeSchedule};
+use crate::{MathError, Result};
+
+/// A trade priced against a market at a given circulating supply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quote {
+    pub tokens: u64,
+    /// Curve cost of the tokens on a buy, their curve value on a sell
+    pub total_cost: u64,
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    /// Everything the buyer pays, or what the seller receives after fees
+    pub net_lamports: u64,
+    /// Spot price once the trade has gone through
+    pub price_after: u64,
+}
+
+impl Quote {
+    /// A buy of `tokens` whose curve cost is `total_cost`; the buyer pays the fees on top.
+    pub fn buy(tokens: u64, total_cost: u64, price_after: u64, fees: &FeeSchedule) -> Result<Self> {
+        let (_, platform_fee, creator_fee) = calculate_fees(total_cost, fees)?;
+        let net_lamports = total_cost
+            .checked_add(platform_fee)
+            .and_then(|v| v.checked_add(creator_fee))
+            .ok_or(MathError::Overflow)?;
+
+        Ok(Self {
+            tokens,
+            total_cost,
+            platform_fee,
+            creator_fee,
+            net_lamports,
+            price_after,
+        })
+    }
+
+    /// A sale of `tokens` whose curve value is `total_value`; the fees come out of it.
+    pub fn sell(
+        tokens: u64,
+        total_value: u64,
+        price_after: u64,
+        fees: &FeeSchedule,
+    ) -> Result<Self> {
+        let (total_fee, platform_fee, creator_fee) = calculate_fees(total_value, fees)?;
+        // The fee never exceeds the value it is taken from
+        let net_lamports = total_value - total_fee;
+
+        Ok(Self {
+            tokens,
+            total_cost: total_value,
+            platform_fee,
+            creator_fee,
+            net_lamports,
+            price_after,
+        })
+    }
+}
+
+/// Buying `amount` tokens from a market with `circulating_supply` already out.
+pub fn quote_buy(
+    circulating_supply: u64,
+    amount: u64,
+    curve: &CurveParams,
+    fees: &FeeSchedule,
+) -> Result<Quote> {
+    let to_supply = circulating_supply
+        .checked_add(amount)
+        .ok_or(MathError::Overflow)?;
+    let total_cost = calculate_buy_cost(circulating_supply, to_supply, curve)?;
+    let price_after = calculate_current_price(to_supply, curve)?;
+
+    Quote::buy(amount, total_cost, price_after, fees)
+}
+
+/// Selling `amount` tokens back to a market with `circulating_supply` out.
+pub fn quote_sell(
+    circulating_supply: u64,
+    amount: u64,
+    curve: &CurveParams,
+    fees: &FeeSchedule,
+) -> Result<Quote> {
+    let to_supply = circulating_supply
+        .checked_sub(amount)
+        .ok_or(MathError::Overflow)?;
+    let total_value = calculate_sell_value(circulating_supply, to_supply, curve)?;
+    let price_after = calculate_current_price(to_supply, curve)?;
+
+    Quote::sell(amount, total_value, price_after, fees)
+}
+
+/// Total lamports a buyer pays for `amount` tokens: curve cost plus platform and creator fees.
+pub fn calculate_buy_charge(
+    circulating_supply: u64,
+    amount: u64,
+    curve: &CurveParams,
+    fees: &FeeSchedule,
+) -> Result<u64> {
+    let to_supply = circulating_supply
+        .checked_add(amount)
+        .ok_or(MathError::Overflow)?;
+    let total_cost = calculate_buy_cost(circulating_supply, to_supply, curve)?;
+    let (_, platform_fee, creator_fee) = calculate_fees(total_cost, fees)?;
+
+    total_cost
+        .checked_add(platform_fee)
+        .and_then(|v| v.checked_add(creator_fee))
+        .ok_or(MathError::Overflow)
+}
+
+/// Largest token amount, up to `available`, whose buy charge fits in `budget`.
+///
+/// The charge is monotonic in amount, so a binary search over the closed-form cost
+/// keeps this at O(log available) curve evaluations.
+pub fn max_tokens_for_budget(
+    circulating_supply: u64,
+    available: u64,
+    budget: u64,
+    curve: &CurveParams,
+    fees: &FeeSchedule,
+) -> u64 {
+    let mut low = 0u64;
+    // No token costs less than the base price
+    let mut high = available
+        .min(u64::MAX - circulating_supply)
+        .min(budget / curve.base_price);
+
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        match calculate_buy_charge(circulating_supply, mid, curve, fees) {
+            Ok(charge) if charge <= budget => low = mid,
+            _ => high = mid - 1,
+        }
+    }
+
+    low
+}
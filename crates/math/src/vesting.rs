@@ -0,0 +1,21 @@
+//! Cliff-plus-linear release of a creator's token allocation.
+//!
+//! Nothing is released before the cliff. From then on the allocation releases in
+//! proportion to the time since the schedule started, so the cliff unlocks everything
+//! that accrued before it at once, and the whole allocation is out once it ends.
+
+/// Tokens of `total` released at `now` by a schedule running from `start` to `end` with
+/// its cliff at `cliff`, rounded down. Never decreases as `now` grows.
+pub fn vested_amount(total: u64, start: i64, cliff: i64, end: i64, now: i64) -> u64 {
+    if now < cliff {
+        return 0;
+    }
+    if now >= end || end <= start {
+        return total;
+    }
+    // start ≤ cliff ≤ now < end, so the elapsed time is below the duration and the
+    // share fits in `total`
+    let elapsed = (now - start) as u128;
+    let duration = (end - start) as u128;
+    (total as u128 * elapsed / duration) as u64
+}
@@ -0,0 +1,166 @@
+use celebrity_exchange_math::{
+    calculate_buy_cost, calculate_current_price, exp_wad, ln_wad, CurveParams, CurveType, WAD,
+};
+use proptest::prelude::*;
+
+// The cap create_market applies when none is given: base price × 2^20
+const DEFAULT_MAX_PRICE_MULTIPLIER: u64 = 1 << 20;
+
+fn curve_types() -> impl Strategy<Value = CurveType> {
+    prop::sample::select(vec![
+        CurveType::Exponential,
+        CurveType::Linear,
+        CurveType::Sigmoid,
+        CurveType::ConstantProduct,
+    ])
+}
+
+fn curves() -> impl Strategy<Value = CurveParams> {
+    (
+        curve_types(),
+        1u64..=u64::MAX / DEFAULT_MAX_PRICE_MULTIPLIER,
+        1u64..=DEFAULT_MAX_PRICE_MULTIPLIER,
+        1u64..=1_000,
+        1u64..=1_000_000,
+    )
+        .prop_map(
+            |(curve_type, base_price, multiplier, k_factor, scale_factor)| CurveParams {
+                curve_type,
+                base_price,
+                k_factor,
+                scale_factor,
+                max_price: base_price * multiplier,
+            },
+        )
+        .prop_filter("curve must validate", |curve| curve.validate().is_ok())
+}
+
+// Curves where one token moves the price by at least a lamport
+fn smooth_curves() -> impl Strategy<Value = CurveParams> {
+    (
+        curve_types(),
+        1_000_000u64..=1_000_000_000,
+        1u64..=100,
+        1_000u64..=100_000,
+    )
+        .prop_map(
+            |(curve_type, base_price, k_factor, scale_factor)| CurveParams {
+                curve_type,
+                base_price,
+                k_factor,
+                scale_factor,
+                max_price: base_price * DEFAULT_MAX_PRICE_MULTIPLIER,
+            },
+        )
+}
+
+// The price each curve type describes, before any fixed-point rounding
+fn exact_price(supply: u64, curve: &CurveParams) -> f64 {
+    let base_price = curve.base_price as f64;
+    let max_price = curve.max_price as f64;
+    let growth = supply as f64 * curve.k_factor as f64 / curve.scale_factor as f64;
+
+    let price = match curve.curve_type {
+        CurveType::Exponential => base_price * growth.exp(),
+        CurveType::Linear => base_price * (1.0 + growth),
+        CurveType::Sigmoid => {
+            let t = growth.min(1.0);
+            base_price + (max_price - base_price) * (3.0 * t * t - 2.0 * t * t * t)
+        }
+        CurveType::ConstantProduct if growth < 1.0 => {
+            base_price / ((1.0 - growth) * (1.0 - growth))
+        }
+        CurveType::ConstantProduct => max_price,
+    };
+    price.min(max_price)
+}
+
+proptest! {
+    #[test]
+    fn exp_wad_is_accurate_and_monotonic(x in 0u128..40 * WAD, step in 0u128..WAD) {
+        let value = exp_wad(x).unwrap();
+        let exact = (x as f64 / WAD as f64).exp() * WAD as f64;
+        // f64 itself only resolves the exponent to ~1e-16 of 40
+        prop_assert!((value as f64 - exact).abs() <= exact * 1e-14);
+        prop_assert!(exp_wad(x + step).unwrap() >= value);
+    }
+
+    #[test]
+    fn ln_wad_inverts_exp_wad(x in 0u128..40 * WAD) {
+        let round_trip = ln_wad(exp_wad(x).unwrap()).unwrap();
+        prop_assert!(round_trip.abs_diff(x) <= 100);
+    }
+
+    #[test]
+    fn price_is_monotonic_and_bounded(
+        curve in curves(),
+        a in any::<u64>(),
+        b in any::<u64>(),
+    ) {
+        let (low, high) = (a.min(b), a.max(b));
+        let low_price = calculate_current_price(low, &curve).unwrap();
+        let high_price = calculate_current_price(high, &curve).unwrap();
+
+        prop_assert!(low_price <= high_price);
+        prop_assert!(low_price >= curve.base_price);
+        prop_assert!(high_price <= curve.max_price);
+    }
+
+    #[test]
+    fn price_tracks_the_exact_curve(curve in curves(), supply in any::<u64>()) {
+        let price = calculate_current_price(supply, &curve).unwrap() as f64;
+        let exact = exact_price(supply, &curve);
+        prop_assert!((price - exact).abs() <= 1.0 + exact * 1e-12, "{} vs {}", price, exact);
+    }
+
+    #[test]
+    fn every_token_moves_the_price(curve in smooth_curves(), supply in 0u64..10_000_000) {
+        let price = calculate_current_price(supply, &curve).unwrap();
+        let next = calculate_current_price(supply + 1, &curve).unwrap();
+        prop_assert!(next > price || next == curve.max_price);
+    }
+
+    #[test]
+    fn buy_cost_is_additive(
+        curve in curves(),
+        a in any::<u64>(),
+        b in any::<u64>(),
+        c in any::<u64>(),
+    ) {
+        let mut points = [a, b, c];
+        points.sort();
+        let [a, b, c] = points;
+
+        if let (Ok(first), Ok(second)) =
+            (calculate_buy_cost(a, b, &curve), calculate_buy_cost(b, c, &curve))
+        {
+            let whole = calculate_buy_cost(a, c, &curve);
+            prop_assert!(first.checked_add(second).map_or(whole.is_err(), |sum| whole.unwrap() == sum));
+        }
+    }
+
+    #[test]
+    fn buy_cost_lies_between_start_and_end_price(
+        curve in curves(),
+        a in any::<u64>(),
+        b in any::<u64>(),
+    ) {
+        let (from, to) = (a.min(b), a.max(b));
+        let amount = (to - from) as f64;
+        let floor = amount * calculate_current_price(from, &curve).unwrap() as f64;
+        let end_price = calculate_current_price(to, &curve).unwrap() as f64;
+        let ceiling = amount * end_price;
+        // Rounding of the two integrals, plus the exp error scaled by the integral's size
+        let slack = 2.0 + end_price * curve.scale_factor as f64 / curve.k_factor as f64 * 1e-14;
+
+        match calculate_buy_cost(from, to, &curve) {
+            Ok(cost) => {
+                let cost = cost as f64;
+                prop_assert!(floor * (1.0 - 1e-12) <= cost + slack, "{} < {}", cost, floor);
+                prop_assert!(cost <= ceiling * (1.0 + 1e-12) + slack, "{} > {}", cost, ceiling);
+            }
+            // Only a cost that cannot be represented may fail
+            Err(_) => prop_assert!(ceiling >= u64::MAX as f64 * (1.0 - 1e-12)),
+        }
+    }
+}
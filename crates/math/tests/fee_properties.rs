@@ -0,0 +1,84 @@
+use celebrity_exchange_math::{
+    calculate_fees, exact_fees, FeeSchedule, MathError, BPS_DENOMINATOR, FEE_PRECISION,
+};
+use proptest::prelude::*;
+
+fn fee_schedules() -> impl Strategy<Value = FeeSchedule> {
+    (0u64..=1_000, 0u64..=BPS_DENOMINATOR, 0u64..=1_000_000).prop_map(
+        |(transaction_fee_bps, platform_share, min_fee)| FeeSchedule {
+            transaction_fee_bps,
+            platform_fee_share_bps: platform_share,
+            creator_fee_share_bps: BPS_DENOMINATOR - platform_share,
+            min_fee,
+        },
+    )
+}
+
+proptest! {
+    #[test]
+    fn shares_add_up_to_the_fee(amount in any::<u64>(), fees in fee_schedules()) {
+        let (total, platform, creator) = calculate_fees(amount, &fees).unwrap();
+        prop_assert_eq!(platform + creator, total);
+        prop_assert!(total <= amount);
+    }
+
+    #[test]
+    fn fees_follow_their_unrounded_values(amount in any::<u64>(), fees in fee_schedules()) {
+        let (total, platform, creator) = calculate_fees(amount, &fees).unwrap();
+        let exact = exact_fees(amount, &fees).unwrap();
+        prop_assert_eq!(exact.platform + exact.creator, exact.total);
+
+        if fees.transaction_fee_bps == 0 {
+            prop_assert_eq!((exact.total, total), (0, 0));
+            return Ok(());
+        }
+        if exact.total / FEE_PRECISION >= u128::from(fees.min_fee) {
+            // Only rounding separates the fee from the percentage
+            prop_assert_eq!(u128::from(total), exact.total / FEE_PRECISION);
+            // The creator's share is rounded down and the platform keeps what is left
+            prop_assert!(u128::from(creator) * FEE_PRECISION <= exact.creator);
+            prop_assert!(u128::from(platform) * FEE_PRECISION + FEE_PRECISION > exact.platform);
+        } else {
+            prop_assert_eq!(total, fees.min_fee.min(amount));
+        }
+    }
+}
+
+#[test]
+fn small_trades_pay_the_minimum_fee() {
+    let fees = FeeSchedule {
+        transaction_fee_bps: 100,
+        platform_fee_share_bps: 7_000,
+        creator_fee_share_bps: 3_000,
+        min_fee: 5_000,
+    };
+
+    // 1% of 99 lamports rounds to nothing
+    assert_eq!(calculate_fees(99, &fees), Ok((99, 70, 29)));
+    assert_eq!(calculate_fees(10_000, &fees), Ok((5_000, 3_500, 1_500)));
+    assert_eq!(calculate_fees(1_000_001, &fees), Ok((10_000, 7_000, 3_000)));
+    // Rounding the creator's share down leaves the odd lamport with the platform
+    assert_eq!(calculate_fees(1_000_300, &fees), Ok((10_003, 7_003, 3_000)));
+    assert_eq!(calculate_fees(0, &fees), Ok((0, 0, 0)));
+
+    let free = FeeSchedule {
+        transaction_fee_bps: 0,
+        ..fees
+    };
+    assert_eq!(calculate_fees(10_000, &free), Ok((0, 0, 0)));
+}
+
+#[test]
+fn rejects_shares_that_do_not_add_up() {
+    let fees = FeeSchedule {
+        transaction_fee_bps: 100,
+        platform_fee_share_bps: 7_000,
+        creator_fee_share_bps: 2_000,
+        min_fee: 0,
+    };
+    assert_eq!(
+        calculate_fees(1_000, &fees),
+        Err(MathError::InvalidFeeSchedule)
+    );
+    assert_eq!(exact_fees(1_000, &fees), Err(MathError::InvalidFeeSchedule));
+}
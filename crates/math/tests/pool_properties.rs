@@ -0,0 +1,100 @@
+use celebrity_exchange_math::{
+    deposit_for_liquidity, initial_liquidity, quote_swap_buy, quote_swap_sell,
+    withdrawal_for_liquidity, FeeSchedule, BPS_DENOMINATOR,
+};
+use proptest::prelude::*;
+
+fn fee_schedules() -> impl Strategy<Value = FeeSchedule> {
+    (0u64..=1_000, 0u64..=BPS_DENOMINATOR, 0u64..=10_000).prop_map(
+        |(transaction_fee_bps, platform_share, min_fee)| FeeSchedule {
+            transaction_fee_bps,
+            platform_fee_share_bps: platform_share,
+            creator_fee_share_bps: BPS_DENOMINATOR - platform_share,
+            min_fee,
+        },
+    )
+}
+
+fn reserves() -> impl Strategy<Value = (u64, u64)> {
+    (1u64..=1 << 50, 1u64..=1 << 40)
+}
+
+fn k(sol_reserve: u64, token_reserve: u64) -> u128 {
+    sol_reserve as u128 * token_reserve as u128
+}
+
+proptest! {
+    #[test]
+    fn buys_never_shrink_k(
+        (sol, tokens) in reserves(),
+        sol_in in 0u64..=1 << 50,
+        fees in fee_schedules(),
+    ) {
+        let quote = quote_swap_buy(sol_in, sol, tokens, &fees).unwrap();
+        prop_assert_eq!(
+            quote.sol_reserve_change + quote.platform_fee + quote.creator_fee,
+            sol_in
+        );
+        prop_assert!(quote.amount_out < tokens);
+        prop_assert!(
+            k(sol + quote.sol_reserve_change, tokens - quote.amount_out) >= k(sol, tokens)
+        );
+    }
+
+    #[test]
+    fn sells_never_shrink_k(
+        (sol, tokens) in reserves(),
+        tokens_in in 0u64..=1 << 40,
+        fees in fee_schedules(),
+    ) {
+        let quote = quote_swap_sell(tokens_in, sol, tokens, &fees).unwrap();
+        prop_assert_eq!(
+            quote.amount_out + quote.platform_fee + quote.creator_fee,
+            quote.sol_reserve_change
+        );
+        prop_assert!(quote.sol_reserve_change < sol);
+        prop_assert!(
+            k(sol - quote.sol_reserve_change, tokens + tokens_in) >= k(sol, tokens)
+        );
+    }
+
+    #[test]
+    fn liquidity_round_trips_never_profit(
+        (sol, tokens) in reserves(),
+        supply_extra in 0u64..=1 << 30,
+        liquidity in 1u64..=1 << 30,
+    ) {
+        let supply = initial_liquidity(sol, tokens).max(1) + supply_extra;
+        let sol_in = deposit_for_liquidity(liquidity, sol, supply).unwrap();
+        let tokens_in = deposit_for_liquidity(liquidity, tokens, supply).unwrap();
+
+        let sol_out =
+            withdrawal_for_liquidity(liquidity, sol + sol_in, supply + liquidity).unwrap();
+        let tokens_out =
+            withdrawal_for_liquidity(liquidity, tokens + tokens_in, supply + liquidity).unwrap();
+        prop_assert!(sol_out <= sol_in);
+        prop_assert!(tokens_out <= tokens_in);
+    }
+}
+
+#[test]
+fn swaps_follow_the_constant_product() {
+    let fees = FeeSchedule {
+        transaction_fee_bps: 100,
+        platform_fee_share_bps: 7_000,
+        creator_fee_share_bps: 3_000,
+        min_fee: 5_000,
+    };
+
+    // 1% of the lamports in is the fee; the rest buys 1,000 × 990k / (1M + 990k) tokens
+    let buy = quote_swap_buy(1_000_000, 1_000_000, 1_000, &fees).unwrap();
+    assert_eq!((buy.platform_fee, buy.creator_fee), (7_000, 3_000));
+    assert_eq!(buy.sol_reserve_change, 990_000);
+    assert_eq!(buy.amount_out, 497);
+
+    // Doubling the token reserve releases half the SOL, less 1%
+    let sell = quote_swap_sell(1_000, 1_000_000, 1_000, &fees).unwrap();
+    assert_eq!(sell.sol_reserve_change, 500_000);
+    assert_eq!(sell.amount_out, 495_000);
+    assert_eq!((sell.platform_fee, sell.creator_fee), (3_500, 1_500));
+}
@@ -0,0 +1,79 @@
+use celebrity_exchange_math::{
+    calculate_buy_charge, calculate_current_price, max_tokens_for_budget, quote_buy, quote_sell,
+    CurveParams, CurveType, FeeSchedule, BPS_DENOMINATOR,
+};
+use proptest::prelude::*;
+
+fn curves() -> impl Strategy<Value = CurveParams> {
+    (
+        prop::sample::select(vec![
+            CurveType::Exponential,
+            CurveType::Linear,
+            CurveType::Sigmoid,
+            CurveType::ConstantProduct,
+        ]),
+        1u64..=1_000_000,
+        1u64..=100,
+        1_000u64..=100_000,
+    )
+        .prop_map(
+            |(curve_type, base_price, k_factor, scale_factor)| CurveParams {
+                curve_type,
+                base_price,
+                k_factor,
+                scale_factor,
+                max_price: base_price << 20,
+            },
+        )
+}
+
+fn fee_schedules() -> impl Strategy<Value = FeeSchedule> {
+    (0u64..=1_000, 0u64..=BPS_DENOMINATOR, 0u64..=10_000).prop_map(
+        |(transaction_fee_bps, platform_share, min_fee)| FeeSchedule {
+            transaction_fee_bps,
+            platform_fee_share_bps: platform_share,
+            creator_fee_share_bps: BPS_DENOMINATOR - platform_share,
+            min_fee,
+        },
+    )
+}
+
+proptest! {
+    #[test]
+    fn quotes_add_up(
+        curve in curves(),
+        fees in fee_schedules(),
+        start in 0u64..1_000_000,
+        amount in 1u64..1_000_000,
+    ) {
+        let buy = quote_buy(start, amount, &curve, &fees).unwrap();
+        prop_assert_eq!(buy.net_lamports, buy.total_cost + buy.platform_fee + buy.creator_fee);
+        prop_assert_eq!(buy.price_after, calculate_current_price(start + amount, &curve).unwrap());
+
+        // Selling straight back prices the same curve segment
+        let sell = quote_sell(start + amount, amount, &curve, &fees).unwrap();
+        prop_assert_eq!(sell.total_cost, buy.total_cost);
+        prop_assert_eq!(sell.net_lamports + sell.platform_fee + sell.creator_fee, sell.total_cost);
+        prop_assert_eq!(sell.price_after, calculate_current_price(start, &curve).unwrap());
+    }
+
+    #[test]
+    fn budget_buys_the_most_it_can_afford(
+        curve in curves(),
+        fees in fee_schedules(),
+        start in 0u64..1_000_000,
+        budget in any::<u64>(),
+    ) {
+        let available = 10_000_000;
+        let amount = max_tokens_for_budget(start, available, budget, &curve, &fees);
+        prop_assert!(amount <= available);
+
+        if amount > 0 {
+            prop_assert!(calculate_buy_charge(start, amount, &curve, &fees).unwrap() <= budget);
+        }
+        if amount < available {
+            let next = calculate_buy_charge(start, amount + 1, &curve, &fees);
+            prop_assert!(next.map_or(true, |charge| charge > budget));
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use celebrity_exchange_math::vested_amount;
+use proptest::prelude::*;
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn schedules() -> impl Strategy<Value = (i64, i64, i64)> {
+    (0i64..=1 << 40, 0i64..=4 * 365 * DAY, 1i64..=4 * 365 * DAY)
+        .prop_map(|(start, cliff, duration)| (start, start + cliff.min(duration), start + duration))
+}
+
+proptest! {
+    #[test]
+    fn releases_never_shrink_or_exceed_the_allocation(
+        total in any::<u64>(),
+        (start, cliff, end) in schedules(),
+        offset in 0i64..=5 * 365 * DAY,
+        step in 0i64..=365 * DAY,
+    ) {
+        let now = start + offset;
+        let vested = vested_amount(total, start, cliff, end, now);
+        let later = vested_amount(total, start, cliff, end, now + step);
+        prop_assert!(vested <= later);
+        prop_assert!(later <= total);
+    }
+}
+
+#[test]
+fn releases_on_a_cliff_then_linearly() {
+    let (start, cliff, end) = (1_000, 1_000 + 90 * DAY, 1_000 + 360 * DAY);
+
+    assert_eq!(vested_amount(36_000, start, cliff, end, start), 0);
+    assert_eq!(vested_amount(36_000, start, cliff, end, cliff - 1), 0);
+    // The cliff releases everything that accrued before it
+    assert_eq!(vested_amount(36_000, start, cliff, end, cliff), 9_000);
+    assert_eq!(
+        vested_amount(36_000, start, cliff, end, start + 180 * DAY),
+        18_000
+    );
+    assert_eq!(vested_amount(36_000, start, cliff, end, end), 36_000);
+    assert_eq!(vested_amount(36_000, start, cliff, end, end + DAY), 36_000);
+}
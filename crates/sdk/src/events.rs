@@ -0,0 +1,88 @@
+//! Event decoders.
+//!
+//! `emit!` logs each event as `Program data: <base64>`, the event's discriminator
+//! followed by its Borsh encoding. Logs interleave every program a transaction touches,
+//! so [`parse_logs`] follows the invocation stack and only decodes data this program logged.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Event};
+use base64::Engine;
+use celebrity_exchange::{
+    MarketClosed, MarketClosing, MarketCreated, MarketGraduated, MarketMetadataUpdated,
+    MarketPaused, MarketResumed, MintAuthorityMigrated, TokensRedeemed, TradeExecuted,
+};
+
+use crate::ID;
+
+pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// The events indexers care about: every one that moves a market's sequence number.
+pub enum ExchangeEvent {
+    MarketCreated(MarketCreated),
+    TradeExecuted(TradeExecuted),
+    MarketGraduated(MarketGraduated),
+    MarketClosing(MarketClosing),
+    TokensRedeemed(TokensRedeemed),
+    MarketClosed(MarketClosed),
+    MarketPaused(MarketPaused),
+    MarketResumed(MarketResumed),
+    MintAuthorityMigrated(MintAuthorityMigrated),
+    MarketMetadataUpdated(MarketMetadataUpdated),
+}
+
+impl ExchangeEvent {
+    /// Decodes one event from its raw `sol_log_data` bytes; `None` for other events.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        decode_event(data)
+            .map(ExchangeEvent::MarketCreated)
+            .or_else(|| decode_event(data).map(ExchangeEvent::TradeExecuted))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketGraduated))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketClosing))
+            .or_else(|| decode_event(data).map(ExchangeEvent::TokensRedeemed))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketClosed))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketPaused))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketResumed))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MintAuthorityMigrated))
+            .or_else(|| decode_event(data).map(ExchangeEvent::MarketMetadataUpdated))
+    }
+}
+
+/// Decodes an event of type `T` from its raw bytes; `None` if the discriminator differs
+/// or the payload does not decode.
+pub fn decode_event<T: Event + AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    let payload = data.strip_prefix(T::DISCRIMINATOR)?;
+    T::try_from_slice(payload).ok()
+}
+
+/// Every [`ExchangeEvent`] the program emitted in a transaction's logs, in order.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<ExchangeEvent> {
+    let mut stack: Vec<Pubkey> = Vec::new();
+    let mut events = Vec::new();
+
+    for log in logs {
+        let log = log.as_ref();
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA_PREFIX) {
+            if stack.last() != Some(&ID) {
+                continue;
+            }
+            let event = base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .ok()
+                .and_then(|data| ExchangeEvent::decode(&data));
+            events.extend(event);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            // "Program <id> invoke [depth]" and "Program <id> success" / "failed: ..."
+            let mut words = rest.split_whitespace();
+            let program = words.next().and_then(|id| id.parse::<Pubkey>().ok());
+            match (program, words.next()) {
+                (Some(program), Some("invoke")) => stack.push(program),
+                (Some(_), Some("success" | "failed:")) => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    events
+}
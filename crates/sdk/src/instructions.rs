@@ -0,0 +1,496 @@
+//! Instruction builders.
+//!
+//! Each builder fills in the program's own `accounts` struct, so account order always
+//! matches the program. Arguments are the program's `instruction` structs where an
+//! instruction takes more than a couple of them.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{
+    self, get_associated_token_address, spl_associated_token_account,
+};
+use anchor_spl::token::spl_token;
+use celebrity_exchange::{
+    accounts, instruction, ConfigParams, Market, Pool, TradeKind, TradingMode,
+};
+
+use crate::pda::{self, MarketAddresses, PoolAddresses};
+use crate::ID;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// `initialize_config`, signed by the program's upgrade authority.
+pub fn initialize_config(authority: Pubkey, admin: Pubkey, params: ConfigParams) -> Instruction {
+    build(
+        accounts::InitializeConfig {
+            authority,
+            config: pda::config(),
+            program_data: pda::program_data(),
+            system_program: system_program::ID,
+        },
+        instruction::InitializeConfig { admin, params },
+    )
+}
+
+fn update_config_accounts(admin: Pubkey) -> accounts::UpdateConfig {
+    accounts::UpdateConfig {
+        admin,
+        config: pda::config(),
+    }
+}
+
+pub fn update_config(admin: Pubkey, params: ConfigParams) -> Instruction {
+    build(
+        update_config_accounts(admin),
+        instruction::UpdateConfig { params },
+    )
+}
+
+pub fn propose_admin(admin: Pubkey, new_admin: Pubkey) -> Instruction {
+    build(
+        update_config_accounts(admin),
+        instruction::ProposeAdmin { new_admin },
+    )
+}
+
+/// `accept_admin`, signed by the proposed admin.
+pub fn accept_admin(pending_admin: Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAdmin {
+            pending_admin,
+            config: pda::config(),
+        },
+        instruction::AcceptAdmin {},
+    )
+}
+
+pub fn set_global_trading_mode(admin: Pubkey, mode: TradingMode) -> Instruction {
+    build(
+        update_config_accounts(admin),
+        instruction::SetGlobalTradingMode { mode },
+    )
+}
+
+pub fn set_market_trading_mode(admin: Pubkey, market: Pubkey, mode: TradingMode) -> Instruction {
+    build(
+        accounts::SetMarketTradingMode {
+            admin,
+            config: pda::config(),
+            market,
+        },
+        instruction::SetMarketTradingMode { mode },
+    )
+}
+
+/// `create_market` for a fresh `mint` keypair; `payer` becomes the market owner and
+/// must sign along with the mint. `fee_wallet` is the config's fee wallet. The vesting
+/// accounts are only passed along with a creator allocation.
+pub fn create_market(
+    payer: Pubkey,
+    mint: Pubkey,
+    fee_wallet: Pubkey,
+    args: instruction::CreateMarket,
+) -> Instruction {
+    let addresses = MarketAddresses::new(mint);
+    let vesting = args.creator_allocation.is_some();
+    build(
+        accounts::CreateMarket {
+            payer,
+            mint,
+            mint_authority: addresses.mint_authority,
+            market: addresses.market,
+            escrow_authority: addresses.escrow_authority,
+            escrow_token_account: addresses.escrow,
+            treasury: addresses.treasury,
+            metadata: addresses.metadata,
+            config: pda::config(),
+            platform_fee_wallet: fee_wallet,
+            system_program: system_program::ID,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            metadata_program: mpl_token_metadata::ID,
+            rent: sysvar::rent::ID,
+            vesting_schedule: vesting.then_some(addresses.vesting_schedule),
+            vesting_vault: vesting.then_some(addresses.vesting_vault),
+        },
+        args,
+    )
+}
+
+/// `migrate_mint_authority` for a market still minted by the legacy global authority.
+pub fn migrate_mint_authority(admin: Pubkey, market: Pubkey, mint: Pubkey) -> Instruction {
+    build(
+        accounts::MigrateMintAuthority {
+            admin,
+            config: pda::config(),
+            market,
+            treasury: pda::treasury(&market),
+            mint,
+            legacy_mint_authority: pda::legacy_mint_authority(),
+            mint_authority: pda::mint_authority(&mint),
+            metadata: pda::metadata(&mint),
+            token_program: spl_token::ID,
+            metadata_program: mpl_token_metadata::ID,
+            system_program: system_program::ID,
+        },
+        instruction::MigrateMintAuthority {},
+    )
+}
+
+/// `update_market_metadata`, signed by the market owner.
+pub fn update_market_metadata(
+    owner: Pubkey,
+    market: Pubkey,
+    mint: Pubkey,
+    args: instruction::UpdateMarketMetadata,
+) -> Instruction {
+    build(
+        accounts::UpdateMarketMetadata {
+            owner,
+            market,
+            mint,
+            mint_authority: pda::mint_authority(&mint),
+            metadata: pda::metadata(&mint),
+            metadata_program: mpl_token_metadata::ID,
+        },
+        args,
+    )
+}
+
+/// Accounts for trading on `market` through `user`'s associated token account.
+/// `fee_wallet` is the config's fee wallet.
+pub fn buy_sell_accounts(
+    user: Pubkey,
+    market_address: Pubkey,
+    market: &Market,
+    fee_wallet: Pubkey,
+) -> accounts::BuySell {
+    accounts::BuySell {
+        user,
+        market: market_address,
+        escrow_authority: pda::escrow_authority(&market_address),
+        escrow_token_account: market.escrow,
+        treasury: market.treasury,
+        user_token_account: get_associated_token_address(&user, &market.mint),
+        config: pda::config(),
+        platform_fee_wallet: fee_wallet,
+        creator_wallet: market.owner,
+        token_program: spl_token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn buy_tokens(accounts: accounts::BuySell, amount: u64, max_cost_lamports: u64) -> Instruction {
+    build(
+        accounts,
+        instruction::BuyTokens {
+            amount,
+            max_cost_lamports,
+        },
+    )
+}
+
+pub fn buy_tokens_exact_in(
+    accounts: accounts::BuySell,
+    max_spend_lamports: u64,
+    min_tokens_out: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::BuyTokensExactIn {
+            max_spend_lamports,
+            min_tokens_out,
+        },
+    )
+}
+
+pub fn sell_tokens(
+    accounts: accounts::BuySell,
+    amount: u64,
+    min_receive_lamports: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::SellTokens {
+            amount,
+            min_receive_lamports,
+        },
+    )
+}
+
+/// Creates `user`'s token account for `mint` unless it already exists; buyers need one.
+pub fn create_user_token_account(payer: Pubkey, user: Pubkey, mint: Pubkey) -> Instruction {
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        &payer,
+        &user,
+        &mint,
+        &spl_token::ID,
+    )
+}
+
+/// `get_market_details`; simulate it and decode the return data as `MarketDetails`.
+pub fn get_market_details(market: Pubkey) -> Instruction {
+    build(
+        accounts::GetMarket { market },
+        instruction::GetMarketDetails {},
+    )
+}
+
+/// `quote_buy`; simulate it and decode the return data as `TradeQuote`.
+pub fn quote_buy(market: Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::Quote {
+            market,
+            config: pda::config(),
+        },
+        instruction::QuoteBuy { amount },
+    )
+}
+
+/// `quote_sell`; simulate it and decode the return data as `TradeQuote`.
+pub fn quote_sell(market: Pubkey, amount: u64) -> Instruction {
+    build(
+        accounts::Quote {
+            market,
+            config: pda::config(),
+        },
+        instruction::QuoteSell { amount },
+    )
+}
+
+/// `graduate_market` for the market of `mint`; anyone may send it once the market has
+/// reached its graduation threshold, and `payer` funds the new pool accounts.
+pub fn graduate_market(payer: Pubkey, mint: Pubkey) -> Instruction {
+    let addresses = MarketAddresses::new(mint);
+    build(
+        accounts::GraduateMarket {
+            payer,
+            config: pda::config(),
+            market: addresses.market,
+            mint,
+            escrow_authority: addresses.escrow_authority,
+            escrow_token_account: addresses.escrow,
+            treasury: addresses.treasury,
+            pool: addresses.pool.pool,
+            pool_token_vault: addresses.pool.token_vault,
+            pool_sol_vault: addresses.pool.sol_vault,
+            pool_lp_mint: addresses.pool.lp_mint,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::GraduateMarket {},
+    )
+}
+
+/// `close_market`, signed by the market owner or the config admin.
+pub fn close_market(authority: Pubkey, market: Pubkey) -> Instruction {
+    build(
+        accounts::CloseMarket {
+            authority,
+            config: pda::config(),
+            market,
+            treasury: pda::treasury(&market),
+        },
+        instruction::CloseMarket {},
+    )
+}
+
+/// Accounts for redeeming tokens of a closing `market` from `user`'s associated token
+/// account.
+pub fn redeem_accounts(user: Pubkey, market_address: Pubkey, market: &Market) -> accounts::Redeem {
+    accounts::Redeem {
+        user,
+        market: market_address,
+        mint: market.mint,
+        treasury: market.treasury,
+        user_token_account: get_associated_token_address(&user, &market.mint),
+        token_program: spl_token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn redeem_tokens(accounts: accounts::Redeem, amount: u64) -> Instruction {
+    build(accounts, instruction::RedeemTokens { amount })
+}
+
+/// `finalize_close`; anyone may send it once the market's tokens are all redeemed or
+/// its grace period is over. The vesting accounts are passed when the market has a
+/// creator allocation that the first run has not burned yet.
+pub fn finalize_close(market_address: Pubkey, market: &Market) -> Instruction {
+    let vesting = market.creator_allocation > 0 && !market.escrow_closed;
+    let addresses = MarketAddresses::new(market.mint);
+    build(
+        accounts::FinalizeClose {
+            market: market_address,
+            owner: market.owner,
+            mint: market.mint,
+            escrow_authority: pda::escrow_authority(&market_address),
+            escrow_token_account: market.escrow,
+            treasury: market.treasury,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            vesting_schedule: vesting.then_some(addresses.vesting_schedule),
+            vesting_vault: vesting.then_some(addresses.vesting_vault),
+        },
+        instruction::FinalizeClose {},
+    )
+}
+
+/// `claim_vested` for the market of `mint`, paid into `beneficiary`'s associated token
+/// account.
+pub fn claim_vested(beneficiary: Pubkey, mint: Pubkey) -> Instruction {
+    let addresses = MarketAddresses::new(mint);
+    build(
+        accounts::ClaimVested {
+            beneficiary,
+            market: addresses.market,
+            vesting_schedule: addresses.vesting_schedule,
+            vault: addresses.vesting_vault,
+            beneficiary_token_account: get_associated_token_address(&beneficiary, &mint),
+            token_program: spl_token::ID,
+        },
+        instruction::ClaimVested {},
+    )
+}
+
+/// `verify_market`; simulate it and decode the return data as `SolvencyReport`.
+pub fn verify_market(market: Pubkey) -> Instruction {
+    build(
+        accounts::VerifyMarket {
+            market,
+            treasury: pda::treasury(&market),
+        },
+        instruction::VerifyMarket {},
+    )
+}
+
+/// `initialize_pool` for `mint`, funded from `creator`'s associated token account.
+/// The creator's LP token account is created along with the pool.
+pub fn initialize_pool(
+    creator: Pubkey,
+    mint: Pubkey,
+    sol_amount: u64,
+    token_amount: u64,
+) -> Instruction {
+    let addresses = PoolAddresses::new(mint);
+    build(
+        accounts::InitializePool {
+            creator,
+            config: pda::config(),
+            mint,
+            market: pda::market(&mint),
+            pool: addresses.pool,
+            token_vault: addresses.token_vault,
+            sol_vault: addresses.sol_vault,
+            lp_mint: addresses.lp_mint,
+            creator_token_account: get_associated_token_address(&creator, &mint),
+            creator_lp_account: get_associated_token_address(&creator, &addresses.lp_mint),
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        instruction::InitializePool {
+            sol_amount,
+            token_amount,
+        },
+    )
+}
+
+/// Accounts for swapping against `pool` through `user`'s associated token account.
+/// `fee_wallet` is the config's fee wallet.
+pub fn swap_accounts(
+    user: Pubkey,
+    pool_address: Pubkey,
+    pool: &Pool,
+    fee_wallet: Pubkey,
+) -> accounts::Swap {
+    accounts::Swap {
+        user,
+        config: pda::config(),
+        pool: pool_address,
+        market: pool.market,
+        token_vault: pool.token_vault,
+        sol_vault: pool.sol_vault,
+        user_token_account: get_associated_token_address(&user, &pool.mint),
+        platform_fee_wallet: fee_wallet,
+        creator_wallet: pool.creator,
+        token_program: spl_token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+/// `swap`: `amount_in` is lamports on a buy and tokens on a sell.
+pub fn swap(
+    accounts: accounts::Swap,
+    kind: TradeKind,
+    amount_in: u64,
+    min_amount_out: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::Swap {
+            kind,
+            amount_in,
+            min_amount_out,
+        },
+    )
+}
+
+/// Accounts for adding liquidity to or removing it from `pool` through `user`'s
+/// associated token accounts for the token and the LP mint.
+pub fn liquidity_accounts(user: Pubkey, pool_address: Pubkey, pool: &Pool) -> accounts::Liquidity {
+    accounts::Liquidity {
+        user,
+        config: pda::config(),
+        pool: pool_address,
+        market: pool.market,
+        token_vault: pool.token_vault,
+        sol_vault: pool.sol_vault,
+        lp_mint: pool.lp_mint,
+        user_token_account: get_associated_token_address(&user, &pool.mint),
+        user_lp_account: get_associated_token_address(&user, &pool.lp_mint),
+        token_program: spl_token::ID,
+        system_program: system_program::ID,
+    }
+}
+
+pub fn add_liquidity(
+    accounts: accounts::Liquidity,
+    liquidity: u64,
+    max_sol: u64,
+    max_tokens: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::AddLiquidity {
+            liquidity,
+            max_sol,
+            max_tokens,
+        },
+    )
+}
+
+pub fn remove_liquidity(
+    accounts: accounts::Liquidity,
+    liquidity: u64,
+    min_sol: u64,
+    min_tokens: u64,
+) -> Instruction {
+    build(
+        accounts,
+        instruction::RemoveLiquidity {
+            liquidity,
+            min_sol,
+            min_tokens,
+        },
+    )
+}
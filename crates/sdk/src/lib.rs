@@ -0,0 +1,19 @@
+//! Rust client for the celebrity exchange program.
+//!
+//! - [`pda`] derives every program address a market or pool needs
+//! - [`instructions`] builds each instruction with its accounts in program order
+//! - [`state`] decodes `Market`, `Pool`, `VestingSchedule` and `GlobalConfig` accounts and
+//!   quotes trades offline
+//! - [`events`] pulls `MarketCreated` and `TradeExecuted` out of transaction logs
+//!
+//! Account and event types are the program's own, reexported through [`program`], so a
+//! layout change in the program is a compile error here rather than a decoding bug.
+
+pub mod events;
+pub mod instructions;
+pub mod pda;
+pub mod state;
+
+pub use celebrity_exchange as program;
+pub use celebrity_exchange::ID;
+pub use celebrity_exchange_math as math;
@@ -0,0 +1,132 @@
+//! Program derived addresses.
+
+use anchor_lang::prelude::{ProgramData, Pubkey};
+use anchor_lang::Owner;
+use anchor_spl::associated_token::get_associated_token_address;
+
+use crate::ID;
+
+/// The global `GlobalConfig` account.
+pub fn config() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &ID).0
+}
+
+/// The upgradeable loader's ProgramData account, checked by `initialize_config`.
+pub fn program_data() -> Pubkey {
+    Pubkey::find_program_address(&[ID.as_ref()], &ProgramData::owner()).0
+}
+
+/// The single mint authority markets used before `migrate_mint_authority`.
+pub fn legacy_mint_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"mint-authority"], &ID).0
+}
+
+/// Mint, freeze and metadata update authority of one market's mint.
+pub fn mint_authority(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"mint-authority", mint.as_ref()], &ID).0
+}
+
+pub fn market(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"market", mint.as_ref()], &ID).0
+}
+
+/// Owner of the market's escrow token account.
+pub fn escrow_authority(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"escrow", market.as_ref()], &ID).0
+}
+
+/// System account holding the lamports paid into the market's curve.
+pub fn treasury(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"treasury", market.as_ref()], &ID).0
+}
+
+/// The market creator's `VestingSchedule`, which also owns the vault of their allocation.
+pub fn vesting_schedule(market: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vesting", market.as_ref()], &ID).0
+}
+
+/// Constant-product pool `mint` trades in, once its market graduates or someone opens
+/// one with `initialize_pool`.
+pub fn pool(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool", mint.as_ref()], &ID).0
+}
+
+/// System account holding a pool's SOL.
+pub fn pool_sol_vault(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool-sol", pool.as_ref()], &ID).0
+}
+
+/// Mint of a pool's LP tokens.
+pub fn pool_lp_mint(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"pool-lp", pool.as_ref()], &ID).0
+}
+
+/// Metaplex metadata account of a mint.
+pub fn metadata(mint: &Pubkey) -> Pubkey {
+    mpl_token_metadata::accounts::Metadata::find_pda(mint).0
+}
+
+/// Every address belonging to the market of `mint`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarketAddresses {
+    pub mint: Pubkey,
+    pub mint_authority: Pubkey,
+    pub metadata: Pubkey,
+    pub market: Pubkey,
+    pub escrow_authority: Pubkey,
+    /// Associated token account of the escrow authority holding unsold supply
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    /// Only created for markets with a creator allocation
+    pub vesting_schedule: Pubkey,
+    /// Associated token account of the vesting schedule holding the allocation
+    pub vesting_vault: Pubkey,
+    /// The pool the market graduates into
+    pub pool: PoolAddresses,
+}
+
+impl MarketAddresses {
+    pub fn new(mint: Pubkey) -> Self {
+        let market = market(&mint);
+        let escrow_authority = escrow_authority(&market);
+        let vesting_schedule = vesting_schedule(&market);
+
+        Self {
+            mint,
+            mint_authority: mint_authority(&mint),
+            metadata: metadata(&mint),
+            market,
+            escrow_authority,
+            escrow: get_associated_token_address(&escrow_authority, &mint),
+            treasury: treasury(&market),
+            vesting_schedule,
+            vesting_vault: get_associated_token_address(&vesting_schedule, &mint),
+            pool: PoolAddresses::new(mint),
+        }
+    }
+}
+
+/// Every address belonging to the pool of `mint`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAddresses {
+    pub mint: Pubkey,
+    pub pool: Pubkey,
+    /// Associated token account of the pool holding its tokens
+    pub token_vault: Pubkey,
+    pub sol_vault: Pubkey,
+    pub lp_mint: Pubkey,
+}
+
+impl PoolAddresses {
+    pub fn new(mint: Pubkey) -> Self {
+        let pool = pool(&mint);
+
+        Self {
+            mint,
+            pool,
+            token_vault: get_associated_token_address(&pool, &mint),
+            sol_vault: pool_sol_vault(&pool),
+            lp_mint: pool_lp_mint(&pool),
+        }
+    }
+}
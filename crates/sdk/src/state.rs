@@ -0,0 +1,71 @@
+//! Account decoders, and offline quotes from decoded accounts.
+
+use anchor_lang::{AccountDeserialize, Result};
+use celebrity_exchange::{GlobalConfig, LegacyMarket, Market, Pool, TradeKind, VestingSchedule};
+use celebrity_exchange_math as math;
+
+/// Decodes a `Market` account's data, discriminator included.
+pub fn decode_market(data: &[u8]) -> Result<Market> {
+    Market::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a market account still in the legacy layout `migrate_mint_authority` upgrades.
+pub fn decode_legacy_market(data: &[u8]) -> Result<LegacyMarket> {
+    LegacyMarket::try_from_account_data(data)
+}
+
+/// Decodes a `Pool` account's data, discriminator included.
+pub fn decode_pool(data: &[u8]) -> Result<Pool> {
+    Pool::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a `VestingSchedule` account's data, discriminator included.
+pub fn decode_vesting_schedule(data: &[u8]) -> Result<VestingSchedule> {
+    VestingSchedule::try_deserialize(&mut &data[..])
+}
+
+/// Decodes the `GlobalConfig` account's data, discriminator included.
+pub fn decode_config(data: &[u8]) -> Result<GlobalConfig> {
+    GlobalConfig::try_deserialize(&mut &data[..])
+}
+
+/// What buying `amount` tokens costs, priced exactly as `buy_tokens` would price it
+/// against these accounts.
+pub fn quote_buy(market: &Market, config: &GlobalConfig, amount: u64) -> math::Result<math::Quote> {
+    math::quote_buy(
+        market.circulating_supply,
+        amount,
+        &market.curve.into(),
+        &config.fee_schedule(),
+    )
+}
+
+/// What selling `amount` tokens pays out, priced exactly as `sell_tokens` would.
+pub fn quote_sell(
+    market: &Market,
+    config: &GlobalConfig,
+    amount: u64,
+) -> math::Result<math::Quote> {
+    math::quote_sell(
+        market.circulating_supply,
+        amount,
+        &market.curve.into(),
+        &config.fee_schedule(),
+    )
+}
+
+/// What swapping `amount_in` against a pool holding these reserves yields, priced
+/// exactly as `swap` would. The SOL reserve leaves out the vault's rent-exempt minimum.
+pub fn quote_swap(
+    config: &GlobalConfig,
+    kind: TradeKind,
+    amount_in: u64,
+    sol_reserve: u64,
+    token_reserve: u64,
+) -> math::Result<math::SwapQuote> {
+    let fees = config.fee_schedule();
+    match kind {
+        TradeKind::Buy => math::quote_swap_buy(amount_in, sol_reserve, token_reserve, &fees),
+        TradeKind::Sell => math::quote_swap_sell(amount_in, sol_reserve, token_reserve, &fees),
+    }
+}
@@ -0,0 +1,196 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountSerialize, Event};
+use base64::Engine;
+use celebrity_exchange_sdk::events::{parse_logs, ExchangeEvent};
+use celebrity_exchange_sdk::program::{
+    CurveParams, CurveType, GraduationThreshold, Market, MarketClosing, MarketCreated,
+    MarketStatus, TokensRedeemed, TradeExecuted, TradeKind, TradingMode, FEE_PRECISION,
+};
+use celebrity_exchange_sdk::state::decode_market;
+use celebrity_exchange_sdk::ID;
+
+fn program_data(event: &impl Event) -> String {
+    format!(
+        "Program data: {}",
+        base64::engine::general_purpose::STANDARD.encode(event.data())
+    )
+}
+
+fn trade(tokens: u64) -> TradeExecuted {
+    TradeExecuted {
+        market: Pubkey::new_unique(),
+        buyer: Some(Pubkey::new_unique()),
+        seller: None,
+        tokens,
+        price: 1_000_000,
+        total_cost: 1_000_000 * tokens,
+        platform_fee: 7,
+        creator_fee: 3,
+        fee_unrounded: 10 * FEE_PRECISION,
+        platform_fee_unrounded: 7 * FEE_PRECISION,
+        creator_fee_unrounded: 3 * FEE_PRECISION,
+        kind: TradeKind::Buy,
+        sequence: 1,
+        circulating_supply_before: 0,
+        circulating_supply_after: tokens,
+        treasury_balance: 890_880 + 1_000_000 * tokens,
+        timestamp: 1_700_000_000,
+    }
+}
+
+#[test]
+fn parse_logs_decodes_only_this_programs_events() {
+    let other = Pubkey::new_unique();
+    let created = MarketCreated {
+        market: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        initial_price: 1_000_000,
+        initial_supply: 1_000,
+        curve_type: CurveType::Linear,
+        base_price: 1_000_000,
+        k_factor: 5,
+        scale_factor: 10_000,
+        max_price: 2_000_000,
+        name: "Celebrity".to_string(),
+        symbol: "CELEB".to_string(),
+        uri: "https://example.com/celebrity.json".to_string(),
+        sequence: 0,
+        circulating_supply: 0,
+        treasury_balance: 890_880,
+        timestamp: 1_700_000_000,
+    };
+
+    let logs = vec![
+        format!("Program {ID} invoke [1]"),
+        "Program log: Instruction: CreateMarket".to_string(),
+        // Identical bytes logged by another program must not count
+        format!("Program {other} invoke [2]"),
+        program_data(&trade(1)),
+        format!("Program {other} success"),
+        program_data(&created),
+        format!("Program {ID} success"),
+        format!("Program {ID} invoke [1]"),
+        program_data(&trade(5)),
+        format!("Program {ID} consumed 1000 of 200000 compute units"),
+        format!("Program {ID} success"),
+    ];
+
+    let events = parse_logs(&logs);
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        ExchangeEvent::MarketCreated(event) => {
+            assert_eq!(event.mint, created.mint);
+            assert_eq!(event.curve_type, CurveType::Linear);
+            assert_eq!(event.symbol, "CELEB");
+        }
+        _ => panic!("expected MarketCreated first"),
+    }
+    match &events[1] {
+        ExchangeEvent::TradeExecuted(event) => {
+            assert_eq!(event.tokens, 5);
+            assert!(event.kind == TradeKind::Buy);
+        }
+        _ => panic!("expected TradeExecuted second"),
+    }
+}
+
+#[test]
+fn parse_logs_decodes_lifecycle_events() {
+    let market = Pubkey::new_unique();
+    let closing = MarketClosing {
+        market,
+        initiated_by: Pubkey::new_unique(),
+        sequence: 2,
+        circulating_supply: 100,
+        treasury_balance: 100_890_880,
+        grace_period_ends_at: 1_700_604_800,
+        timestamp: 1_700_000_000,
+    };
+    let redeemed = TokensRedeemed {
+        market,
+        holder: Pubkey::new_unique(),
+        tokens: 100,
+        lamports: 100_000_000,
+        sequence: 3,
+        circulating_supply_before: 100,
+        circulating_supply_after: 0,
+        treasury_balance: 890_880,
+        timestamp: 1_700_000_001,
+    };
+    let logs = vec![
+        format!("Program {ID} invoke [1]"),
+        program_data(&closing),
+        program_data(&redeemed),
+        format!("Program {ID} success"),
+    ];
+
+    let events = parse_logs(&logs);
+    assert_eq!(events.len(), 2);
+    match &events[0] {
+        ExchangeEvent::MarketClosing(event) => {
+            assert_eq!(event.sequence, 2);
+            assert_eq!(event.circulating_supply, 100);
+        }
+        _ => panic!("expected MarketClosing first"),
+    }
+    match &events[1] {
+        ExchangeEvent::TokensRedeemed(event) => {
+            assert_eq!(event.sequence, 3);
+            assert_eq!(
+                (
+                    event.circulating_supply_before,
+                    event.circulating_supply_after
+                ),
+                (100, 0)
+            );
+        }
+        _ => panic!("expected TokensRedeemed second"),
+    }
+}
+
+#[test]
+fn decode_market_round_trips_and_checks_the_discriminator() {
+    let market = Market {
+        owner: Pubkey::new_unique(),
+        mint: Pubkey::new_unique(),
+        escrow: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        current_price: 1_500_000,
+        total_supply: 1_000_000,
+        circulating_supply: 42,
+        trade_count: 3,
+        curve: CurveParams {
+            curve_type: CurveType::Sigmoid,
+            base_price: 1_000_000,
+            k_factor: 5,
+            scale_factor: 10_000,
+            max_price: 2_000_000,
+        },
+        trading_mode: TradingMode::SellOnly,
+        metadata_locked: true,
+        name: "Celebrity".to_string(),
+        symbol: "CELEB".to_string(),
+        uri: "https://example.com/celebrity.json".to_string(),
+        graduation: GraduationThreshold::MarketCap(85_000_000_000),
+        status: MarketStatus::Active,
+        closing_started_at: 0,
+        creator_allocation: 0,
+        escrow_closed: false,
+    };
+    let mut data = Vec::new();
+    market.try_serialize(&mut data).unwrap();
+    // Accounts are allocated at their maximum size
+    data.resize(8 + Market::MAX_SIZE, 0);
+
+    let decoded = decode_market(&data).unwrap();
+    assert_eq!(decoded.mint, market.mint);
+    assert_eq!(decoded.circulating_supply, 42);
+    assert_eq!(decoded.curve, market.curve);
+    assert_eq!(decoded.trading_mode, TradingMode::SellOnly);
+    assert_eq!(decoded.uri, market.uri);
+    assert_eq!(decoded.graduation, market.graduation);
+
+    data[0] ^= 1;
+    assert!(decode_market(&data).is_err());
+}
@@ -0,0 +1,117 @@
+#![no_main]
+
+//! Replays arbitrary buy/sell sequences against a `Market` and checks that the treasury
+//! always holds at least the curve integral of the outstanding supply.
+
+use anchor_lang::prelude::Pubkey;
+use arbitrary::Arbitrary;
+use celebrity_exchange::{
+    CurveParams, CurveType, GraduationThreshold, Market, MarketStatus, TradingMode,
+};
+use celebrity_exchange_math::{calculate_buy_cost, calculate_current_price};
+use libfuzzer_sys::fuzz_target;
+
+// Lamports the treasury holds before any trade
+const TREASURY_RENT: u128 = 890_880;
+
+#[derive(Arbitrary, Debug)]
+enum Trade {
+    Buy(u64),
+    Sell(u64),
+}
+
+const CURVE_TYPES: [CurveType; 4] = [
+    CurveType::Exponential,
+    CurveType::Linear,
+    CurveType::Sigmoid,
+    CurveType::ConstantProduct,
+];
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    curve_type: u8,
+    base_price: u64,
+    k_factor: u64,
+    scale_factor: u64,
+    max_price: u64,
+    trades: Vec<Trade>,
+}
+
+fuzz_target!(|input: Input| {
+    let curve = CurveParams {
+        curve_type: CURVE_TYPES[input.curve_type as usize % CURVE_TYPES.len()],
+        base_price: input.base_price,
+        k_factor: input.k_factor,
+        scale_factor: input.scale_factor,
+        max_price: input.max_price,
+    };
+    // create_market refuses anything else
+    if curve.validate().is_err() {
+        return;
+    }
+    let math_curve: celebrity_exchange_math::CurveParams = curve.into();
+
+    let mut market = Market {
+        owner: Pubkey::default(),
+        mint: Pubkey::default(),
+        escrow: Pubkey::default(),
+        treasury: Pubkey::default(),
+        current_price: curve.base_price,
+        total_supply: u64::MAX,
+        circulating_supply: 0,
+        trade_count: 0,
+        curve,
+        trading_mode: TradingMode::Active,
+        metadata_locked: false,
+        name: String::new(),
+        symbol: String::new(),
+        uri: String::new(),
+        // Out of reach, so the curve takes every trade
+        graduation: GraduationThreshold::SoldSupply(u64::MAX),
+        status: MarketStatus::Active,
+        closing_started_at: 0,
+        creator_allocation: 0,
+        escrow_closed: false,
+    };
+    let mut treasury = TREASURY_RENT;
+
+    for trade in input.trades {
+        let before = market.circulating_supply;
+        match trade {
+            Trade::Buy(amount) => match market.apply_buy(amount) {
+                Ok(cost) => treasury += cost as u128,
+                Err(_) => {
+                    assert_eq!(market.circulating_supply, before);
+                    continue;
+                }
+            },
+            Trade::Sell(amount) => {
+                let amount = amount.min(market.circulating_supply);
+                match market.apply_sell(amount) {
+                    Ok(value) => {
+                        let value = value as u128;
+                        assert!(value <= treasury - TREASURY_RENT, "treasury overdrawn");
+                        treasury -= value;
+                    }
+                    // Only a sale worth more than any u64 treasury may fail
+                    Err(_) => {
+                        assert!(treasury - TREASURY_RENT > u64::MAX as u128);
+                        assert_eq!(market.circulating_supply, before);
+                        continue;
+                    }
+                }
+            }
+        }
+
+        assert_eq!(
+            market.current_price,
+            calculate_current_price(market.circulating_supply, &math_curve).unwrap()
+        );
+        if let Ok(integral) = calculate_buy_cost(0, market.circulating_supply, &math_curve) {
+            assert!(
+                treasury - TREASURY_RENT >= integral as u128,
+                "treasury {treasury} below integral {integral}"
+            );
+        }
+    }
+});
@@ -1,457 +1,1843 @@
+// create_market's arguments; the generated CPI wrapper adds a context on top
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_option::COption;
 use anchor_lang::system_program;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::metadata::{create_metadata_accounts_v3, CreateMetadataAccountsV3, Metadata};
-use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount, Transfer};
+use anchor_spl::metadata::{
+    create_metadata_accounts_v3, update_metadata_accounts_v2, CreateMetadataAccountsV3, Metadata,
+    UpdateMetadataAccountsV2,
+};
+use anchor_spl::token::spl_token::instruction::AuthorityType;
+use anchor_spl::token::{
+    self, Burn, CloseAccount, Mint, MintTo, SetAuthority, Token, TokenAccount, Transfer,
+};
+use celebrity_exchange_math as math;
 use mpl_token_metadata::types::DataV2;
 
 declare_id!("6RYHxeQ4turMqubZkmLg4UB9AeNRbgW9tR5L2uQ7VJ4f");
 
-pub const PLATFORM_FEE_WALLET: &str = "2XchL7rEESZFJPEUgR4RjvduHdTR9J8ANqvsuzqmLUkv";
-pub const CREATION_FEE: u64 = 100_000_000; // 0.1 SOL
-pub const TRANSACTION_FEE_BPS: u64 = 100; // 1% = 100 basis points
-pub const PLATFORM_FEE_SHARE_BPS: u64 = 7000; // 70% = 7000 basis points
-pub const CREATOR_FEE_SHARE_BPS: u64 = 3000; // 30% = 3000 basis points
-                                             // P = BASE_PRICE × e^(K × supply / SCALE_FACTOR)
-pub const BASE_PRICE: u64 = 1_000_000; // 0.001 SOL
+pub const BPS_DENOMINATOR: u64 = math::BPS_DENOMINATOR; // 100% in basis points
+pub const MAX_TRANSACTION_FEE_BPS: u64 = 1_000; // Fees are capped at 10% of a trade
+pub const MAX_MIN_TRADE_FEE: u64 = 10_000_000; // No trade is charged a floor above 0.01 SOL
+pub const FEE_PRECISION: u128 = math::FEE_PRECISION; // Unrounded fee units per lamport
+
+// Default exponential curve: P = base_price × e^(K × supply / SCALE_FACTOR)
+pub const BASE_PRICE: u64 = 1_000_000; // 0.001 SOL, reference price for clients
 pub const K_FACTOR: u64 = 5; // Growth rate (adjust for steepness)
 pub const SCALE_FACTOR: u64 = 10_000; // Scale to prevent overflow
-
-#[program]
-pub mod celebrity_exchange {
+pub const DEFAULT_MAX_PRICE_MULTIPLIER: u64 = 1 << 20; // Default cap: base price × 2^20
+pub const DEFAULT_GRADUATION_SUPPLY_BPS: u64 = 8_000; // Graduate once 80% of the supply has sold
+pub const MINIMUM_LIQUIDITY: u64 = 1_000; // Locked for good in every pool opened by initialize_pool
+pub const CLOSE_GRACE_PERIOD: i64 = 7 * 24 * 60 * 60; // Seconds holders have to redeem once a market closes
+pub const MAX_CREATOR_ALLOCATION_BPS: u64 = 2_000; // A creator may reserve at most 20% of the supply
+
+// `#[program]` emits its IDL handlers next to the module it annotates, and they still call
+// the deprecated `AccountInfo::realloc`; this module keeps the allow to them
+#[allow(deprecated)]
+mod handlers {
     use super::*;
-    pub fn create_market(
-        ctx: Context<CreateMarket>,
-        initial_price_lamports: u64,
-        initial_supply: u64,
-        name: String,
-        symbol: String,
-        uri: String,
-    ) -> Result<()> {
-        require!(name.len() <= 32, ExchangeError::InvalidMetadata);
-        require!(symbol.len() <= 10, ExchangeError::InvalidMetadata);
-        require!(uri.len() <= 200, ExchangeError::InvalidMetadata);
-
-        // Charge creation fee
-        system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.payer.to_account_info(),
-                    to: ctx.accounts.platform_fee_wallet.to_account_info(),
-                },
-            ),
-            CREATION_FEE,
-        )?;
-
-        // Initialize market
-        let market = &mut ctx.accounts.market;
-        market.owner = *ctx.accounts.payer.key;
-        market.mint = ctx.accounts.mint.key();
-        market.escrow = ctx.accounts.escrow_token_account.key();
-        market.treasury = ctx.accounts.treasury.key();
-        market.current_price = initial_price_lamports;
-        market.total_supply = initial_supply;
-        market.circulating_supply = 0; //Track circulating supply for bonding curve
-        market.trade_count = 0;
-        market.name = name.clone();
-        market.symbol = symbol.clone();
-        market.uri = uri.clone();
-
-        // Mint tokens to escrow
-        let mint_bump = ctx.bumps.mint_authority;
-        let signer_seeds: &[&[u8]] = &[b"mint-authority".as_ref(), &[mint_bump]];
-
-        token::mint_to(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                MintTo {
-                    mint: ctx.accounts.mint.to_account_info(),
-                    to: ctx.accounts.escrow_token_account.to_account_info(),
-                    authority: ctx.accounts.mint_authority.to_account_info(),
-                },
-                &[&signer_seeds[..]],
-            ),
-            initial_supply,
-        )?;
-
-        // Create token metadata
-        create_metadata_accounts_v3(
-            CpiContext::new_with_signer(
-                ctx.accounts.metadata_program.to_account_info(),
-                CreateMetadataAccountsV3 {
-                    metadata: ctx.accounts.metadata.to_account_info(),
-                    mint: ctx.accounts.mint.to_account_info(),
-                    mint_authority: ctx.accounts.mint_authority.to_account_info(),
-                    update_authority: ctx.accounts.mint_authority.to_account_info(),
-                    payer: ctx.accounts.payer.to_account_info(),
-                    system_program: ctx.accounts.system_program.to_account_info(),
-                    rent: ctx.accounts.rent.to_account_info(),
-                },
-                &[&signer_seeds[..]],
-            ),
-            DataV2 {
-                name,
-                symbol,
-                uri,
-                seller_fee_basis_points: 0,
-                creators: None,
-                collection: None,
-                uses: None,
-            },
-            true,
-            true,
-            None,
-        )?;
-
-        emit!(MarketCreated {
-            market: market.key(),
-            owner: market.owner,
-            mint: market.mint,
-            initial_price: market.current_price,
-            initial_supply,
-            name: market.name.clone(),
-            symbol: market.symbol.clone(),
-            uri: market.uri.clone(),
-            timestamp: Clock::get()?.unix_timestamp,
-        });
-
-        Ok(())
-    }
-
-    pub fn buy_tokens(ctx: Context<BuySell>, amount: u64) -> Result<()> {
-        require!(amount > 0, ExchangeError::InvalidAmount);
-        let market = &mut ctx.accounts.market;
 
-        // ✅ NEW: Calculate price using exponential bonding curve
-        let new_circulating = market
-            .circulating_supply
-            .checked_add(amount)
-            .ok_or(ExchangeError::MathError)?;
-
-        let total_cost = calculate_buy_cost(
-            market.circulating_supply,
-            new_circulating,
-            market.current_price,
-        )?;
-
-        // Calculate 1% platform fee
-        let total_fee = (total_cost as u128)
-            .checked_mul(TRANSACTION_FEE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        // ✅ NEW: Split fee 70/30 between platform and creator
-        let platform_fee = (total_fee as u128)
-            .checked_mul(PLATFORM_FEE_SHARE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        let creator_fee = (total_fee as u128)
-            .checked_mul(CREATOR_FEE_SHARE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        msg!("💰 Buy Details:");
-        msg!("  Total Cost: {} lamports", total_cost);
-        msg!("  Platform Fee (70%): {} lamports", platform_fee);
-        msg!("  Creator Fee (30%): {} lamports", creator_fee);
-
-        // Transfer SOL to treasury
-        system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.user.to_account_info(),
-                    to: ctx.accounts.treasury.to_account_info(),
-                },
-            ),
-            total_cost,
-        )?;
-
-        // ✅ Transfer 70% to platform
-        system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.user.to_account_info(),
-                    to: ctx.accounts.platform_fee_wallet.to_account_info(),
-                },
-            ),
-            platform_fee,
-        )?;
-
-        // ✅ Transfer 30% to creator
-        system_program::transfer(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                system_program::Transfer {
-                    from: ctx.accounts.user.to_account_info(),
-                    to: ctx.accounts.creator_wallet.to_account_info(),
-                },
-            ),
-            creator_fee,
-        )?;
-
-        // Transfer tokens from escrow to buyer
-        let market_key = market.key();
-        let escrow_bump = ctx.bumps.escrow_authority;
-        let escrow_seeds: &[&[u8]] = &[b"escrow".as_ref(), market_key.as_ref(), &[escrow_bump]];
-
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.escrow_token_account.to_account_info(),
-                    to: ctx.accounts.user_token_account.to_account_info(),
-                    authority: ctx.accounts.escrow_authority.to_account_info(),
+    #[program]
+    pub mod celebrity_exchange {
+        use super::*;
+
+        pub fn initialize_config(
+            ctx: Context<InitializeConfig>,
+            admin: Pubkey,
+            params: ConfigParams,
+        ) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            config.admin = admin;
+            config.pending_admin = None;
+            config.trading_mode = TradingMode::Active;
+            config.bump = ctx.bumps.config;
+            config.apply(params)?;
+
+            emit!(config.updated_event()?);
+
+            Ok(())
+        }
+
+        pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            config.apply(params)?;
+
+            emit!(config.updated_event()?);
+
+            Ok(())
+        }
+
+        pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            config.pending_admin = Some(new_admin);
+
+            emit!(AdminProposed {
+                admin: config.admin,
+                pending_admin: new_admin,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+            let config = &mut ctx.accounts.config;
+            let previous_admin = config.admin;
+            config.admin = ctx.accounts.pending_admin.key();
+            config.pending_admin = None;
+
+            emit!(AdminTransferred {
+                previous_admin,
+                new_admin: config.admin,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        pub fn set_global_trading_mode(
+            ctx: Context<UpdateConfig>,
+            mode: TradingMode,
+        ) -> Result<()> {
+            ctx.accounts.config.trading_mode = mode;
+            emit_trading_mode_change(None, mode)
+        }
+
+        pub fn set_market_trading_mode(
+            ctx: Context<SetMarketTradingMode>,
+            mode: TradingMode,
+        ) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            market.trading_mode = mode;
+            emit_trading_mode_change(Some(market), mode)
+        }
+
+        pub fn create_market(
+            ctx: Context<CreateMarket>,
+            initial_price_lamports: u64,
+            initial_supply: u64,
+            name: String,
+            symbol: String,
+            uri: String,
+            curve_type: CurveType,
+            max_price_lamports: Option<u64>,
+            graduation_threshold: Option<GraduationThreshold>,
+            creator_allocation: Option<VestingParams>,
+            curve_growth: Option<CurveGrowth>,
+        ) -> Result<()> {
+            require!(
+                ctx.accounts.config.trading_mode == TradingMode::Active,
+                ExchangeError::Paused
+            );
+            validate_metadata(&name, &symbol, &uri)?;
+
+            // The creator's allocation comes out of the supply; only the rest is sold on the curve
+            let allocation = match creator_allocation {
+                Some(params) => {
+                    params.validate(initial_supply)?;
+                    params.amount
+                }
+                None => 0,
+            };
+            require!(
+                creator_allocation.is_some() == ctx.accounts.vesting_schedule.is_some()
+                    && creator_allocation.is_some() == ctx.accounts.vesting_vault.is_some(),
+                ExchangeError::InvalidVestingSchedule
+            );
+            let curve_supply = initial_supply - allocation;
+
+            // Without an explicit cap the price may grow to 2^20 times the starting price
+            let max_price = match max_price_lamports {
+                Some(max_price) => max_price,
+                None => initial_price_lamports
+                    .checked_mul(DEFAULT_MAX_PRICE_MULTIPLIER)
+                    .ok_or(ExchangeError::InvalidCurveParams)?,
+            };
+            // Without explicit growth every curve climbs at K_FACTOR / SCALE_FACTOR
+            let (k_factor, scale_factor) = match curve_growth {
+                Some(growth) => growth.factors(curve_type)?,
+                None => (K_FACTOR, SCALE_FACTOR),
+            };
+            let curve = CurveParams {
+                curve_type,
+                base_price: initial_price_lamports,
+                k_factor,
+                scale_factor,
+                max_price,
+            };
+            curve.validate()?;
+
+            // By default the market graduates once most of its curve supply has sold, keeping
+            // the rest back to open its pool with
+            let graduation = match graduation_threshold {
+                Some(threshold) => threshold,
+                None => GraduationThreshold::SoldSupply(
+                    ((curve_supply as u128) * (DEFAULT_GRADUATION_SUPPLY_BPS as u128)
+                        / (BPS_DENOMINATOR as u128)) as u64,
+                ),
+            };
+            graduation.validate(curve_supply)?;
+
+            // Charge creation fee
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.platform_fee_wallet.to_account_info(),
+                    },
+                ),
+                ctx.accounts.config.creation_fee,
+            )?;
+
+            // Fund the treasury to rent exemption up front, so the first buy does not have to
+            // and selling every token back cannot take it below
+            let treasury_rent = Rent::get()?
+                .minimum_balance(0)
+                .saturating_sub(ctx.accounts.treasury.lamports());
+            if treasury_rent > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: ctx.accounts.treasury.to_account_info(),
+                        },
+                    ),
+                    treasury_rent,
+                )?;
+            }
+
+            // Initialize market
+            let market = &mut ctx.accounts.market;
+            market.owner = *ctx.accounts.payer.key;
+            market.mint = ctx.accounts.mint.key();
+            market.escrow = ctx.accounts.escrow_token_account.key();
+            market.treasury = ctx.accounts.treasury.key();
+            market.current_price =
+                math::calculate_current_price(0, &curve.into()).map_err(ExchangeError::from)?;
+            market.total_supply = initial_supply;
+            market.circulating_supply = 0; //Track circulating supply for bonding curve
+            market.trade_count = 0;
+            market.curve = curve;
+            market.trading_mode = TradingMode::Active;
+            market.metadata_locked = false;
+            market.name = name.clone();
+            market.symbol = symbol.clone();
+            market.uri = uri.clone();
+            market.graduation = graduation;
+            market.status = MarketStatus::Active;
+            market.closing_started_at = 0;
+            market.creator_allocation = allocation;
+            market.escrow_closed = false;
+
+            // Mint tokens to escrow
+            let mint_key = ctx.accounts.mint.key();
+            let mint_bump = ctx.bumps.mint_authority;
+            let signer_seeds: &[&[u8]] =
+                &[b"mint-authority".as_ref(), mint_key.as_ref(), &[mint_bump]];
+
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.escrow_token_account.to_account_info(),
+                        authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                curve_supply,
+            )?;
+
+            // Lock the creator's allocation in the vesting schedule's vault
+            if let (Some(params), Some(schedule), Some(vault)) = (
+                creator_allocation,
+                ctx.accounts.vesting_schedule.as_mut(),
+                ctx.accounts.vesting_vault.as_ref(),
+            ) {
+                token::mint_to(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        MintTo {
+                            mint: ctx.accounts.mint.to_account_info(),
+                            to: vault.to_account_info(),
+                            authority: ctx.accounts.mint_authority.to_account_info(),
+                        },
+                        &[signer_seeds],
+                    ),
+                    params.amount,
+                )?;
+
+                let now = Clock::get()?.unix_timestamp;
+                schedule.market = market.key();
+                schedule.beneficiary = market.owner;
+                schedule.mint = market.mint;
+                schedule.vault = vault.key();
+                schedule.total_amount = params.amount;
+                schedule.claimed_amount = 0;
+                schedule.start_time = now;
+                schedule.cliff_time = now
+                    .checked_add(params.cliff_seconds)
+                    .ok_or(ExchangeError::InvalidVestingSchedule)?;
+                schedule.end_time = now
+                    .checked_add(params.duration_seconds)
+                    .ok_or(ExchangeError::InvalidVestingSchedule)?;
+                schedule.bump = ctx
+                    .bumps
+                    .vesting_schedule
+                    .ok_or(ExchangeError::InvalidVestingSchedule)?;
+
+                emit!(VestingScheduleCreated {
+                    market: schedule.market,
+                    schedule: schedule.key(),
+                    beneficiary: schedule.beneficiary,
+                    mint: schedule.mint,
+                    total_amount: schedule.total_amount,
+                    start_time: schedule.start_time,
+                    cliff_time: schedule.cliff_time,
+                    end_time: schedule.end_time,
+                });
+            }
+
+            // Create token metadata
+            create_metadata_accounts_v3(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    CreateMetadataAccountsV3 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        mint_authority: ctx.accounts.mint_authority.to_account_info(),
+                        update_authority: ctx.accounts.mint_authority.to_account_info(),
+                        payer: ctx.accounts.payer.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        rent: ctx.accounts.rent.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                DataV2 {
+                    name,
+                    symbol,
+                    uri,
+                    seller_fee_basis_points: 0,
+                    creators: None,
+                    collection: None,
+                    uses: None,
                 },
-                &[&escrow_seeds[..]],
-            ),
-            amount,
-        )?;
-
-        // ✅ Update market state
-        market.circulating_supply = new_circulating;
-        market.current_price = calculate_current_price(new_circulating, market.current_price)?;
-        market.trade_count = market.trade_count.saturating_add(1);
-
-        emit!(TradeExecuted {
-            market: market.key(),
-            buyer: Some(*ctx.accounts.user.key),
-            seller: None,
-            tokens: amount,
-            price: market.current_price,
-            total_cost,
-            platform_fee,
-            creator_fee,
-            kind: TradeKind::Buy,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
-
-        Ok(())
+                true,
+                true,
+                None,
+            )?;
+
+            emit!(MarketCreated {
+                market: market.key(),
+                owner: market.owner,
+                mint: market.mint,
+                initial_price: market.current_price,
+                initial_supply,
+                curve_type: curve.curve_type,
+                base_price: curve.base_price,
+                k_factor: curve.k_factor,
+                scale_factor: curve.scale_factor,
+                max_price: curve.max_price,
+                name: market.name.clone(),
+                symbol: market.symbol.clone(),
+                uri: market.uri.clone(),
+                sequence: market.trade_count,
+                circulating_supply: market.circulating_supply,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Moves a market created before per-market authorities from the legacy global
+        // PDA to its own mint, freeze and metadata update authority
+        pub fn migrate_mint_authority(ctx: Context<MigrateMintAuthority>) -> Result<()> {
+            // Such markets are still in the legacy layout; rewrite them in the current one,
+            // the admin topping up rent for the larger account
+            let market_info = ctx.accounts.market.to_account_info();
+            let legacy = LegacyMarket::try_from_account_data(&market_info.try_borrow_data()?).ok();
+            let is_legacy = legacy.is_some();
+            let mut market = match legacy {
+                Some(legacy) => legacy.upgrade(),
+                None => Market::try_deserialize(&mut &market_info.try_borrow_data()?[..])?,
+            };
+            require_keys_eq!(
+                market.mint,
+                ctx.accounts.mint.key(),
+                ExchangeError::InvalidMint
+            );
+            let sequence = market.next_sequence()?;
+
+            if is_legacy {
+                let space = 8 + Market::MAX_SIZE;
+                let top_up = Rent::get()?
+                    .minimum_balance(space)
+                    .saturating_sub(market_info.lamports());
+                if top_up > 0 {
+                    transfer_lamports(
+                        &ctx.accounts.system_program,
+                        &ctx.accounts.admin,
+                        &ctx.accounts.market,
+                        top_up,
+                        &[],
+                    )?;
+                }
+                market_info.resize(space)?;
+            }
+            market.try_serialize(&mut &mut market_info.try_borrow_mut_data()?[..])?;
+
+            // Legacy treasuries only hold what the stepped curve charged and were never
+            // funded to rent exemption, so the admin covers whatever the market's curve
+            // now needs to buy back the circulating supply
+            let shortfall = market
+                .solvency(
+                    ctx.accounts.treasury.lamports(),
+                    Rent::get()?.minimum_balance(0),
+                )?
+                .shortfall;
+            if shortfall > 0 {
+                transfer_lamports(
+                    &ctx.accounts.system_program,
+                    &ctx.accounts.admin,
+                    &ctx.accounts.treasury,
+                    shortfall,
+                    &[],
+                )?;
+            }
+
+            let legacy_bump = ctx.bumps.legacy_mint_authority;
+            let legacy_seeds: &[&[u8]] = &[b"mint-authority".as_ref(), &[legacy_bump]];
+            let new_authority = ctx.accounts.mint_authority.key();
+
+            for authority_type in [AuthorityType::MintTokens, AuthorityType::FreezeAccount] {
+                token::set_authority(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        SetAuthority {
+                            current_authority: ctx.accounts.legacy_mint_authority.to_account_info(),
+                            account_or_mint: ctx.accounts.mint.to_account_info(),
+                        },
+                        &[legacy_seeds],
+                    ),
+                    authority_type,
+                    Some(new_authority),
+                )?;
+            }
+
+            update_metadata_accounts_v2(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    UpdateMetadataAccountsV2 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        update_authority: ctx.accounts.legacy_mint_authority.to_account_info(),
+                    },
+                    &[legacy_seeds],
+                ),
+                Some(new_authority),
+                None,
+                None,
+                None,
+            )?;
+
+            emit!(MintAuthorityMigrated {
+                market: ctx.accounts.market.key(),
+                mint: ctx.accounts.mint.key(),
+                mint_authority: new_authority,
+                sequence,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Lets the creator fix name, symbol or uri; `lock` freezes them for good
+        pub fn update_market_metadata(
+            ctx: Context<UpdateMarketMetadata>,
+            name: String,
+            symbol: String,
+            uri: String,
+            lock: bool,
+        ) -> Result<()> {
+            validate_metadata(&name, &symbol, &uri)?;
+
+            let mint_key = ctx.accounts.mint.key();
+            let mint_bump = ctx.bumps.mint_authority;
+            let signer_seeds: &[&[u8]] =
+                &[b"mint-authority".as_ref(), mint_key.as_ref(), &[mint_bump]];
+
+            update_metadata_accounts_v2(
+                CpiContext::new_with_signer(
+                    ctx.accounts.metadata_program.to_account_info(),
+                    UpdateMetadataAccountsV2 {
+                        metadata: ctx.accounts.metadata.to_account_info(),
+                        update_authority: ctx.accounts.mint_authority.to_account_info(),
+                    },
+                    &[signer_seeds],
+                ),
+                None,
+                Some(DataV2 {
+                    name: name.clone(),
+                    symbol: symbol.clone(),
+                    uri: uri.clone(),
+                    seller_fee_basis_points: 0,
+                    creators: None,
+                    collection: None,
+                    uses: None,
+                }),
+                None,
+                lock.then_some(false),
+            )?;
+
+            let market = &mut ctx.accounts.market;
+            market.name = name;
+            market.symbol = symbol;
+            market.uri = uri;
+            market.metadata_locked = lock;
+
+            emit!(MarketMetadataUpdated {
+                market: market.key(),
+                sequence: market.next_sequence()?,
+                name: market.name.clone(),
+                symbol: market.symbol.clone(),
+                uri: market.uri.clone(),
+                locked: market.metadata_locked,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        pub fn buy_tokens(
+            ctx: Context<BuySell>,
+            amount: u64,
+            max_cost_lamports: u64,
+        ) -> Result<()> {
+            execute_buy(ctx, amount, max_cost_lamports)
+        }
+
+        pub fn buy_tokens_exact_in(
+            ctx: Context<BuySell>,
+            max_spend_lamports: u64,
+            min_tokens_out: u64,
+        ) -> Result<()> {
+            let market = &ctx.accounts.market;
+            let amount = math::max_tokens_for_budget(
+                market.circulating_supply,
+                ctx.accounts
+                    .escrow_token_account
+                    .amount
+                    .min(market.curve_tokens_left()),
+                max_spend_lamports,
+                &market.curve.into(),
+                &ctx.accounts.config.fee_schedule(),
+            );
+
+            require!(amount > 0, ExchangeError::InvalidAmount);
+            require!(amount >= min_tokens_out, ExchangeError::SlippageExceeded);
+
+            execute_buy(ctx, amount, max_spend_lamports)
+        }
+
+        pub fn sell_tokens(
+            ctx: Context<BuySell>,
+            amount: u64,
+            min_receive_lamports: u64,
+        ) -> Result<()> {
+            require!(amount > 0, ExchangeError::InvalidAmount);
+            let market = &mut ctx.accounts.market;
+
+            // Holders can always exit in sell-only mode
+            require!(
+                market.effective_trading_mode(&ctx.accounts.config) != TradingMode::Paused,
+                ExchangeError::Paused
+            );
+            require!(
+                market.status != MarketStatus::Closing,
+                ExchangeError::MarketClosing
+            );
+            require!(!market.curve_complete(), ExchangeError::CurveComplete);
+            require!(
+                amount <= market.circulating_supply,
+                ExchangeError::InvalidAmount
+            );
+
+            // ✅ NEW: Calculate sell value using bonding curve
+            let supply_before = market.circulating_supply;
+            let total_value = market.apply_sell(amount)?;
+
+            // Calculate fee, split between platform and creator
+            let fees = ctx.accounts.config.fee_schedule();
+            let math::Quote {
+                platform_fee,
+                creator_fee,
+                net_lamports: user_receives,
+                ..
+            } = math::Quote::sell(amount, total_value, market.current_price, &fees)
+                .map_err(ExchangeError::from)?;
+            let unrounded = math::exact_fees(total_value, &fees).map_err(ExchangeError::from)?;
+
+            // The minimum fee can swallow a small sale whole; the seller would give up
+            // their tokens for nothing
+            require!(user_receives > 0, ExchangeError::SaleProceedsTooSmall);
+            require!(
+                user_receives >= min_receive_lamports,
+                ExchangeError::SlippageExceeded
+            );
+
+            msg!("💸 Sell Details:");
+            msg!("  Total Value: {} lamports", total_value);
+            msg!("  Platform Fee: {} lamports", platform_fee);
+            msg!("  Creator Fee: {} lamports", creator_fee);
+            msg!("  User Receives: {} lamports", user_receives);
+
+            // Transfer tokens from seller to escrow
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.user_token_account.to_account_info(),
+                        to: ctx.accounts.escrow_token_account.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+
+            let market_key = market.key();
+            let treasury_bump = ctx.bumps.treasury;
+            let treasury_seeds: &[&[u8]] =
+                &[b"treasury".as_ref(), market_key.as_ref(), &[treasury_bump]];
+
+            // Transfer SOL to seller (minus fees)
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.treasury.key(),
+                    &ctx.accounts.user.key(),
+                    user_receives,
+                ),
+                &[
+                    ctx.accounts.treasury.to_account_info(),
+                    ctx.accounts.user.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[treasury_seeds],
+            )?;
+
+            // ✅ Transfer platform fee (70%)
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.treasury.key(),
+                    &ctx.accounts.platform_fee_wallet.key(),
+                    platform_fee,
+                ),
+                &[
+                    ctx.accounts.treasury.to_account_info(),
+                    ctx.accounts.platform_fee_wallet.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[treasury_seeds],
+            )?;
+
+            // ✅ Transfer creator fee (30%)
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.treasury.key(),
+                    &ctx.accounts.creator_wallet.key(),
+                    creator_fee,
+                ),
+                &[
+                    ctx.accounts.treasury.to_account_info(),
+                    ctx.accounts.creator_wallet.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[treasury_seeds],
+            )?;
+
+            market.check_solvency(ctx.accounts.treasury.lamports())?;
+
+            emit!(TradeExecuted {
+                market: market.key(),
+                buyer: None,
+                seller: Some(*ctx.accounts.user.key),
+                tokens: amount,
+                price: market.current_price,
+                total_cost: total_value,
+                platform_fee,
+                creator_fee,
+                fee_unrounded: unrounded.total,
+                platform_fee_unrounded: unrounded.platform,
+                creator_fee_unrounded: unrounded.creator,
+                kind: TradeKind::Sell,
+                sequence: market.trade_count,
+                circulating_supply_before: supply_before,
+                circulating_supply_after: market.circulating_supply,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        pub fn get_market_details(ctx: Context<GetMarket>) -> Result<MarketDetails> {
+            let market = &ctx.accounts.market;
+
+            Ok(MarketDetails {
+                owner: market.owner,
+                mint: market.mint,
+                escrow: market.escrow,
+                treasury: market.treasury,
+                current_price: market.current_price,
+                total_supply: market.total_supply,
+                circulating_supply: market.circulating_supply,
+                trade_count: market.trade_count,
+                curve: market.curve,
+                trading_mode: market.trading_mode,
+                metadata_locked: market.metadata_locked,
+                name: market.name.clone(),
+                symbol: market.symbol.clone(),
+                uri: market.uri.clone(),
+                graduation: market.graduation,
+                status: market.status,
+                closing_started_at: market.closing_started_at,
+                creator_allocation: market.creator_allocation,
+                escrow_closed: market.escrow_closed,
+            })
+        }
+
+        // Read-only: what buying `amount` tokens would cost right now. Meant for
+        // simulateTransaction; the quote comes back as return data.
+        pub fn quote_buy(ctx: Context<Quote>, amount: u64) -> Result<TradeQuote> {
+            require!(amount > 0, ExchangeError::InvalidAmount);
+            let market = &ctx.accounts.market;
+            require!(
+                amount <= market.curve_tokens_left(),
+                ExchangeError::InvalidAmount
+            );
+
+            let quote = math::quote_buy(
+                market.circulating_supply,
+                amount,
+                &market.curve.into(),
+                &ctx.accounts.config.fee_schedule(),
+            )
+            .map_err(ExchangeError::from)?;
+            Ok(quote.into())
+        }
+
+        // Read-only: what selling `amount` tokens would pay out right now
+        pub fn quote_sell(ctx: Context<Quote>, amount: u64) -> Result<TradeQuote> {
+            require!(amount > 0, ExchangeError::InvalidAmount);
+            let market = &ctx.accounts.market;
+            require!(
+                amount <= market.circulating_supply,
+                ExchangeError::InvalidAmount
+            );
+
+            let quote = math::quote_sell(
+                market.circulating_supply,
+                amount,
+                &market.curve.into(),
+                &ctx.accounts.config.fee_schedule(),
+            )
+            .map_err(ExchangeError::from)?;
+            // `sell_tokens` rejects the same sales
+            require!(quote.net_lamports > 0, ExchangeError::SaleProceedsTooSmall);
+            Ok(quote.into())
+        }
+
+        // Permissionless once the market has reached its graduation threshold: moves the
+        // treasury's SOL above rent and the unsold escrow tokens into a pool owned by the
+        // program. The liquidity they make is locked in the pool for good.
+        pub fn graduate_market(ctx: Context<GraduateMarket>) -> Result<()> {
+            // A market graduates once: its pool account can only be created the first time
+            let market = &mut ctx.accounts.market;
+            require!(
+                market.status == MarketStatus::Active,
+                ExchangeError::MarketNotActive
+            );
+            require!(
+                market.graduation_reached(),
+                ExchangeError::GraduationThresholdNotReached
+            );
+            require!(
+                market.effective_trading_mode(&ctx.accounts.config) == TradingMode::Active,
+                ExchangeError::Paused
+            );
+
+            let rent = Rent::get()?.minimum_balance(0);
+            let sol_reserve = ctx.accounts.treasury.lamports().saturating_sub(rent);
+            let token_reserve = ctx.accounts.escrow_token_account.amount;
+            let locked_liquidity = math::initial_liquidity(sol_reserve, token_reserve);
+            require!(locked_liquidity > 0, ExchangeError::InsufficientLiquidity);
+
+            // The vault holds the pool's SOL, so it has to stay rent-exempt on its own
+            let vault_rent = rent.saturating_sub(ctx.accounts.pool_sol_vault.lamports());
+            if vault_rent > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        ctx.accounts.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: ctx.accounts.payer.to_account_info(),
+                            to: ctx.accounts.pool_sol_vault.to_account_info(),
+                        },
+                    ),
+                    vault_rent,
+                )?;
+            }
+
+            let market_key = market.key();
+            let treasury_bump = ctx.bumps.treasury;
+            let treasury_seeds: &[&[u8]] =
+                &[b"treasury".as_ref(), market_key.as_ref(), &[treasury_bump]];
+            anchor_lang::solana_program::program::invoke_signed(
+                &anchor_lang::solana_program::system_instruction::transfer(
+                    &ctx.accounts.treasury.key(),
+                    &ctx.accounts.pool_sol_vault.key(),
+                    sol_reserve,
+                ),
+                &[
+                    ctx.accounts.treasury.to_account_info(),
+                    ctx.accounts.pool_sol_vault.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[treasury_seeds],
+            )?;
+
+            let escrow_bump = ctx.bumps.escrow_authority;
+            let escrow_seeds: &[&[u8]] = &[b"escrow".as_ref(), market_key.as_ref(), &[escrow_bump]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.escrow_token_account.to_account_info(),
+                        to: ctx.accounts.pool_token_vault.to_account_info(),
+                        authority: ctx.accounts.escrow_authority.to_account_info(),
+                    },
+                    &[escrow_seeds],
+                ),
+                token_reserve,
+            )?;
+
+            // No LP tokens are minted: all of the opening liquidity stays locked, and the
+            // market owner earns the creator share of the pool's swap fees
+            let pool = &mut ctx.accounts.pool;
+            pool.market = Some(market_key);
+            pool.mint = market.mint;
+            pool.creator = market.owner;
+            pool.token_vault = ctx.accounts.pool_token_vault.key();
+            pool.sol_vault = ctx.accounts.pool_sol_vault.key();
+            pool.lp_mint = ctx.accounts.pool_lp_mint.key();
+            pool.locked_liquidity = locked_liquidity;
+            pool.bump = ctx.bumps.pool;
+
+            market.status = MarketStatus::Graduated;
+
+            emit!(MarketGraduated {
+                market: market_key,
+                pool: pool.key(),
+                mint: market.mint,
+                circulating_supply: market.circulating_supply,
+                final_price: market.current_price,
+                sol_reserve,
+                token_reserve,
+                locked_liquidity,
+                sequence: market.next_sequence()?,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Winds a market down, at its owner's or the admin's request. Trading stops for good
+        // and holders redeem their tokens against the treasury; once every token is back or
+        // CLOSE_GRACE_PERIOD has passed, `finalize_close` returns the accounts' rent.
+        pub fn close_market(ctx: Context<CloseMarket>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            require!(
+                market.status == MarketStatus::Active,
+                ExchangeError::MarketNotActive
+            );
+
+            let now = Clock::get()?.unix_timestamp;
+            market.status = MarketStatus::Closing;
+            market.closing_started_at = now;
+
+            emit!(MarketClosing {
+                market: market.key(),
+                initiated_by: ctx.accounts.authority.key(),
+                sequence: market.next_sequence()?,
+                circulating_supply: market.circulating_supply,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                grace_period_ends_at: now.saturating_add(CLOSE_GRACE_PERIOD),
+                timestamp: now,
+            });
+
+            Ok(())
+        }
+
+        // Burns `amount` tokens of a closing market for their pro-rata share of the treasury
+        // above rent. Every token is worth the same share, whoever redeems first.
+        pub fn redeem_tokens(ctx: Context<Redeem>, amount: u64) -> Result<()> {
+            require!(amount > 0, ExchangeError::InvalidAmount);
+            let market = &mut ctx.accounts.market;
+            require!(
+                market.status == MarketStatus::Closing,
+                ExchangeError::MarketNotClosing
+            );
+
+            let lamports = market.redemption_value(
+                amount,
+                ctx.accounts.treasury.lamports(),
+                Rent::get()?.minimum_balance(0),
+            )?;
+
+            token::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        from: ctx.accounts.user_token_account.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+            let supply_before = market.circulating_supply;
+            market.circulating_supply -= amount;
+
+            let market_key = market.key();
+            let treasury_seeds: &[&[u8]] = &[
+                b"treasury".as_ref(),
+                market_key.as_ref(),
+                &[ctx.bumps.treasury],
+            ];
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.treasury,
+                &ctx.accounts.user,
+                lamports,
+                &[treasury_seeds],
+            )?;
+
+            emit!(TokensRedeemed {
+                market: market_key,
+                holder: ctx.accounts.user.key(),
+                tokens: amount,
+                lamports,
+                sequence: market.next_sequence()?,
+                circulating_supply_before: supply_before,
+                circulating_supply_after: market.circulating_supply,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Permissionless once a closing market's tokens are all redeemed or its grace period
+        // is over: burns the unsold supply and closes the escrow, returning its rent to the
+        // market owner. The treasury keeps backing any tokens still out, which holders can
+        // redeem for as long as they like; once the last is redeemed, running this again
+        // closes the treasury and market and returns their rent to the owner too.
+        pub fn finalize_close(ctx: Context<FinalizeClose>) -> Result<()> {
+            let market = &mut ctx.accounts.market;
+            require!(
+                market.status == MarketStatus::Closing,
+                ExchangeError::MarketNotClosing
+            );
+            let now = Clock::get()?.unix_timestamp;
+            require!(
+                market.circulating_supply == 0
+                    || now >= market.closing_started_at.saturating_add(CLOSE_GRACE_PERIOD),
+                ExchangeError::RedemptionWindowOpen
+            );
+
+            // Closed already if this ran once while tokens were still out. Anyone can
+            // recreate the escrow's associated token account after that, so only the flag
+            // tells whether this is the first run.
+            let escrow = &ctx.accounts.escrow_token_account;
+            let first_run = !market.escrow_closed;
+            require!(
+                first_run || market.circulating_supply == 0,
+                ExchangeError::RedemptionWindowOpen
+            );
+
+            let market_key = market.key();
+            let mut lamports_returned = 0;
+            let mut schedule_spent = false;
+            if first_run {
+                let unsold = TokenAccount::try_deserialize(&mut &escrow.data.borrow()[..])?.amount;
+                lamports_returned += escrow.lamports();
+
+                let escrow_seeds: &[&[u8]] = &[
+                    b"escrow".as_ref(),
+                    market_key.as_ref(),
+                    &[ctx.bumps.escrow_authority],
+                ];
+                token::burn(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Burn {
+                            mint: ctx.accounts.mint.to_account_info(),
+                            from: escrow.to_account_info(),
+                            authority: ctx.accounts.escrow_authority.to_account_info(),
+                        },
+                        &[escrow_seeds],
+                    ),
+                    unsold,
+                )?;
+                token::close_account(CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    CloseAccount {
+                        account: escrow.to_account_info(),
+                        destination: ctx.accounts.owner.to_account_info(),
+                        authority: ctx.accounts.escrow_authority.to_account_info(),
+                    },
+                    &[escrow_seeds],
+                ))?;
+                market.escrow_closed = true;
+            }
+
+            // A market only starts closing while its curve still trades, before any of the
+            // creator allocation is claimable, so all of it is burned with the unsold supply.
+            // The schedule is spent after that, and later runs neither need nor touch it.
+            if first_run && market.creator_allocation > 0 {
+                let (Some(schedule), Some(vault)) = (
+                    ctx.accounts.vesting_schedule.as_ref(),
+                    ctx.accounts.vesting_vault.as_ref(),
+                ) else {
+                    return err!(ExchangeError::InvalidVestingSchedule);
+                };
+                require_keys_eq!(
+                    vault.key(),
+                    schedule.vault,
+                    ExchangeError::InvalidTokenAccount
+                );
+                lamports_returned +=
+                    vault.to_account_info().lamports() + schedule.to_account_info().lamports();
+
+                let schedule_seeds: &[&[u8]] =
+                    &[b"vesting".as_ref(), market_key.as_ref(), &[schedule.bump]];
+                token::burn(
+                    CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        Burn {
+                            mint: ctx.accounts.mint.to_account_info(),
+                            from: vault.to_account_info(),
+                            authority: schedule.to_account_info(),
+                        },
+                        &[schedule_seeds],
+                    ),
+                    vault.amount,
+                )?;
+                token::close_account(CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    CloseAccount {
+                        account: vault.to_account_info(),
+                        destination: ctx.accounts.owner.to_account_info(),
+                        authority: schedule.to_account_info(),
+                    },
+                    &[schedule_seeds],
+                ))?;
+                schedule_spent = true;
+            }
+
+            if market.circulating_supply == 0 {
+                // Only rent and rounding dust are left once every token is redeemed
+                let treasury = ctx.accounts.treasury.lamports();
+                lamports_returned += treasury + market.to_account_info().lamports();
+
+                let treasury_seeds: &[&[u8]] = &[
+                    b"treasury".as_ref(),
+                    market_key.as_ref(),
+                    &[ctx.bumps.treasury],
+                ];
+                transfer_lamports(
+                    &ctx.accounts.system_program,
+                    &ctx.accounts.treasury,
+                    &ctx.accounts.owner,
+                    treasury,
+                    &[treasury_seeds],
+                )?;
+                market.close(ctx.accounts.owner.to_account_info())?;
+            }
+            // Closing moves lamports directly, so the schedule waits until every CPI is done
+            if let Some(schedule) = ctx
+                .accounts
+                .vesting_schedule
+                .as_ref()
+                .filter(|_| schedule_spent)
+            {
+                schedule.close(ctx.accounts.owner.to_account_info())?;
+            }
+
+            emit!(MarketClosed {
+                market: market_key,
+                owner: market.owner,
+                sequence: market.next_sequence()?,
+                unredeemed_supply: market.circulating_supply,
+                treasury_balance: ctx.accounts.treasury.lamports(),
+                lamports_returned,
+                timestamp: now,
+            });
+
+            Ok(())
+        }
+
+        // Releases whatever of the creator allocation has vested and not been claimed yet.
+        // Nothing is released before the market graduates: the allocation is outside the
+        // curve supply and unbacked by the treasury, so it must never reach the curve's sells
+        // or a closing market's redemptions.
+        pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+            require!(
+                ctx.accounts.market.status == MarketStatus::Graduated,
+                ExchangeError::MarketNotGraduated
+            );
+
+            let now = Clock::get()?.unix_timestamp;
+            let schedule = &mut ctx.accounts.vesting_schedule;
+            let amount = schedule.vested(now).saturating_sub(schedule.claimed_amount);
+            require!(amount > 0, ExchangeError::NothingToClaim);
+
+            let market_key = schedule.market;
+            let schedule_seeds: &[&[u8]] =
+                &[b"vesting".as_ref(), market_key.as_ref(), &[schedule.bump]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.vault.to_account_info(),
+                        to: ctx.accounts.beneficiary_token_account.to_account_info(),
+                        authority: schedule.to_account_info(),
+                    },
+                    &[schedule_seeds],
+                ),
+                amount,
+            )?;
+            schedule.claimed_amount += amount;
+
+            emit!(VestedTokensClaimed {
+                market: market_key,
+                schedule: schedule.key(),
+                beneficiary: schedule.beneficiary,
+                amount,
+                claimed_amount: schedule.claimed_amount,
+                locked_amount: schedule.total_amount - schedule.claimed_amount,
+                timestamp: now,
+            });
+
+            Ok(())
+        }
+
+        // Read-only: the treasury against the rent reserve and the curve value of every
+        // circulating token. Any shortfall can be covered with a plain transfer to the treasury.
+        pub fn verify_market(ctx: Context<VerifyMarket>) -> Result<SolvencyReport> {
+            let report = ctx.accounts.market.solvency(
+                ctx.accounts.treasury.lamports(),
+                Rent::get()?.minimum_balance(0),
+            )?;
+            if report.shortfall > 0 {
+                msg!("Treasury short by {} lamports", report.shortfall);
+            }
+            Ok(report)
+        }
+
+        // Opens a pool for a token that does not back a market here; market tokens get
+        // theirs from `graduate_market`. The opener earns the creator share of its swap
+        // fees and receives its liquidity as LP tokens, less MINIMUM_LIQUIDITY, which stays
+        // locked so the pool can never be emptied.
+        pub fn initialize_pool(
+            ctx: Context<InitializePool>,
+            sol_amount: u64,
+            token_amount: u64,
+        ) -> Result<()> {
+            require!(
+                ctx.accounts.config.trading_mode == TradingMode::Active,
+                ExchangeError::Paused
+            );
+            let liquidity = math::initial_liquidity(sol_amount, token_amount);
+            require!(
+                liquidity > MINIMUM_LIQUIDITY,
+                ExchangeError::InsufficientLiquidity
+            );
+
+            // The vault holds the pool's SOL, so it has to stay rent-exempt on its own
+            let vault_rent = Rent::get()?
+                .minimum_balance(0)
+                .saturating_sub(ctx.accounts.sol_vault.lamports());
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.creator,
+                &ctx.accounts.sol_vault,
+                vault_rent
+                    .checked_add(sol_amount)
+                    .ok_or(ExchangeError::MathError)?,
+                &[],
+            )?;
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.creator_token_account.to_account_info(),
+                        to: ctx.accounts.token_vault.to_account_info(),
+                        authority: ctx.accounts.creator.to_account_info(),
+                    },
+                ),
+                token_amount,
+            )?;
+
+            let pool = &mut ctx.accounts.pool;
+            pool.market = None;
+            pool.mint = ctx.accounts.mint.key();
+            pool.creator = ctx.accounts.creator.key();
+            pool.token_vault = ctx.accounts.token_vault.key();
+            pool.sol_vault = ctx.accounts.sol_vault.key();
+            pool.lp_mint = ctx.accounts.lp_mint.key();
+            pool.locked_liquidity = MINIMUM_LIQUIDITY;
+            pool.bump = ctx.bumps.pool;
+
+            let mint_key = pool.mint;
+            let pool_seeds: &[&[u8]] = &[b"pool".as_ref(), mint_key.as_ref(), &[pool.bump]];
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        to: ctx.accounts.creator_lp_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[pool_seeds],
+                ),
+                liquidity - MINIMUM_LIQUIDITY,
+            )?;
+
+            emit!(PoolInitialized {
+                pool: pool.key(),
+                mint: mint_key,
+                creator: pool.creator,
+                lp_mint: pool.lp_mint,
+                sol_reserve: sol_amount,
+                token_reserve: token_amount,
+                liquidity,
+                locked_liquidity: MINIMUM_LIQUIDITY,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Trades against a pool: lamports for tokens on a `Buy`, tokens for lamports on a
+        // `Sell`. The fee is charged on the SOL side and split between the platform and the
+        // pool's creator exactly like a curve trade's.
+        pub fn swap(
+            ctx: Context<Swap>,
+            kind: TradeKind,
+            amount_in: u64,
+            min_amount_out: u64,
+        ) -> Result<()> {
+            require!(amount_in > 0, ExchangeError::InvalidAmount);
+            // Holders can always exit in sell-only mode
+            let mode = ctx
+                .accounts
+                .pool
+                .trading_mode(ctx.accounts.market.as_ref(), &ctx.accounts.config)?;
+            let allowed = match kind {
+                TradeKind::Buy => mode == TradingMode::Active,
+                TradeKind::Sell => mode != TradingMode::Paused,
+            };
+            require!(allowed, ExchangeError::Paused);
+
+            let (sol_reserve, token_reserve) =
+                Pool::reserves(&ctx.accounts.sol_vault, &ctx.accounts.token_vault)?;
+            let fees = ctx.accounts.config.fee_schedule();
+            let quote = match kind {
+                TradeKind::Buy => {
+                    math::quote_swap_buy(amount_in, sol_reserve, token_reserve, &fees)
+                }
+                TradeKind::Sell => {
+                    math::quote_swap_sell(amount_in, sol_reserve, token_reserve, &fees)
+                }
+            }
+            .map_err(ExchangeError::from)?;
+
+            require!(quote.amount_out > 0, ExchangeError::InvalidAmount);
+            require!(
+                quote.amount_out >= min_amount_out,
+                ExchangeError::SlippageExceeded
+            );
+
+            let pool = &ctx.accounts.pool;
+            let pool_key = pool.key();
+            let pool_seeds: &[&[u8]] = &[b"pool".as_ref(), pool.mint.as_ref(), &[pool.bump]];
+            let vault_seeds: &[&[u8]] = &[
+                b"pool-sol".as_ref(),
+                pool_key.as_ref(),
+                &[ctx.bumps.sol_vault],
+            ];
+
+            match kind {
+                TradeKind::Buy => {
+                    let user = &ctx.accounts.user;
+                    let system = &ctx.accounts.system_program;
+                    transfer_lamports(
+                        system,
+                        user,
+                        &ctx.accounts.sol_vault,
+                        quote.sol_reserve_change,
+                        &[],
+                    )?;
+                    transfer_lamports(
+                        system,
+                        user,
+                        &ctx.accounts.platform_fee_wallet,
+                        quote.platform_fee,
+                        &[],
+                    )?;
+                    transfer_lamports(
+                        system,
+                        user,
+                        &ctx.accounts.creator_wallet,
+                        quote.creator_fee,
+                        &[],
+                    )?;
+                    token::transfer(
+                        CpiContext::new_with_signer(
+                            ctx.accounts.token_program.to_account_info(),
+                            Transfer {
+                                from: ctx.accounts.token_vault.to_account_info(),
+                                to: ctx.accounts.user_token_account.to_account_info(),
+                                authority: pool.to_account_info(),
+                            },
+                            &[pool_seeds],
+                        ),
+                        quote.amount_out,
+                    )?;
+                }
+                TradeKind::Sell => {
+                    token::transfer(
+                        CpiContext::new(
+                            ctx.accounts.token_program.to_account_info(),
+                            Transfer {
+                                from: ctx.accounts.user_token_account.to_account_info(),
+                                to: ctx.accounts.token_vault.to_account_info(),
+                                authority: ctx.accounts.user.to_account_info(),
+                            },
+                        ),
+                        amount_in,
+                    )?;
+                    let vault = &ctx.accounts.sol_vault;
+                    let system = &ctx.accounts.system_program;
+                    transfer_lamports(
+                        system,
+                        vault,
+                        &ctx.accounts.user,
+                        quote.amount_out,
+                        &[vault_seeds],
+                    )?;
+                    transfer_lamports(
+                        system,
+                        vault,
+                        &ctx.accounts.platform_fee_wallet,
+                        quote.platform_fee,
+                        &[vault_seeds],
+                    )?;
+                    transfer_lamports(
+                        system,
+                        vault,
+                        &ctx.accounts.creator_wallet,
+                        quote.creator_fee,
+                        &[vault_seeds],
+                    )?;
+                }
+            }
+
+            ctx.accounts.token_vault.reload()?;
+            let (sol_reserve, token_reserve) =
+                Pool::reserves(&ctx.accounts.sol_vault, &ctx.accounts.token_vault)?;
+
+            emit!(SwapExecuted {
+                pool: pool_key,
+                mint: ctx.accounts.pool.mint,
+                user: ctx.accounts.user.key(),
+                kind,
+                amount_in,
+                amount_out: quote.amount_out,
+                platform_fee: quote.platform_fee,
+                creator_fee: quote.creator_fee,
+                sol_reserve,
+                token_reserve,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Deposits lamports and tokens in the pool's current ratio for `liquidity` new LP
+        // tokens. Both deposits round up, so existing providers are never diluted.
+        pub fn add_liquidity(
+            ctx: Context<Liquidity>,
+            liquidity: u64,
+            max_sol: u64,
+            max_tokens: u64,
+        ) -> Result<()> {
+            require!(liquidity > 0, ExchangeError::InvalidAmount);
+            let mode = ctx
+                .accounts
+                .pool
+                .trading_mode(ctx.accounts.market.as_ref(), &ctx.accounts.config)?;
+            require!(mode == TradingMode::Active, ExchangeError::Paused);
+
+            let (sol_reserve, token_reserve) =
+                Pool::reserves(&ctx.accounts.sol_vault, &ctx.accounts.token_vault)?;
+            let pool = &ctx.accounts.pool;
+            let lp_supply = pool.lp_supply(&ctx.accounts.lp_mint)?;
+            let sol_amount = math::deposit_for_liquidity(liquidity, sol_reserve, lp_supply)
+                .map_err(ExchangeError::from)?;
+            let token_amount = math::deposit_for_liquidity(liquidity, token_reserve, lp_supply)
+                .map_err(ExchangeError::from)?;
+            require!(
+                sol_amount <= max_sol && token_amount <= max_tokens,
+                ExchangeError::SlippageExceeded
+            );
+
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.user,
+                &ctx.accounts.sol_vault,
+                sol_amount,
+                &[],
+            )?;
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.user_token_account.to_account_info(),
+                        to: ctx.accounts.token_vault.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                token_amount,
+            )?;
+
+            let pool_seeds: &[&[u8]] = &[b"pool".as_ref(), pool.mint.as_ref(), &[pool.bump]];
+            token::mint_to(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    MintTo {
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        to: ctx.accounts.user_lp_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[pool_seeds],
+                ),
+                liquidity,
+            )?;
+
+            emit!(LiquidityAdded {
+                pool: pool.key(),
+                provider: ctx.accounts.user.key(),
+                liquidity,
+                sol_amount,
+                token_amount,
+                lp_supply: lp_supply + liquidity,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
+
+        // Burns `liquidity` LP tokens for their share of both reserves, rounded down.
+        pub fn remove_liquidity(
+            ctx: Context<Liquidity>,
+            liquidity: u64,
+            min_sol: u64,
+            min_tokens: u64,
+        ) -> Result<()> {
+            require!(liquidity > 0, ExchangeError::InvalidAmount);
+            // Providers can always exit in sell-only mode
+            let mode = ctx
+                .accounts
+                .pool
+                .trading_mode(ctx.accounts.market.as_ref(), &ctx.accounts.config)?;
+            require!(mode != TradingMode::Paused, ExchangeError::Paused);
+
+            let (sol_reserve, token_reserve) =
+                Pool::reserves(&ctx.accounts.sol_vault, &ctx.accounts.token_vault)?;
+            let pool = &ctx.accounts.pool;
+            let lp_supply = pool.lp_supply(&ctx.accounts.lp_mint)?;
+            let sol_amount = math::withdrawal_for_liquidity(liquidity, sol_reserve, lp_supply)
+                .map_err(ExchangeError::from)?;
+            let token_amount = math::withdrawal_for_liquidity(liquidity, token_reserve, lp_supply)
+                .map_err(ExchangeError::from)?;
+            require!(
+                sol_amount >= min_sol && token_amount >= min_tokens,
+                ExchangeError::SlippageExceeded
+            );
+
+            token::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: ctx.accounts.lp_mint.to_account_info(),
+                        from: ctx.accounts.user_lp_account.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                liquidity,
+            )?;
+
+            let pool_key = pool.key();
+            let pool_seeds: &[&[u8]] = &[b"pool".as_ref(), pool.mint.as_ref(), &[pool.bump]];
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.token_vault.to_account_info(),
+                        to: ctx.accounts.user_token_account.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    &[pool_seeds],
+                ),
+                token_amount,
+            )?;
+            let vault_seeds: &[&[u8]] = &[
+                b"pool-sol".as_ref(),
+                pool_key.as_ref(),
+                &[ctx.bumps.sol_vault],
+            ];
+            transfer_lamports(
+                &ctx.accounts.system_program,
+                &ctx.accounts.sol_vault,
+                &ctx.accounts.user,
+                sol_amount,
+                &[vault_seeds],
+            )?;
+
+            emit!(LiquidityRemoved {
+                pool: pool_key,
+                provider: ctx.accounts.user.key(),
+                liquidity,
+                sol_amount,
+                token_amount,
+                lp_supply: lp_supply - liquidity,
+                timestamp: Clock::get()?.unix_timestamp,
+            });
+
+            Ok(())
+        }
     }
+}
 
-    pub fn sell_tokens(
-        ctx: Context<BuySell>,
-        amount: u64,
-        min_receive_lamports: u64,
-    ) -> Result<()> {
-        require!(amount > 0, ExchangeError::InvalidAmount);
-        let market = &mut ctx.accounts.market;
+pub use handlers::*;
+
+fn execute_buy(ctx: Context<BuySell>, amount: u64, max_cost_lamports: u64) -> Result<()> {
+    let market = &mut ctx.accounts.market;
+
+    require!(
+        market.effective_trading_mode(&ctx.accounts.config) == TradingMode::Active,
+        ExchangeError::Paused
+    );
+    require!(
+        market.status != MarketStatus::Closing,
+        ExchangeError::MarketClosing
+    );
+    require!(!market.curve_complete(), ExchangeError::CurveComplete);
+
+    // Nothing is sold past the graduation threshold, so the escrow always keeps enough
+    // back to open the pool with; quote_buy rejects the same amounts
+    require!(amount > 0, ExchangeError::InvalidAmount);
+    require!(
+        amount <= market.curve_tokens_left(),
+        ExchangeError::InvalidAmount
+    );
+
+    // ✅ NEW: Calculate price using exponential bonding curve
+    let supply_before = market.circulating_supply;
+    let total_cost = market.apply_buy(amount)?;
+
+    // Calculate fee, split between platform and creator
+    let fees = ctx.accounts.config.fee_schedule();
+    let math::Quote {
+        platform_fee,
+        creator_fee,
+        net_lamports: total_charged,
+        ..
+    } = math::Quote::buy(amount, total_cost, market.current_price, &fees)
+        .map_err(ExchangeError::from)?;
+    let unrounded = math::exact_fees(total_cost, &fees).map_err(ExchangeError::from)?;
+
+    require!(
+        total_charged <= max_cost_lamports,
+        ExchangeError::SlippageExceeded
+    );
+
+    msg!("💰 Buy Details:");
+    msg!("  Total Cost: {} lamports", total_cost);
+    msg!("  Platform Fee: {} lamports", platform_fee);
+    msg!("  Creator Fee: {} lamports", creator_fee);
+
+    // Transfer SOL to treasury
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            },
+        ),
+        total_cost,
+    )?;
+
+    // ✅ Transfer 70% to platform
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.platform_fee_wallet.to_account_info(),
+            },
+        ),
+        platform_fee,
+    )?;
+
+    // ✅ Transfer 30% to creator
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.creator_wallet.to_account_info(),
+            },
+        ),
+        creator_fee,
+    )?;
+
+    // Transfer tokens from escrow to buyer
+    let market_key = market.key();
+    let escrow_bump = ctx.bumps.escrow_authority;
+    let escrow_seeds: &[&[u8]] = &[b"escrow".as_ref(), market_key.as_ref(), &[escrow_bump]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.escrow_token_account.to_account_info(),
+                to: ctx.accounts.user_token_account.to_account_info(),
+                authority: ctx.accounts.escrow_authority.to_account_info(),
+            },
+            &[escrow_seeds],
+        ),
+        amount,
+    )?;
+
+    market.check_solvency(ctx.accounts.treasury.lamports())?;
+
+    emit!(TradeExecuted {
+        market: market.key(),
+        buyer: Some(*ctx.accounts.user.key),
+        seller: None,
+        tokens: amount,
+        price: market.current_price,
+        total_cost,
+        platform_fee,
+        creator_fee,
+        fee_unrounded: unrounded.total,
+        platform_fee_unrounded: unrounded.platform,
+        creator_fee_unrounded: unrounded.creator,
+        kind: TradeKind::Buy,
+        sequence: market.trade_count,
+        circulating_supply_before: supply_before,
+        circulating_supply_after: market.circulating_supply,
+        treasury_balance: ctx.accounts.treasury.lamports(),
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
 
-        require!(
-            amount <= market.circulating_supply,
-            ExchangeError::InvalidAmount
-        );
+fn validate_metadata(name: &str, symbol: &str, uri: &str) -> Result<()> {
+    require!(name.len() <= 32, ExchangeError::InvalidMetadata);
+    require!(symbol.len() <= 10, ExchangeError::InvalidMetadata);
+    require!(uri.len() <= 200, ExchangeError::InvalidMetadata);
 
-        // ✅ NEW: Calculate sell value using bonding curve
-        let new_circulating = market
-            .circulating_supply
-            .checked_sub(amount)
-            .ok_or(ExchangeError::MathError)?;
+    Ok(())
+}
 
-        let total_value = calculate_sell_value(
-            market.circulating_supply,
-            new_circulating,
-            market.current_price,
-        )?;
-
-        // Calculate 1% fee
-        let total_fee = (total_value as u128)
-            .checked_mul(TRANSACTION_FEE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        // ✅ Split fee 70/30
-        let platform_fee = (total_fee as u128)
-            .checked_mul(PLATFORM_FEE_SHARE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        let creator_fee = (total_fee as u128)
-            .checked_mul(CREATOR_FEE_SHARE_BPS as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000u128)
-            .ok_or(ExchangeError::MathError)? as u64;
-
-        let user_receives = total_value
-            .checked_sub(total_fee)
-            .ok_or(ExchangeError::MathError)?;
+// Lamports between system accounts; `signer_seeds` sign for a program-owned PDA sender
+fn transfer_lamports<'info>(
+    system: &Program<'info, System>,
+    from: &impl ToAccountInfo<'info>,
+    to: &impl ToAccountInfo<'info>,
+    lamports: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new_with_signer(
+            system.to_account_info(),
+            system_program::Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        lamports,
+    )
+}
 
-        require!(
-            user_receives >= min_receive_lamports,
-            ExchangeError::SlippageExceeded
-        );
+// `market` is None for the global switch
+fn emit_trading_mode_change(market: Option<&mut Account<Market>>, mode: TradingMode) -> Result<()> {
+    let timestamp = Clock::get()?.unix_timestamp;
+    let (market, sequence) = match market {
+        Some(market) => (Some(market.key()), Some(market.next_sequence()?)),
+        None => (None, None),
+    };
 
-        msg!("💸 Sell Details:");
-        msg!("  Total Value: {} lamports", total_value);
-        msg!("  Platform Fee (70%): {} lamports", platform_fee);
-        msg!("  Creator Fee (30%): {} lamports", creator_fee);
-        msg!("  User Receives: {} lamports", user_receives);
-
-        // Transfer tokens from seller to escrow
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.user_token_account.to_account_info(),
-                    to: ctx.accounts.escrow_token_account.to_account_info(),
-                    authority: ctx.accounts.user.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
-
-        let market_key = market.key();
-        let treasury_bump = ctx.bumps.treasury;
-        let treasury_seeds: &[&[u8]] =
-            &[b"treasury".as_ref(), market_key.as_ref(), &[treasury_bump]];
-
-        // Transfer SOL to seller (minus fees)
-        anchor_lang::solana_program::program::invoke_signed(
-            &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.treasury.key(),
-                &ctx.accounts.user.key(),
-                user_receives,
-            ),
-            &[
-                ctx.accounts.treasury.to_account_info(),
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[treasury_seeds],
-        )?;
-
-        // ✅ Transfer platform fee (70%)
-        anchor_lang::solana_program::program::invoke_signed(
-            &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.treasury.key(),
-                &ctx.accounts.platform_fee_wallet.key(),
-                platform_fee,
-            ),
-            &[
-                ctx.accounts.treasury.to_account_info(),
-                ctx.accounts.platform_fee_wallet.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[treasury_seeds],
-        )?;
-
-        // ✅ Transfer creator fee (30%)
-        anchor_lang::solana_program::program::invoke_signed(
-            &anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.treasury.key(),
-                &ctx.accounts.creator_wallet.key(),
-                creator_fee,
-            ),
-            &[
-                ctx.accounts.treasury.to_account_info(),
-                ctx.accounts.creator_wallet.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-            &[treasury_seeds],
-        )?;
-
-        // ✅ Update market state
-        market.circulating_supply = new_circulating;
-        market.current_price = calculate_current_price(new_circulating, market.current_price)?;
-        market.trade_count = market.trade_count.saturating_add(1);
-
-        emit!(TradeExecuted {
-            market: market.key(),
-            buyer: None,
-            seller: Some(*ctx.accounts.user.key),
-            tokens: amount,
-            price: market.current_price,
-            total_cost: total_value,
-            platform_fee,
-            creator_fee,
-            kind: TradeKind::Sell,
-            timestamp: Clock::get()?.unix_timestamp,
+    if mode == TradingMode::Active {
+        emit!(MarketResumed {
+            market,
+            sequence,
+            timestamp
+        });
+    } else {
+        emit!(MarketPaused {
+            market,
+            sequence,
+            mode,
+            timestamp
         });
+    }
 
-        Ok(())
+    Ok(())
+}
+
+//  Pricing
+//
+// Curve, fee and quote math lives in `celebrity_exchange_math`, which off-chain clients
+// link as well, so a quote computed anywhere matches what a trade here settles to.
+
+impl From<CurveType> for math::CurveType {
+    fn from(curve_type: CurveType) -> Self {
+        match curve_type {
+            CurveType::Exponential => math::CurveType::Exponential,
+            CurveType::Linear => math::CurveType::Linear,
+            CurveType::Sigmoid => math::CurveType::Sigmoid,
+            CurveType::ConstantProduct => math::CurveType::ConstantProduct,
+        }
     }
+}
 
-    pub fn get_market_details(ctx: Context<GetMarket>) -> Result<MarketDetails> {
-        let market = &ctx.accounts.market;
-
-        Ok(MarketDetails {
-            owner: market.owner,
-            mint: market.mint,
-            escrow: market.escrow,
-            treasury: market.treasury,
-            current_price: market.current_price,
-            total_supply: market.total_supply,
-            circulating_supply: market.circulating_supply,
-            trade_count: market.trade_count,
-            name: market.name.clone(),
-            symbol: market.symbol.clone(),
-            uri: market.uri.clone(),
-        })
+impl From<CurveParams> for math::CurveParams {
+    fn from(curve: CurveParams) -> Self {
+        math::CurveParams {
+            curve_type: curve.curve_type.into(),
+            base_price: curve.base_price,
+            k_factor: curve.k_factor,
+            scale_factor: curve.scale_factor,
+            max_price: curve.max_price,
+        }
     }
 }
 
-//  Exponential bonding curve price calculation
-fn calculate_current_price(circulating_supply: u64, _base_price: u64) -> Result<u64> {
-    if circulating_supply == 0 {
-        return Ok(BASE_PRICE);
+impl From<math::MathError> for ExchangeError {
+    fn from(error: math::MathError) -> Self {
+        match error {
+            math::MathError::Overflow => ExchangeError::MathError,
+            math::MathError::InvalidCurveParams => ExchangeError::InvalidCurveParams,
+            math::MathError::InvalidFeeSchedule => ExchangeError::InvalidFeeConfig,
+        }
     }
+}
 
-    // P = BASE_PRICE × e^(K × S / SCALE_FACTOR)
-    // Simplified using integer math to avoid floating point
+impl From<math::Quote> for TradeQuote {
+    fn from(quote: math::Quote) -> Self {
+        TradeQuote {
+            tokens: quote.tokens,
+            total_cost: quote.total_cost,
+            platform_fee: quote.platform_fee,
+            creator_fee: quote.creator_fee,
+            net_lamports: quote.net_lamports,
+            price_after: quote.price_after,
+        }
+    }
+}
 
-    let exponent = (circulating_supply as u128)
-        .checked_mul(K_FACTOR as u128)
-        .ok_or(ExchangeError::MathError)?
-        .checked_div(SCALE_FACTOR as u128)
-        .ok_or(ExchangeError::MathError)? as u64;
+//  CONTEXTS
 
-    // Approximate e^x using series expansion for small x
-    // e^x ≈ 1 + x + x²/2 + x³/6 ...
-    let price = if exponent < 10 {
-        let multiplier = 10000 + (exponent * 10000) + (exponent * exponent * 5000) / 10000;
-        (BASE_PRICE as u128)
-            .checked_mul(multiplier as u128)
-            .ok_or(ExchangeError::MathError)?
-            .checked_div(10000)
-            .ok_or(ExchangeError::MathError)? as u64
-    } else {
-        // For larger exponents, use capped exponential
-        (BASE_PRICE as u128)
-            .checked_mul(2u128.pow(exponent.min(20) as u32))
-            .ok_or(ExchangeError::MathError)? as u64
-    };
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
 
-    Ok(price.max(BASE_PRICE))
-}
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + GlobalConfig::MAX_SIZE,
+        seeds = [b"config".as_ref()],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    // Only the program's upgrade authority may bootstrap the config
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = ProgramData::owner(),
+        constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ExchangeError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
 
-fn calculate_buy_cost(from_supply: u64, to_supply: u64, base_price: u64) -> Result<u64> {
-    // Integrate price curve from from_supply to to_supply
-    let mut total_cost: u128 = 0;
+    pub system_program: Program<'info, System>,
+}
 
-    for i in from_supply..to_supply {
-        let price = calculate_current_price(i, base_price)?;
-        total_cost = total_cost
-            .checked_add(price as u128)
-            .ok_or(ExchangeError::MathError)?;
-    }
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
 
-    Ok(total_cost as u64)
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = admin @ ExchangeError::Unauthorized
+    )]
+    pub config: Account<'info, GlobalConfig>,
 }
 
-fn calculate_sell_value(from_supply: u64, to_supply: u64, base_price: u64) -> Result<u64> {
-    // Same as buy cost but in reverse
-    calculate_buy_cost(to_supply, from_supply, base_price)
+// The incoming admin signs for itself, so a mistyped key can never take over.
+// No lamports are debited, which lets a multisig PDA sign through CPI.
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        constraint = config.pending_admin == Some(pending_admin.key()) @ ExchangeError::Unauthorized
+    )]
+    pub config: Account<'info, GlobalConfig>,
 }
 
-//  CONTEXTS
+#[derive(Accounts)]
+pub struct SetMarketTradingMode<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = admin @ ExchangeError::Unauthorized
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub market: Account<'info, Market>,
+}
 
 #[derive(Accounts)]
 #[instruction(initial_price_lamports: u64, initial_supply: u64, name: String, symbol: String, uri: String)]
@@ -468,8 +1854,8 @@ pub struct CreateMarket<'info> {
     )]
     pub mint: Account<'info, Mint>,
 
-    /// CHECK: PDA authority for minting
-    #[account(seeds = [b"mint-authority".as_ref()], bump)]
+    /// CHECK: Per-market PDA authority for minting
+    #[account(seeds = [b"mint-authority".as_ref(), mint.key().as_ref()], bump)]
     pub mint_authority: UncheckedAccount<'info>,
 
     #[account(
@@ -505,10 +1891,13 @@ pub struct CreateMarket<'info> {
     #[account(mut)]
     pub metadata: UncheckedAccount<'info>,
 
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
     /// CHECK: Platform fee wallet
     #[account(
         mut,
-        constraint = platform_fee_wallet.key().to_string() == PLATFORM_FEE_WALLET @ ExchangeError::InvalidPlatformWallet
+        address = config.fee_wallet @ ExchangeError::InvalidPlatformWallet
     )]
     pub platform_fee_wallet: UncheckedAccount<'info>,
 
@@ -517,6 +1906,108 @@ pub struct CreateMarket<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub metadata_program: Program<'info, Metadata>,
     pub rent: Sysvar<'info, Rent>,
+
+    /// Only with a creator allocation
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + VestingSchedule::MAX_SIZE,
+        seeds = [b"vesting".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub vesting_schedule: Option<Account<'info, VestingSchedule>>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = vesting_schedule
+    )]
+    pub vesting_vault: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMintAuthority<'info> {
+    // Pays the rent for the larger layout and any treasury shortfall
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = admin @ ExchangeError::Unauthorized
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    /// CHECK: A market in the legacy or current layout, decoded by the handler
+    #[account(mut, owner = crate::ID)]
+    pub market: UncheckedAccount<'info>,
+
+    /// CHECK: Treasury PDA, topped up to back the circulating supply
+    #[account(
+        mut,
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Global PDA that was the authority of every market before migration
+    #[account(
+        seeds = [b"mint-authority".as_ref()],
+        bump,
+        constraint = mint.mint_authority == COption::Some(legacy_mint_authority.key()) @ ExchangeError::AlreadyMigrated
+    )]
+    pub legacy_mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Per-market PDA taking over mint, freeze and metadata authority
+    #[account(seeds = [b"mint-authority".as_ref(), mint.key().as_ref()], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex metadata PDA of the mint
+    #[account(
+        mut,
+        seeds = [b"metadata".as_ref(), metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub metadata_program: Program<'info, Metadata>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMarketMetadata<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = owner @ ExchangeError::Unauthorized,
+        has_one = mint @ ExchangeError::InvalidMint,
+        constraint = !market.metadata_locked @ ExchangeError::MetadataLocked
+    )]
+    pub market: Account<'info, Market>,
+
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Per-market PDA, update authority of the metadata
+    #[account(seeds = [b"mint-authority".as_ref(), mint.key().as_ref()], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex metadata PDA of the mint
+    #[account(
+        mut,
+        seeds = [b"metadata".as_ref(), metadata_program.key().as_ref(), mint.key().as_ref()],
+        bump,
+        seeds::program = metadata_program.key()
+    )]
+    pub metadata: UncheckedAccount<'info>,
+
+    pub metadata_program: Program<'info, Metadata>,
 }
 
 #[derive(Accounts)]
@@ -531,7 +2022,12 @@ pub struct BuySell<'info> {
     #[account(seeds = [b"escrow".as_ref(), market.key().as_ref()], bump)]
     pub escrow_authority: UncheckedAccount<'info>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = escrow_token_account.key() == market.escrow @ ExchangeError::InvalidEscrowAccount,
+        constraint = escrow_token_account.mint == market.mint @ ExchangeError::InvalidMint,
+        constraint = escrow_token_account.owner == escrow_authority.key() @ ExchangeError::InvalidEscrowAccount
+    )]
     pub escrow_token_account: Account<'info, TokenAccount>,
 
     /// CHECK: Treasury PDA
@@ -542,13 +2038,20 @@ pub struct BuySell<'info> {
     )]
     pub treasury: UncheckedAccount<'info>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint @ ExchangeError::InvalidMint,
+        constraint = user_token_account.key() != market.escrow @ ExchangeError::InvalidTokenAccount
+    )]
     pub user_token_account: Account<'info, TokenAccount>,
 
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
     /// CHECK: Platform fee wallet
     #[account(
         mut,
-        constraint = platform_fee_wallet.key().to_string() == PLATFORM_FEE_WALLET @ ExchangeError::InvalidPlatformWallet
+        address = config.fee_wallet @ ExchangeError::InvalidPlatformWallet
     )]
     pub platform_fee_wallet: UncheckedAccount<'info>,
 
@@ -568,70 +2071,1285 @@ pub struct GetMarket<'info> {
     pub market: Account<'info, Market>,
 }
 
-#[account]
-pub struct Market {
-    pub owner: Pubkey,
-    pub mint: Pubkey,
-    pub escrow: Pubkey,
-    pub treasury: Pubkey,
-    pub current_price: u64,
-    pub total_supply: u64,
-    pub circulating_supply: u64,
-    pub trade_count: u64,
-    pub name: String,
-    pub symbol: String,
-    pub uri: String,
-}
+#[derive(Accounts)]
+pub struct Quote<'info> {
+    pub market: Account<'info, Market>,
 
-impl Market {
-    pub const MAX_SIZE: usize = 32 * 4 + 8 * 4 + 4 + 32 + 4 + 10 + 4 + 200;
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct MarketDetails {
-    pub owner: Pubkey,
-    pub mint: Pubkey,
-    pub escrow: Pubkey,
-    pub treasury: Pubkey,
-    pub current_price: u64,
-    pub total_supply: u64,
-    pub circulating_supply: u64,
-    pub trade_count: u64,
-    pub name: String,
-    pub symbol: String,
-    pub uri: String,
-}
+#[derive(Accounts)]
+pub struct GraduateMarket<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-#[event]
-pub struct MarketCreated {
-    pub market: Pubkey,
-    pub owner: Pubkey,
-    pub mint: Pubkey,
-    pub initial_price: u64,
-    pub initial_supply: u64,
-    pub name: String,
-    pub symbol: String,
-    pub uri: String,
-    pub timestamp: i64,
-}
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
-pub enum TradeKind {
-    Buy,
-    Sell,
-}
+    #[account(
+        mut,
+        constraint = market.mint == mint.key() @ ExchangeError::InvalidMint
+    )]
+    pub market: Account<'info, Market>,
 
-#[event]
-pub struct TradeExecuted {
-    pub market: Pubkey,
-    pub buyer: Option<Pubkey>,
-    pub seller: Option<Pubkey>,
-    pub tokens: u64,
-    pub price: u64,
-    pub total_cost: u64,
-    pub platform_fee: u64,
-    pub creator_fee: u64,
-    pub kind: TradeKind,
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Escrow authority PDA
+    #[account(seeds = [b"escrow".as_ref(), market.key().as_ref()], bump)]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = market.escrow @ ExchangeError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Treasury PDA
+    #[account(
+        mut,
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Pool::MAX_SIZE,
+        seeds = [b"pool".as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint,
+        associated_token::authority = pool
+    )]
+    pub pool_token_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Pool SOL vault PDA
+    #[account(
+        mut,
+        seeds = [b"pool-sol".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub pool_sol_vault: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"pool-lp".as_ref(), pool.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool
+    )]
+    pub pool_lp_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseMarket<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == market.owner
+            || authority.key() == config.admin @ ExchangeError::Unauthorized
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Treasury PDA, only its balance is read
+    #[account(
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = mint @ ExchangeError::InvalidMint
+    )]
+    pub market: Account<'info, Market>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Treasury PDA
+    #[account(
+        mut,
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == market.mint @ ExchangeError::InvalidMint,
+        constraint = user_token_account.key() != market.escrow @ ExchangeError::InvalidTokenAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeClose<'info> {
+    #[account(
+        mut,
+        has_one = mint @ ExchangeError::InvalidMint,
+        has_one = owner @ ExchangeError::InvalidCreatorWallet
+    )]
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Market owner, receives the rent of every closed account
+    #[account(mut)]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Escrow authority PDA
+    #[account(seeds = [b"escrow".as_ref(), market.key().as_ref()], bump)]
+    pub escrow_authority: UncheckedAccount<'info>,
+
+    /// CHECK: The market's escrow, decoded in the handler; empty once an earlier
+    /// `finalize_close` has closed it
+    #[account(
+        mut,
+        address = market.escrow @ ExchangeError::InvalidEscrowAccount
+    )]
+    pub escrow_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Treasury PDA
+    #[account(
+        mut,
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// Only with a creator allocation; burned and closed with the escrow
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), market.key().as_ref()],
+        bump = vesting_schedule.bump
+    )]
+    pub vesting_schedule: Option<Account<'info, VestingSchedule>>,
+
+    #[account(mut)]
+    pub vesting_vault: Option<Account<'info, TokenAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    pub beneficiary: Signer<'info>,
+
+    pub market: Account<'info, Market>,
+
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), market.key().as_ref()],
+        bump = vesting_schedule.bump,
+        has_one = beneficiary @ ExchangeError::Unauthorized,
+        has_one = vault @ ExchangeError::InvalidTokenAccount
+    )]
+    pub vesting_schedule: Account<'info, VestingSchedule>,
+
+    #[account(mut)]
+    pub vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = beneficiary_token_account.mint == vesting_schedule.mint @ ExchangeError::InvalidMint
+    )]
+    pub beneficiary_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyMarket<'info> {
+    pub market: Account<'info, Market>,
+
+    /// CHECK: Treasury PDA, only its balance is read
+    #[account(
+        seeds = [b"treasury".as_ref(), market.key().as_ref()],
+        bump
+    )]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub mint: Account<'info, Mint>,
+
+    /// CHECK: Market PDA of the mint, which must not exist
+    #[account(
+        seeds = [b"market".as_ref(), mint.key().as_ref()],
+        bump,
+        constraint = market.data_is_empty() @ ExchangeError::PoolReservedForMarket
+    )]
+    pub market: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + Pool::MAX_SIZE,
+        seeds = [b"pool".as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = mint,
+        associated_token::authority = pool
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Pool SOL vault PDA
+    #[account(
+        mut,
+        seeds = [b"pool-sol".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = creator,
+        seeds = [b"pool-lp".as_ref(), pool.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = pool
+    )]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = creator_token_account.mint == mint.key() @ ExchangeError::InvalidMint
+    )]
+    pub creator_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = creator,
+        associated_token::mint = lp_mint,
+        associated_token::authority = creator
+    )]
+    pub creator_lp_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(has_one = token_vault @ ExchangeError::InvalidTokenAccount)]
+    pub pool: Account<'info, Pool>,
+
+    /// The market that graduated into `pool`; omitted for pools from `initialize_pool`
+    pub market: Option<Account<'info, Market>>,
+
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Pool SOL vault PDA
+    #[account(
+        mut,
+        seeds = [b"pool-sol".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == pool.mint @ ExchangeError::InvalidMint,
+        constraint = user_token_account.key() != pool.token_vault @ ExchangeError::InvalidTokenAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: Platform fee wallet
+    #[account(
+        mut,
+        address = config.fee_wallet @ ExchangeError::InvalidPlatformWallet
+    )]
+    pub platform_fee_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Creator wallet (pool creator)
+    #[account(
+        mut,
+        address = pool.creator @ ExchangeError::InvalidCreatorWallet
+    )]
+    pub creator_wallet: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Liquidity<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(seeds = [b"config".as_ref()], bump = config.bump)]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        has_one = token_vault @ ExchangeError::InvalidTokenAccount,
+        has_one = lp_mint @ ExchangeError::InvalidMint
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// The market that graduated into `pool`; omitted for pools from `initialize_pool`
+    pub market: Option<Account<'info, Market>>,
+
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Pool SOL vault PDA
+    #[account(
+        mut,
+        seeds = [b"pool-sol".as_ref(), pool.key().as_ref()],
+        bump
+    )]
+    pub sol_vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = user_token_account.mint == pool.mint @ ExchangeError::InvalidMint,
+        constraint = user_token_account.key() != pool.token_vault @ ExchangeError::InvalidTokenAccount
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = user_lp_account.mint == lp_mint.key() @ ExchangeError::InvalidMint
+    )]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Program-wide settings. `admin` changes fees and pauses trading, and is handed
+/// over with `propose_admin` / `accept_admin`. It only ever signs, so it may be a
+/// multisig PDA.
+#[account]
+pub struct GlobalConfig {
+    pub admin: Pubkey,
+    pub pending_admin: Option<Pubkey>,
+    pub fee_wallet: Pubkey,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u64,
+    pub platform_fee_share_bps: u64,
+    pub creator_fee_share_bps: u64,
+    /// The least any trade pays while fees are on, in lamports
+    pub min_fee: u64,
+    pub trading_mode: TradingMode,
+    pub bump: u8,
+}
+
+impl GlobalConfig {
+    pub const MAX_SIZE: usize = 32 + (1 + 32) + 32 + 8 * 5 + 1 + 1;
+
+    fn apply(&mut self, params: ConfigParams) -> Result<()> {
+        require!(
+            params.transaction_fee_bps <= MAX_TRANSACTION_FEE_BPS,
+            ExchangeError::InvalidFeeConfig
+        );
+        require!(
+            params
+                .platform_fee_share_bps
+                .checked_add(params.creator_fee_share_bps)
+                == Some(BPS_DENOMINATOR),
+            ExchangeError::InvalidFeeConfig
+        );
+        require!(
+            params.min_fee <= MAX_MIN_TRADE_FEE,
+            ExchangeError::InvalidFeeConfig
+        );
+
+        self.fee_wallet = params.fee_wallet;
+        self.creation_fee = params.creation_fee;
+        self.transaction_fee_bps = params.transaction_fee_bps;
+        self.platform_fee_share_bps = params.platform_fee_share_bps;
+        self.creator_fee_share_bps = params.creator_fee_share_bps;
+        self.min_fee = params.min_fee;
+
+        Ok(())
+    }
+
+    // The fee settings trades are priced with
+    pub fn fee_schedule(&self) -> math::FeeSchedule {
+        math::FeeSchedule {
+            transaction_fee_bps: self.transaction_fee_bps,
+            platform_fee_share_bps: self.platform_fee_share_bps,
+            creator_fee_share_bps: self.creator_fee_share_bps,
+            min_fee: self.min_fee,
+        }
+    }
+
+    fn updated_event(&self) -> Result<ConfigUpdated> {
+        Ok(ConfigUpdated {
+            admin: self.admin,
+            fee_wallet: self.fee_wallet,
+            creation_fee: self.creation_fee,
+            transaction_fee_bps: self.transaction_fee_bps,
+            platform_fee_share_bps: self.platform_fee_share_bps,
+            creator_fee_share_bps: self.creator_fee_share_bps,
+            min_fee: self.min_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
+
+/// Fee settings written by `initialize_config` and `update_config`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ConfigParams {
+    pub fee_wallet: Pubkey,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u64,
+    pub platform_fee_share_bps: u64,
+    pub creator_fee_share_bps: u64,
+    /// At most MAX_MIN_TRADE_FEE
+    pub min_fee: u64,
+}
+
+#[account]
+pub struct Market {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    pub current_price: u64,
+    pub total_supply: u64,
+    pub circulating_supply: u64,
+    /// Sequence number of the market's latest event: bumped by every trade, redemption,
+    /// graduation and closing step
+    pub trade_count: u64,
+    pub curve: CurveParams,
+    pub trading_mode: TradingMode,
+    pub metadata_locked: bool,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub graduation: GraduationThreshold,
+    pub status: MarketStatus,
+    /// When `close_market` ran; 0 unless the market is closing
+    pub closing_started_at: i64,
+    /// Tokens of `total_supply` locked in the creator's vesting schedule rather than
+    /// sold on the curve
+    pub creator_allocation: u64,
+    /// Set by the first `finalize_close`, which burns the unsold supply and closes the
+    /// escrow
+    pub escrow_closed: bool,
+}
+
+impl Market {
+    pub const MAX_SIZE: usize = 32 * 4
+        + 8 * 4
+        + CurveParams::SIZE
+        + 1
+        + 1
+        + 4
+        + 32
+        + 4
+        + 10
+        + 4
+        + 200
+        + GraduationThreshold::SIZE
+        + 1
+        + 8
+        + 8
+        + 1;
+
+    // The stricter of the global and per-market modes applies
+    pub fn effective_trading_mode(&self, config: &GlobalConfig) -> TradingMode {
+        self.trading_mode.max(config.trading_mode)
+    }
+
+    // Moves the market up the curve by `amount` tokens and returns their cost in lamports
+    pub fn apply_buy(&mut self, amount: u64) -> Result<u64> {
+        let new_circulating = self
+            .circulating_supply
+            .checked_add(amount)
+            .ok_or(ExchangeError::MathError)?;
+        let total_cost =
+            math::calculate_buy_cost(self.circulating_supply, new_circulating, &self.curve.into())
+                .map_err(ExchangeError::from)?;
+
+        self.record_trade(new_circulating)?;
+        Ok(total_cost)
+    }
+
+    // Moves the market down the curve by `amount` tokens and returns their value in lamports
+    pub fn apply_sell(&mut self, amount: u64) -> Result<u64> {
+        let new_circulating = self
+            .circulating_supply
+            .checked_sub(amount)
+            .ok_or(ExchangeError::MathError)?;
+        let total_value = math::calculate_sell_value(
+            self.circulating_supply,
+            new_circulating,
+            &self.curve.into(),
+        )
+        .map_err(ExchangeError::from)?;
+
+        self.record_trade(new_circulating)?;
+        Ok(total_value)
+    }
+
+    // Tokens the curve sells: the supply less the creator's allocation
+    pub fn curve_supply(&self) -> u64 {
+        self.total_supply - self.creator_allocation
+    }
+
+    // current_price × circulating_supply, in lamports
+    pub fn market_cap(&self) -> u128 {
+        self.current_price as u128 * self.circulating_supply as u128
+    }
+
+    // Most tokens the curve sells before it must graduate, leaving the rest of the escrow
+    // to open the pool with. A market-cap threshold keeps back the default share.
+    pub fn curve_sale_limit(&self) -> u64 {
+        match self.graduation {
+            GraduationThreshold::SoldSupply(supply) => supply.min(self.curve_supply()),
+            GraduationThreshold::MarketCap(_) => {
+                ((self.curve_supply() as u128) * (DEFAULT_GRADUATION_SUPPLY_BPS as u128)
+                    / (BPS_DENOMINATOR as u128)) as u64
+            }
+        }
+    }
+
+    // Tokens a buy can still take off the curve; larger buys are rejected and exact-in
+    // buys stop here
+    pub fn curve_tokens_left(&self) -> u64 {
+        self.curve_sale_limit()
+            .saturating_sub(self.circulating_supply)
+    }
+
+    pub fn graduation_reached(&self) -> bool {
+        match self.graduation {
+            GraduationThreshold::SoldSupply(supply) => self.circulating_supply >= supply,
+            GraduationThreshold::MarketCap(cap) => {
+                self.market_cap() >= cap as u128
+                    || self.circulating_supply >= self.curve_sale_limit()
+            }
+        }
+    }
+
+    // The curve takes no more trades once the threshold is reached; the trade that
+    // reached it is the last
+    pub fn curve_complete(&self) -> bool {
+        self.status == MarketStatus::Graduated || self.graduation_reached()
+    }
+
+    // The treasury measured against what it owes: rent exemption for itself and the
+    // curve value of every circulating token, which selling them all back would pay out.
+    // Holders of a graduated market trade in its pool, so its treasury owes them nothing.
+    pub fn solvency(&self, treasury_balance: u64, rent_reserve: u64) -> Result<SolvencyReport> {
+        let curve_reserve = if self.status == MarketStatus::Graduated {
+            0
+        } else {
+            math::calculate_sell_value(self.circulating_supply, 0, &self.curve.into())
+                .map_err(ExchangeError::from)?
+        };
+        let required = rent_reserve
+            .checked_add(curve_reserve)
+            .ok_or(ExchangeError::MathError)?;
+
+        Ok(SolvencyReport {
+            treasury_balance,
+            rent_reserve,
+            curve_reserve,
+            shortfall: required.saturating_sub(treasury_balance),
+        })
+    }
+
+    // Run after every trade has settled: a treasury that could not buy back every
+    // circulating token fails the trade
+    pub fn check_solvency(&self, treasury_balance: u64) -> Result<()> {
+        let report = self.solvency(treasury_balance, Rent::get()?.minimum_balance(0))?;
+        require!(report.shortfall == 0, ExchangeError::TreasuryInsolvent);
+        Ok(())
+    }
+
+    // Lamports that redeeming `amount` of the circulating tokens pays out of a closing
+    // market: the same share of the treasury above rent, rounded down
+    pub fn redemption_value(
+        &self,
+        amount: u64,
+        treasury_balance: u64,
+        rent_reserve: u64,
+    ) -> Result<u64> {
+        require!(
+            amount <= self.circulating_supply,
+            ExchangeError::InvalidAmount
+        );
+        let available = treasury_balance.saturating_sub(rent_reserve) as u128;
+        // amount ≤ circulating_supply, so the share fits in `available`
+        let value = (available * amount as u128)
+            .checked_div(self.circulating_supply as u128)
+            .ok_or(ExchangeError::MathError)?;
+        Ok(value as u64)
+    }
+
+    fn record_trade(&mut self, new_circulating: u64) -> Result<()> {
+        self.circulating_supply = new_circulating;
+        self.current_price = math::calculate_current_price(new_circulating, &self.curve.into())
+            .map_err(ExchangeError::from)?;
+        self.next_sequence()?;
+        Ok(())
+    }
+
+    // Events carry this as their sequence number, so it must never repeat
+    pub fn next_sequence(&mut self) -> Result<u64> {
+        self.trade_count = self
+            .trade_count
+            .checked_add(1)
+            .ok_or(ExchangeError::MathError)?;
+        Ok(self.trade_count)
+    }
+}
+
+/// A market account as the first release laid it out, before per-market curves and mint
+/// authorities. `migrate_mint_authority` rewrites these in the current `Market` layout.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LegacyMarket {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    pub current_price: u64,
+    pub total_supply: u64,
+    pub circulating_supply: u64,
+    pub trade_count: u64,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+impl LegacyMarket {
+    pub const MAX_SIZE: usize = 32 * 4 + 8 * 4 + 4 + 32 + 4 + 10 + 4 + 200;
+
+    // Legacy accounts share the `Market` discriminator; only their length tells them apart
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() == 8 + Self::MAX_SIZE && data.starts_with(Market::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        Self::deserialize(&mut &data[8..]).map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+    }
+
+    // Every legacy market priced on the global exponential curve and had no graduation
+    // threshold, so it takes the defaults `create_market` applies
+    pub fn upgrade(self) -> Market {
+        Market {
+            owner: self.owner,
+            mint: self.mint,
+            escrow: self.escrow,
+            treasury: self.treasury,
+            current_price: self.current_price,
+            total_supply: self.total_supply,
+            circulating_supply: self.circulating_supply,
+            trade_count: self.trade_count,
+            curve: CurveParams {
+                curve_type: CurveType::Exponential,
+                base_price: BASE_PRICE,
+                k_factor: K_FACTOR,
+                scale_factor: SCALE_FACTOR,
+                max_price: BASE_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER,
+            },
+            trading_mode: TradingMode::Active,
+            metadata_locked: false,
+            name: self.name,
+            symbol: self.symbol,
+            uri: self.uri,
+            graduation: GraduationThreshold::SoldSupply(
+                ((self.total_supply as u128) * (DEFAULT_GRADUATION_SUPPLY_BPS as u128)
+                    / (BPS_DENOMINATOR as u128)) as u64,
+            ),
+            status: MarketStatus::Active,
+            closing_started_at: 0,
+            creator_allocation: 0,
+            escrow_closed: false,
+        }
+    }
+}
+
+/// When a market leaves its curve for a pool, chosen in `create_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraduationThreshold {
+    /// Once this many tokens are in circulation; must leave some of the supply unsold
+    SoldSupply(u64),
+    /// Once current_price × circulating_supply reaches this many lamports
+    MarketCap(u64),
+}
+
+impl GraduationThreshold {
+    pub const SIZE: usize = 1 + 8;
+
+    pub fn validate(&self, total_supply: u64) -> Result<()> {
+        let valid = match *self {
+            GraduationThreshold::SoldSupply(supply) => supply > 0 && supply < total_supply,
+            GraduationThreshold::MarketCap(cap) => cap > 0,
+        };
+        require!(valid, ExchangeError::InvalidGraduationThreshold);
+        Ok(())
+    }
+}
+
+/// Where a market is in its life: trading on its curve, trading in its pool after
+/// `graduate_market`, or winding down after `close_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketStatus {
+    Active,
+    Graduated,
+    /// Trading is frozen; holders redeem against the treasury until `finalize_close`
+    Closing,
+}
+
+/// A creator allocation requested in `create_market` and released by `claim_vested`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VestingParams {
+    /// Tokens reserved out of `initial_supply`, at most MAX_CREATOR_ALLOCATION_BPS of it
+    pub amount: u64,
+    /// Seconds after creation before anything is released
+    pub cliff_seconds: i64,
+    /// Seconds after creation until the whole allocation is released; at least the cliff
+    pub duration_seconds: i64,
+}
+
+impl VestingParams {
+    pub fn validate(&self, initial_supply: u64) -> Result<()> {
+        let max_amount = (initial_supply as u128 * MAX_CREATOR_ALLOCATION_BPS as u128
+            / BPS_DENOMINATOR as u128) as u64;
+        let valid = self.amount > 0
+            && self.amount <= max_amount
+            && self.cliff_seconds >= 0
+            && self.duration_seconds > 0
+            && self.cliff_seconds <= self.duration_seconds;
+        require!(valid, ExchangeError::InvalidVestingSchedule);
+        Ok(())
+    }
+}
+
+/// A market creator's allocation, locked in the schedule's associated token account and
+/// released on a cliff followed by a linear unlock. One per market, at `["vesting", market]`.
+#[account]
+pub struct VestingSchedule {
+    pub market: Pubkey,
+    /// The market owner at creation, the only one who can claim
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    /// Release is measured from here, the market's creation
+    pub start_time: i64,
+    /// Nothing is released before this
+    pub cliff_time: i64,
+    /// Everything is released from here on
+    pub end_time: i64,
+    pub bump: u8,
+}
+
+impl VestingSchedule {
+    pub const MAX_SIZE: usize = 32 * 4 + 8 * 2 + 8 * 3 + 1;
+
+    // Tokens released by `now`, claimed or not
+    pub fn vested(&self, now: i64) -> u64 {
+        math::vested_amount(
+            self.total_amount,
+            self.start_time,
+            self.cliff_time,
+            self.end_time,
+            now,
+        )
+    }
+}
+
+/// A constant-product pool of SOL and one token, opened by `graduate_market` or
+/// `initialize_pool`. The SOL sits in the `pool-sol` PDA, the tokens in the pool's
+/// associated token account, and the pool mints LP tokens from the `pool-lp` PDA.
+#[account]
+pub struct Pool {
+    /// The market that graduated into this pool; None when opened with `initialize_pool`
+    pub market: Option<Pubkey>,
+    pub mint: Pubkey,
+    /// Receives the creator share of swap fees: the market owner or the pool's opener
+    pub creator: Pubkey,
+    pub token_vault: Pubkey,
+    pub sol_vault: Pubkey,
+    pub lp_mint: Pubkey,
+    /// Liquidity no LP token stands for, locked in the pool for good
+    pub locked_liquidity: u64,
+    pub bump: u8,
+}
+
+impl Pool {
+    pub const MAX_SIZE: usize = (1 + 32) + 32 * 5 + 8 + 1;
+
+    // A graduated market's pool trades under that market's effective mode, so pausing the
+    // market pauses its pool too; other pools only follow the global mode
+    fn trading_mode(
+        &self,
+        market: Option<&Account<Market>>,
+        config: &GlobalConfig,
+    ) -> Result<TradingMode> {
+        match (self.market, market) {
+            (None, None) => Ok(config.trading_mode),
+            (Some(expected), Some(market)) if market.key() == expected => {
+                Ok(market.effective_trading_mode(config))
+            }
+            _ => err!(ExchangeError::InvalidMarket),
+        }
+    }
+
+    // The lamports and tokens a pool prices against; its SOL vault's rent stays out
+    fn reserves(sol_vault: &AccountInfo, token_vault: &TokenAccount) -> Result<(u64, u64)> {
+        let rent = Rent::get()?.minimum_balance(0);
+        Ok((
+            sol_vault.lamports().saturating_sub(rent),
+            token_vault.amount,
+        ))
+    }
+
+    /// Every liquidity unit of the pool: LP tokens in circulation plus the locked ones.
+    pub fn lp_supply(&self, lp_mint: &Mint) -> Result<u64> {
+        Ok(lp_mint
+            .supply
+            .checked_add(self.locked_liquidity)
+            .ok_or(ExchangeError::MathError)?)
+    }
+}
+
+/// Ordered from least to most restrictive.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum TradingMode {
+    Active,
+    SellOnly,
+    Paused,
+}
+
+/// Shape of a market's bonding curve, chosen in `create_market`. Every type starts at
+/// `base_price`, grows at a rate set by `k_factor / scale_factor` and stops at `max_price`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveType {
+    /// base_price × e^(K × S / SCALE_FACTOR)
+    Exponential,
+    /// base_price × (1 + K × S / SCALE_FACTOR); flat presale pricing with `max_price == base_price`
+    Linear,
+    /// Eases from base_price to max_price over the first SCALE_FACTOR / K tokens
+    Sigmoid,
+    /// Virtual x*y=k pool of SCALE_FACTOR / K tokens priced at base_price each
+    ConstantProduct,
+}
+
+/// Bonding curve parameters fixed at market creation.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CurveParams {
+    pub curve_type: CurveType,
+    pub base_price: u64,
+    pub k_factor: u64,
+    pub scale_factor: u64,
+    /// The price never rises above this; equal to `base_price` for a flat curve.
+    pub max_price: u64,
+}
+
+impl CurveParams {
+    pub const SIZE: usize = 1 + 8 * 4;
+
+    pub fn validate(&self) -> Result<()> {
+        math::CurveParams::from(*self)
+            .validate()
+            .map_err(|_| error!(ExchangeError::InvalidCurveParams))
+    }
+}
+
+/// How fast a market's curve climbs, chosen in `create_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveGrowth {
+    /// `k_factor / scale_factor`, read by each curve type as its `CurveType` describes
+    Rate { k_factor: u64, scale_factor: u64 },
+    /// ConstantProduct only: the virtual pool starts with `token_reserve` tokens against
+    /// `token_reserve × base_price` lamports
+    VirtualReserves { token_reserve: u64 },
+}
+
+impl CurveGrowth {
+    // The `k_factor` and `scale_factor` this growth stores for `curve_type`
+    pub fn factors(&self, curve_type: CurveType) -> Result<(u64, u64)> {
+        match *self {
+            CurveGrowth::Rate {
+                k_factor,
+                scale_factor,
+            } => Ok((k_factor, scale_factor)),
+            CurveGrowth::VirtualReserves { token_reserve } => {
+                require!(
+                    curve_type == CurveType::ConstantProduct,
+                    ExchangeError::InvalidCurveParams
+                );
+                Ok((1, token_reserve))
+            }
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MarketDetails {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    pub current_price: u64,
+    pub total_supply: u64,
+    pub circulating_supply: u64,
+    pub trade_count: u64,
+    pub curve: CurveParams,
+    pub trading_mode: TradingMode,
+    pub metadata_locked: bool,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub graduation: GraduationThreshold,
+    pub status: MarketStatus,
+    pub closing_started_at: i64,
+    pub creator_allocation: u64,
+    pub escrow_closed: bool,
+}
+
+/// A trade priced against the market as it stands, returned by `quote_buy` and
+/// `quote_sell`. The trade handlers build the same thing, so a quote never drifts
+/// from what the trade itself charges.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TradeQuote {
+    pub tokens: u64,
+    /// Curve cost of the tokens on a buy, their curve value on a sell
+    pub total_cost: u64,
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    /// Everything the buyer pays, or what the seller receives after fees
+    pub net_lamports: u64,
+    /// Spot price once the trade has gone through
+    pub price_after: u64,
+}
+
+/// A market's treasury against what it owes, returned by `verify_market`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SolvencyReport {
+    pub treasury_balance: u64,
+    /// Rent-exempt minimum of the treasury account
+    pub rent_reserve: u64,
+    /// Curve value of the circulating supply, what selling every token back pays out
+    pub curve_reserve: u64,
+    /// Lamports missing from `rent_reserve + curve_reserve`; 0 while the market is solvent
+    pub shortfall: u64,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub fee_wallet: Pubkey,
+    pub creation_fee: u64,
+    pub transaction_fee_bps: u64,
+    pub platform_fee_share_bps: u64,
+    pub creator_fee_share_bps: u64,
+    pub min_fee: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub previous_admin: Pubkey,
+    pub new_admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketPaused {
+    pub market: Option<Pubkey>,
+    /// `None` with `market`, for the global switch
+    pub sequence: Option<u64>,
+    pub mode: TradingMode,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketResumed {
+    pub market: Option<Pubkey>,
+    /// `None` with `market`, for the global switch
+    pub sequence: Option<u64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MintAuthorityMigrated {
+    pub market: Pubkey,
+    pub mint: Pubkey,
+    pub mint_authority: Pubkey,
+    pub sequence: u64,
+    /// After any top-up backing the circulating supply
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketMetadataUpdated {
+    pub market: Pubkey,
+    pub sequence: u64,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub locked: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketCreated {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub initial_price: u64,
+    pub initial_supply: u64,
+    pub curve_type: CurveType,
+    pub base_price: u64,
+    pub k_factor: u64,
+    pub scale_factor: u64,
+    pub max_price: u64,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// Always 0: the first trade is sequence 1
+    pub sequence: u64,
+    pub circulating_supply: u64,
+    /// Lamports in the market treasury once the market exists
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeKind {
+    Buy,
+    Sell,
+}
+
+#[event]
+pub struct TradeExecuted {
+    pub market: Pubkey,
+    pub buyer: Option<Pubkey>,
+    pub seller: Option<Pubkey>,
+    pub tokens: u64,
+    pub price: u64,
+    pub total_cost: u64,
+    /// Always add up to the fee charged; the platform takes the rounding remainder
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    /// The fee and its shares before rounding or the minimum fee, in
+    /// 1 / FEE_PRECISION lamports
+    pub fee_unrounded: u128,
+    pub platform_fee_unrounded: u128,
+    pub creator_fee_unrounded: u128,
+    pub kind: TradeKind,
+    /// The market's `trade_count` after this trade: 1 for its first trade, then one
+    /// more per trade or lifecycle event, so a skipped number is a missed event
+    pub sequence: u64,
+    pub circulating_supply_before: u64,
+    pub circulating_supply_after: u64,
+    /// Lamports left in the market treasury after the trade settled
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketGraduated {
+    pub market: Pubkey,
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub circulating_supply: u64,
+    /// Curve price of the last trade before graduation
+    pub final_price: u64,
+    /// Lamports and tokens the pool opened with
+    pub sol_reserve: u64,
+    pub token_reserve: u64,
+    pub locked_liquidity: u64,
+    /// Follows on from the market's `TradeExecuted` sequence numbers, as do those of
+    /// `MarketClosing`, `TokensRedeemed` and `MarketClosed`
+    pub sequence: u64,
+    /// Lamports left in the market treasury: its rent once the reserve has moved
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketClosing {
+    pub market: Pubkey,
+    /// The market owner or the admin
+    pub initiated_by: Pubkey,
+    pub sequence: u64,
+    pub circulating_supply: u64,
+    pub treasury_balance: u64,
+    /// After this, `finalize_close` can run with tokens still unredeemed
+    pub grace_period_ends_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokensRedeemed {
+    pub market: Pubkey,
+    pub holder: Pubkey,
+    pub tokens: u64,
+    pub lamports: u64,
+    pub sequence: u64,
+    pub circulating_supply_before: u64,
+    /// Tokens still to be redeemed
+    pub circulating_supply_after: u64,
+    pub treasury_balance: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct MarketClosed {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub sequence: u64,
+    /// Tokens still to be redeemed; while any are, the market and treasury stay open
+    /// for them and only the escrow has closed
+    pub unredeemed_supply: u64,
+    /// Lamports backing those tokens; 0 once the treasury has closed
+    pub treasury_balance: u64,
+    /// Lamports of the accounts closed, all paid to the owner
+    pub lamports_returned: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestingScheduleCreated {
+    pub market: Pubkey,
+    pub schedule: Pubkey,
+    pub beneficiary: Pubkey,
+    pub mint: Pubkey,
+    pub total_amount: u64,
+    /// The unlock schedule: nothing before `cliff_time`, then linear from `start_time`
+    /// until everything is released at `end_time`
+    pub start_time: i64,
+    pub cliff_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct VestedTokensClaimed {
+    pub market: Pubkey,
+    pub schedule: Pubkey,
+    pub beneficiary: Pubkey,
+    pub amount: u64,
+    /// Claimed so far, this claim included
+    pub claimed_amount: u64,
+    /// Still in the vault, vested or not
+    pub locked_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PoolInitialized {
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub creator: Pubkey,
+    pub lp_mint: Pubkey,
+    /// Lamports and tokens the pool opened with
+    pub sol_reserve: u64,
+    pub token_reserve: u64,
+    /// Opening liquidity; the creator receives all of it but `locked_liquidity`
+    pub liquidity: u64,
+    pub locked_liquidity: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapExecuted {
+    pub pool: Pubkey,
+    pub mint: Pubkey,
+    pub user: Pubkey,
+    pub kind: TradeKind,
+    /// Lamports on a buy, tokens on a sell
+    pub amount_in: u64,
+    /// Tokens on a buy, lamports after fees on a sell
+    pub amount_out: u64,
+    pub platform_fee: u64,
+    pub creator_fee: u64,
+    /// The pool's reserves once the swap settled
+    pub sol_reserve: u64,
+    pub token_reserve: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub pool: Pubkey,
+    pub provider: Pubkey,
+    pub liquidity: u64,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    /// LP supply afterwards, locked liquidity included
+    pub lp_supply: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub pool: Pubkey,
+    pub provider: Pubkey,
+    pub liquidity: u64,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    /// LP supply afterwards, locked liquidity included
+    pub lp_supply: u64,
     pub timestamp: i64,
 }
 
@@ -649,4 +3367,52 @@ pub enum ExchangeError {
     InvalidPlatformWallet,
     #[msg("Invalid creator wallet")]
     InvalidCreatorWallet,
+    #[msg("Invalid curve parameters")]
+    InvalidCurveParams,
+    #[msg("Escrow token account does not belong to this market")]
+    InvalidEscrowAccount,
+    #[msg("Token account mint does not match the market mint")]
+    InvalidMint,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Invalid fee configuration")]
+    InvalidFeeConfig,
+    #[msg("Trading is paused")]
+    Paused,
+    #[msg("Mint authority already migrated")]
+    AlreadyMigrated,
+    #[msg("Market metadata is locked")]
+    MetadataLocked,
+    #[msg("Treasury cannot cover the circulating supply")]
+    TreasuryInsolvent,
+    #[msg("Invalid graduation threshold")]
+    InvalidGraduationThreshold,
+    #[msg("Bonding curve is complete; the market trades in its pool")]
+    CurveComplete,
+    #[msg("Graduation threshold not reached")]
+    GraduationThresholdNotReached,
+    #[msg("Not enough liquidity")]
+    InsufficientLiquidity,
+    #[msg("Pools for market tokens open when the market graduates")]
+    PoolReservedForMarket,
+    #[msg("Market has graduated or is closing")]
+    MarketNotActive,
+    #[msg("Market is closing; holders can only redeem")]
+    MarketClosing,
+    #[msg("Market is not closing")]
+    MarketNotClosing,
+    #[msg("Holders can still redeem; the grace period has not ended or tokens are still out")]
+    RedemptionWindowOpen,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Vested tokens are only released once the market has graduated")]
+    MarketNotGraduated,
+    #[msg("Market does not match the pool")]
+    InvalidMarket,
+    #[msg("Sale would pay nothing once fees are taken")]
+    SaleProceedsTooSmall,
 }
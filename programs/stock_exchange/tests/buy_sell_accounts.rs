@@ -0,0 +1,258 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use celebrity_exchange::ExchangeError;
+use common::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SUPPLY: u64 = 1_000_000;
+
+struct Setup {
+    context: ProgramTestContext,
+    user: Keypair,
+    market: MarketFixture,
+    other_market: MarketFixture,
+    user_token_account: Pubkey,
+    user_other_token_account: Pubkey,
+    stray_escrow: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let mut program_test = program_test();
+
+    let user = Keypair::new();
+    add_system_account(&mut program_test, user.pubkey(), USER_LAMPORTS);
+    add_config(&mut program_test, Pubkey::new_unique());
+
+    let market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    market.add_to(&mut program_test, SUPPLY);
+    let other_market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    other_market.add_to(&mut program_test, SUPPLY);
+
+    let user_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_token_account,
+        market.mint,
+        user.pubkey(),
+        0,
+    );
+
+    // Worthless tokens of another mint the user would like to dump on the treasury
+    let user_other_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_other_token_account,
+        other_market.mint,
+        user.pubkey(),
+        SUPPLY,
+    );
+
+    // Right mint and owner, but not the escrow recorded on the market
+    let stray_escrow = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        stray_escrow,
+        market.mint,
+        market.escrow_authority,
+        SUPPLY,
+    );
+
+    Setup {
+        context: program_test.start_with_context().await,
+        user,
+        market,
+        other_market,
+        user_token_account,
+        user_other_token_account,
+        stray_escrow,
+    }
+}
+
+async fn buy(setup: &mut Setup, amount: u64) {
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    process(
+        &mut setup.context,
+        &[buy_ix(accounts, amount, u64::MAX)],
+        &[&setup.user],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn buy_and_sell_with_market_accounts() {
+    let mut setup = setup().await;
+    buy(&mut setup, 500).await;
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        500
+    );
+
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    process(
+        &mut setup.context,
+        &[sell_ix(accounts, 200, 0)],
+        &[&setup.user],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        300
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        SUPPLY - 300
+    );
+}
+
+#[tokio::test]
+async fn sell_rejects_tokens_of_another_mint() {
+    let mut setup = setup().await;
+    buy(&mut setup, 500).await;
+
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_other_token_account);
+    let result = process(
+        &mut setup.context,
+        &[sell_ix(accounts, 500, 0)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidMint);
+}
+
+#[tokio::test]
+async fn buy_rejects_user_token_account_of_another_mint() {
+    let mut setup = setup().await;
+
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_other_token_account);
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidMint);
+}
+
+#[tokio::test]
+async fn rejects_escrow_of_another_market() {
+    let mut setup = setup().await;
+
+    let mut accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    accounts.escrow_token_account = setup.other_market.escrow;
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidEscrowAccount);
+}
+
+#[tokio::test]
+async fn rejects_escrow_not_recorded_on_market() {
+    let mut setup = setup().await;
+
+    let mut accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    accounts.escrow_token_account = setup.stray_escrow;
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidEscrowAccount);
+}
+
+#[tokio::test]
+async fn rejects_escrow_as_user_token_account() {
+    let mut setup = setup().await;
+
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.market.escrow);
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidTokenAccount);
+}
+
+#[tokio::test]
+async fn rejects_treasury_of_another_market() {
+    let mut setup = setup().await;
+    buy(&mut setup, 500).await;
+
+    let mut accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    accounts.treasury = setup.other_market.treasury;
+    let result = process(
+        &mut setup.context,
+        &[sell_ix(accounts, 100, 0)],
+        &[&setup.user],
+    )
+    .await;
+
+    // The treasury is a PDA of the market, so the seeds check catches the swap
+    assert_custom_error(result, anchor_lang::error::ErrorCode::ConstraintSeeds);
+}
+
+#[tokio::test]
+async fn rejects_wrong_platform_wallet() {
+    let mut setup = setup().await;
+
+    let mut accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    accounts.platform_fee_wallet = setup.user.pubkey();
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidPlatformWallet);
+}
+
+#[tokio::test]
+async fn rejects_wrong_creator_wallet() {
+    let mut setup = setup().await;
+
+    let mut accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    accounts.creator_wallet = setup.other_market.owner;
+    let result = process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await;
+
+    assert_custom_error(result, ExchangeError::InvalidCreatorWallet);
+}
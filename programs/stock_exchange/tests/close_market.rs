@@ -0,0 +1,260 @@
+mod common;
+
+use celebrity_exchange::{
+    ExchangeError, Market, MarketClosed, MarketClosing, MarketStatus, TokensRedeemed,
+    CLOSE_GRACE_PERIOD,
+};
+use celebrity_exchange_sdk::instructions;
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SUPPLY: u64 = 1_000;
+
+async fn buy(setup: &mut MarketSetup, amount: u64) {
+    let ix = buy_ix(setup.user_accounts(), amount, u64::MAX);
+    setup.send_as_user(&[ix]).await.unwrap();
+}
+
+fn close(setup: &MarketSetup, authority: &Keypair) -> Instruction {
+    instructions::close_market(authority.pubkey(), setup.market.market)
+}
+
+async fn redeem_ix(setup: &mut MarketSetup, amount: u64) -> Instruction {
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let mut accounts =
+        instructions::redeem_accounts(setup.user.pubkey(), setup.market.market, &market);
+    accounts.user_token_account = setup.user_token_account;
+    instructions::redeem_tokens(accounts, amount)
+}
+
+async fn redeem(setup: &mut MarketSetup, amount: u64) -> Result<(), BanksClientError> {
+    let ix = redeem_ix(setup, amount).await;
+    setup.send_as_user(&[ix]).await
+}
+
+async fn finalize(setup: &mut MarketSetup) -> Result<(), BanksClientError> {
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let ix = instructions::finalize_close(setup.market.market, &market);
+    process(&mut setup.context, &[ix], &[]).await
+}
+
+// Lamports held by the accounts `finalize_close` closes
+async fn closable_lamports(setup: &mut MarketSetup) -> u64 {
+    lamports(&mut setup.context, setup.market.market).await
+        + lamports(&mut setup.context, setup.market.escrow).await
+        + lamports(&mut setup.context, setup.market.treasury).await
+}
+
+#[tokio::test]
+async fn holders_redeem_pro_rata_once_the_owner_closes() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    buy(&mut setup, 100).await;
+
+    let result = redeem(&mut setup, 10).await;
+    assert_custom_error(result, ExchangeError::MarketNotClosing);
+
+    let treasury = lamports(&mut setup.context, setup.market.treasury).await;
+    let owner = setup.owner.insecure_clone();
+    let ix = close(&setup, &owner);
+    let events: Vec<MarketClosing> =
+        process_with_events(&mut setup.context, &[ix], &[&owner]).await;
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.status, MarketStatus::Closing);
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.initiated_by, owner.pubkey());
+    // Numbered after the buy
+    assert_eq!(event.sequence, 2);
+    assert_eq!(event.circulating_supply, 100);
+    assert_eq!(event.treasury_balance, treasury);
+    assert_eq!(event.timestamp, market.closing_started_at);
+    assert_eq!(
+        event.grace_period_ends_at,
+        market.closing_started_at + CLOSE_GRACE_PERIOD
+    );
+
+    // Trading is frozen for good
+    let ix = buy_ix(setup.user_accounts(), 1, u64::MAX);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::MarketClosing);
+    let ix = sell_ix(setup.user_accounts(), 1, 0);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::MarketClosing);
+
+    // Every token is worth the same share of the treasury above rent
+    let available = treasury - rent_exempt(0);
+    let user_before = lamports(&mut setup.context, setup.user.pubkey()).await;
+    redeem(&mut setup, 40).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, setup.user.pubkey()).await - user_before,
+        available * 40 / 100
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        60
+    );
+
+    let remaining = lamports(&mut setup.context, setup.market.treasury).await - rent_exempt(0);
+    let ix = redeem_ix(&mut setup, 60).await;
+    let user = setup.user.insecure_clone();
+    let events: Vec<TokensRedeemed> =
+        process_with_events(&mut setup.context, &[ix], &[&user]).await;
+    assert_eq!(events[0].tokens, 60);
+    assert_eq!(events[0].lamports, remaining);
+    assert_eq!(events[0].sequence, 4);
+    assert_eq!(events[0].circulating_supply_before, 60);
+    assert_eq!(events[0].circulating_supply_after, 0);
+    assert_eq!(events[0].treasury_balance, rent_exempt(0));
+
+    // With every token redeemed the accounts close without waiting out the grace period
+    let returned = closable_lamports(&mut setup).await;
+    let owner_before = lamports(&mut setup.context, owner.pubkey()).await;
+    finalize(&mut setup).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, owner.pubkey()).await - owner_before,
+        returned
+    );
+    for address in [
+        setup.market.market,
+        setup.market.escrow,
+        setup.market.treasury,
+    ] {
+        assert!(setup
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[tokio::test]
+async fn only_the_owner_or_admin_can_close() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+
+    let stranger = setup.user.insecure_clone();
+    let ix = close(&setup, &stranger);
+    let result = setup.send(&[ix], &stranger).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+
+    let admin = setup.admin.insecure_clone();
+    let ix = close(&setup, &admin);
+    setup.send(&[ix], &admin).await.unwrap();
+
+    let owner = setup.owner.insecure_clone();
+    let ix = close(&setup, &owner);
+    let result = setup.send(&[ix], &owner).await;
+    assert_custom_error(result, ExchangeError::MarketNotActive);
+}
+
+#[tokio::test]
+async fn unredeemed_tokens_stay_backed_after_the_grace_period() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    buy(&mut setup, 100).await;
+    let owner = setup.owner.insecure_clone();
+    let ix = close(&setup, &owner);
+    setup.send(&[ix], &owner).await.unwrap();
+
+    let result = finalize(&mut setup).await;
+    assert_custom_error(result, ExchangeError::RedemptionWindowOpen);
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    warp_to_timestamp(
+        &mut setup.context,
+        market.closing_started_at + CLOSE_GRACE_PERIOD,
+    )
+    .await;
+
+    // The owner only gets the escrow's rent back; the treasury still backs every token
+    let treasury = lamports(&mut setup.context, setup.market.treasury).await;
+    let escrow_rent = lamports(&mut setup.context, setup.market.escrow).await;
+    let owner_before = lamports(&mut setup.context, owner.pubkey()).await;
+    let ix = instructions::finalize_close(setup.market.market, &market);
+    let events: Vec<MarketClosed> = process_with_events(&mut setup.context, &[ix], &[]).await;
+
+    assert_eq!(
+        lamports(&mut setup.context, owner.pubkey()).await - owner_before,
+        escrow_rent
+    );
+    assert_eq!(
+        lamports(&mut setup.context, setup.market.treasury).await,
+        treasury
+    );
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].owner, owner.pubkey());
+    assert_eq!(events[0].sequence, 3);
+    assert_eq!(events[0].unredeemed_supply, 100);
+    assert_eq!(events[0].treasury_balance, treasury);
+    assert_eq!(events[0].lamports_returned, escrow_rent);
+
+    let result = finalize(&mut setup).await;
+    assert_custom_error(result, ExchangeError::RedemptionWindowOpen);
+
+    // Late holders still get their full share
+    let user_before = lamports(&mut setup.context, setup.user.pubkey()).await;
+    redeem(&mut setup, 100).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, setup.user.pubkey()).await - user_before,
+        treasury - rent_exempt(0)
+    );
+
+    // and with the last token redeemed the rest closes
+    let returned = closable_lamports(&mut setup).await;
+    let owner_before = lamports(&mut setup.context, owner.pubkey()).await;
+    finalize(&mut setup).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, owner.pubkey()).await - owner_before,
+        returned
+    );
+    assert!(setup
+        .context
+        .banks_client
+        .get_account(setup.market.market)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn a_recreated_escrow_does_not_restart_the_close() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    buy(&mut setup, 100).await;
+    let owner = setup.owner.insecure_clone();
+    let ix = close(&setup, &owner);
+    setup.send(&[ix], &owner).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    warp_to_timestamp(
+        &mut setup.context,
+        market.closing_started_at + CLOSE_GRACE_PERIOD,
+    )
+    .await;
+    finalize(&mut setup).await.unwrap();
+
+    // Anyone can open the escrow's associated token account again
+    let payer = setup.context.payer.pubkey();
+    let ix = instructions::create_user_token_account(
+        payer,
+        setup.market.escrow_authority,
+        setup.mint.pubkey(),
+    );
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert!(market.escrow_closed);
+
+    let result = finalize(&mut setup).await;
+    assert_custom_error(result, ExchangeError::RedemptionWindowOpen);
+
+    redeem(&mut setup, 100).await.unwrap();
+    finalize(&mut setup).await.unwrap();
+    assert_eq!(lamports(&mut setup.context, setup.market.market).await, 0);
+    assert_eq!(lamports(&mut setup.context, setup.market.treasury).await, 0);
+    // The recreated account is left to whoever opened it
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        0
+    );
+}
@@ -0,0 +1,710 @@
+#![allow(dead_code)]
+
+pub mod log_data;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::Event;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use base64::Engine;
+use celebrity_exchange::{
+    ConfigParams, CurveParams, CurveType, GlobalConfig, GraduationThreshold, Market, MarketStatus,
+    TradingMode, BASE_PRICE, DEFAULT_MAX_PRICE_MULTIPLIER, K_FACTOR, SCALE_FACTOR,
+};
+use celebrity_exchange_sdk::events::decode_event;
+use celebrity_exchange_sdk::instructions;
+use celebrity_exchange_sdk::pda::{self, MarketAddresses};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+pub const USER_LAMPORTS: u64 = 1_000_000_000_000;
+pub const FEE_WALLET: Pubkey = Pubkey::new_from_array([0xfe; 32]);
+pub const CREATION_FEE: u64 = 100_000_000;
+pub const TRANSACTION_FEE_BPS: u64 = 100;
+pub const PLATFORM_FEE_SHARE_BPS: u64 = 7_000;
+pub const CREATOR_FEE_SHARE_BPS: u64 = 3_000;
+pub const MIN_TRADE_FEE: u64 = 5_000;
+// Token Metadata allocates every metadata account at this fixed size
+const METADATA_LEN: usize = 679;
+
+// Anchor's entrypoint ties the account slice and AccountInfo lifetimes together,
+// which the native processor signature does not, so hand it a leaked copy.
+fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> anchor_lang::solana_program::entrypoint::ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    celebrity_exchange::entry(program_id, accounts, data)
+}
+
+pub fn program_test() -> ProgramTest {
+    log_data::install();
+    let mut program_test = ProgramTest::new(
+        "celebrity_exchange",
+        celebrity_exchange::ID,
+        processor!(process_instruction),
+    );
+    // The mainnet Token Metadata binary, loaded from tests/fixtures
+    program_test.prefer_bpf(true);
+    program_test.add_program("mpl_token_metadata", mpl_token_metadata::ID, None);
+    program_test
+}
+
+pub fn config_address() -> Pubkey {
+    pda::config()
+}
+
+pub fn program_data_address() -> Pubkey {
+    pda::program_data()
+}
+
+pub fn default_config_params() -> ConfigParams {
+    ConfigParams {
+        fee_wallet: FEE_WALLET,
+        creation_fee: CREATION_FEE,
+        transaction_fee_bps: TRANSACTION_FEE_BPS,
+        platform_fee_share_bps: PLATFORM_FEE_SHARE_BPS,
+        creator_fee_share_bps: CREATOR_FEE_SHARE_BPS,
+        min_fee: MIN_TRADE_FEE,
+    }
+}
+
+/// Adds an initialized `GlobalConfig` with the default fees and a funded fee wallet.
+pub fn add_config(program_test: &mut ProgramTest, admin: Pubkey) {
+    let params = default_config_params();
+    let config = GlobalConfig {
+        admin,
+        pending_admin: None,
+        fee_wallet: params.fee_wallet,
+        creation_fee: params.creation_fee,
+        transaction_fee_bps: params.transaction_fee_bps,
+        platform_fee_share_bps: params.platform_fee_share_bps,
+        creator_fee_share_bps: params.creator_fee_share_bps,
+        min_fee: params.min_fee,
+        trading_mode: TradingMode::Active,
+        bump: Pubkey::find_program_address(&[b"config"], &celebrity_exchange::ID).1,
+    };
+    let mut data = Vec::with_capacity(8 + GlobalConfig::MAX_SIZE);
+    config.try_serialize(&mut data).unwrap();
+    data.resize(8 + GlobalConfig::MAX_SIZE, 0);
+    program_test.add_account(
+        config_address(),
+        Account {
+            lamports: rent_exempt(data.len()),
+            data,
+            owner: celebrity_exchange::ID,
+            ..Account::default()
+        },
+    );
+
+    add_system_account(program_test, FEE_WALLET, rent_exempt(0));
+}
+
+/// Adds the upgradeable loader's ProgramData account naming `upgrade_authority`.
+pub fn add_program_data(program_test: &mut ProgramTest, upgrade_authority: Pubkey) {
+    // bincode layout of UpgradeableLoaderState::ProgramData { slot, upgrade_authority_address }
+    let mut data = Vec::with_capacity(45);
+    data.extend_from_slice(&3u32.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(upgrade_authority.as_ref());
+    program_test.add_account(
+        program_data_address(),
+        Account {
+            lamports: rent_exempt(data.len()),
+            data,
+            owner: ProgramData::owner(),
+            ..Account::default()
+        },
+    );
+}
+
+/// The curve `create_market` gives a market priced at `BASE_PRICE` by default.
+pub fn default_curve() -> CurveParams {
+    CurveParams {
+        curve_type: CurveType::Exponential,
+        base_price: BASE_PRICE,
+        k_factor: K_FACTOR,
+        scale_factor: SCALE_FACTOR,
+        max_price: BASE_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER,
+    }
+}
+
+/// Addresses of a market written straight into the test genesis.
+pub struct MarketFixture {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub mint_authority: Pubkey,
+    pub metadata: Pubkey,
+    pub market: Pubkey,
+    pub escrow_authority: Pubkey,
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+}
+
+impl MarketFixture {
+    pub fn new(mint: Pubkey, owner: Pubkey) -> Self {
+        let addresses = MarketAddresses::new(mint);
+
+        Self {
+            owner,
+            mint,
+            mint_authority: addresses.mint_authority,
+            metadata: addresses.metadata,
+            market: addresses.market,
+            escrow_authority: addresses.escrow_authority,
+            escrow: addresses.escrow,
+            treasury: addresses.treasury,
+        }
+    }
+
+    /// Adds the mint, market, escrow and treasury accounts for a fresh market.
+    pub fn add_to(&self, program_test: &mut ProgramTest, total_supply: u64) {
+        self.add_with_curve(program_test, total_supply, default_curve());
+    }
+
+    /// Same as `add_to`, for a market priced by `curve`.
+    pub fn add_with_curve(
+        &self,
+        program_test: &mut ProgramTest,
+        total_supply: u64,
+        curve: CurveParams,
+    ) {
+        // Out of reach, so the curve trades until the escrow runs dry
+        let graduation = GraduationThreshold::SoldSupply(u64::MAX);
+        self.add_with_graduation(program_test, total_supply, curve, graduation);
+    }
+
+    /// Same as `add_with_curve`, for a market that graduates at `graduation`.
+    pub fn add_with_graduation(
+        &self,
+        program_test: &mut ProgramTest,
+        total_supply: u64,
+        curve: CurveParams,
+        graduation: GraduationThreshold,
+    ) {
+        add_mint(program_test, self.mint, total_supply, self.mint_authority);
+        add_token_account(
+            program_test,
+            self.escrow,
+            self.mint,
+            self.escrow_authority,
+            total_supply,
+        );
+
+        let market = Market {
+            owner: self.owner,
+            mint: self.mint,
+            escrow: self.escrow,
+            treasury: self.treasury,
+            current_price: curve.base_price,
+            total_supply,
+            circulating_supply: 0,
+            trade_count: 0,
+            curve,
+            trading_mode: TradingMode::Active,
+            metadata_locked: false,
+            name: "Test Celebrity".to_string(),
+            symbol: "TEST".to_string(),
+            uri: "https://example.com/test.json".to_string(),
+            graduation,
+            status: MarketStatus::Active,
+            closing_started_at: 0,
+            creator_allocation: 0,
+            escrow_closed: false,
+        };
+        let mut data = Vec::with_capacity(8 + Market::MAX_SIZE);
+        market.try_serialize(&mut data).unwrap();
+        data.resize(8 + Market::MAX_SIZE, 0);
+        program_test.add_account(
+            self.market,
+            Account {
+                lamports: rent_exempt(data.len()),
+                data,
+                owner: celebrity_exchange::ID,
+                ..Account::default()
+            },
+        );
+
+        add_system_account(program_test, self.treasury, rent_exempt(0));
+        add_system_account(program_test, self.owner, rent_exempt(0));
+    }
+
+    /// Adds a market as the first release created it: minted by `mint_authority`, with
+    /// `circulating_supply` sold and the account in the original byte layout.
+    pub fn add_legacy_to(
+        &self,
+        program_test: &mut ProgramTest,
+        total_supply: u64,
+        circulating_supply: u64,
+        mint_authority: Pubkey,
+    ) {
+        add_mint(program_test, self.mint, total_supply, mint_authority);
+        add_token_account(
+            program_test,
+            self.escrow,
+            self.mint,
+            self.escrow_authority,
+            total_supply - circulating_supply,
+        );
+
+        // owner, mint, escrow, treasury, current_price, total_supply, circulating_supply,
+        // trade_count, then name, symbol and uri as borsh strings, padded to 8 + 414 bytes
+        let mut data = Market::DISCRIMINATOR.to_vec();
+        for key in [self.owner, self.mint, self.escrow, self.treasury] {
+            data.extend_from_slice(key.as_ref());
+        }
+        for value in [BASE_PRICE, total_supply, circulating_supply, 7] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for text in ["Test Celebrity", "TEST", "https://example.com/test.json"] {
+            data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            data.extend_from_slice(text.as_bytes());
+        }
+        data.resize(8 + 414, 0);
+        program_test.add_account(
+            self.market,
+            Account {
+                lamports: rent_exempt(data.len()),
+                data,
+                owner: celebrity_exchange::ID,
+                ..Account::default()
+            },
+        );
+
+        // The stepped curve charged the base price for each of the first 2,000 tokens, and
+        // nothing funded the treasury to rent exemption
+        add_system_account(program_test, self.treasury, circulating_supply * BASE_PRICE);
+        add_system_account(program_test, self.owner, rent_exempt(0));
+    }
+
+    /// Adds the token metadata account `create_market` would have made, owned by `update_authority`.
+    pub fn add_metadata_to(&self, program_test: &mut ProgramTest, update_authority: Pubkey) {
+        let metadata = mpl_token_metadata::accounts::Metadata {
+            key: mpl_token_metadata::types::Key::MetadataV1,
+            update_authority,
+            mint: self.mint,
+            name: "Test Celebrity".to_string(),
+            symbol: "TEST".to_string(),
+            uri: "https://example.com/test.json".to_string(),
+            seller_fee_basis_points: 0,
+            creators: None,
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: None,
+            collection: None,
+            uses: None,
+            collection_details: None,
+            programmable_config: None,
+        };
+        let mut data = borsh::to_vec(&metadata).unwrap();
+        data.resize(METADATA_LEN, 0);
+        program_test.add_account(
+            self.metadata,
+            Account {
+                lamports: rent_exempt(data.len()),
+                data,
+                owner: mpl_token_metadata::ID,
+                ..Account::default()
+            },
+        );
+    }
+
+    /// `create_market` paid for by the fixture's owner, who must sign along with the mint.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_market_ix(
+        &self,
+        initial_price_lamports: u64,
+        initial_supply: u64,
+        name: &str,
+        symbol: &str,
+        uri: &str,
+        curve_type: CurveType,
+        max_price_lamports: Option<u64>,
+    ) -> Instruction {
+        self.create_market_with(celebrity_exchange::instruction::CreateMarket {
+            initial_price_lamports,
+            initial_supply,
+            name: name.to_string(),
+            symbol: symbol.to_string(),
+            uri: uri.to_string(),
+            curve_type,
+            max_price_lamports,
+            graduation_threshold: None,
+            creator_allocation: None,
+            curve_growth: None,
+        })
+    }
+
+    /// `create_market` with every argument spelled out.
+    pub fn create_market_with(
+        &self,
+        args: celebrity_exchange::instruction::CreateMarket,
+    ) -> Instruction {
+        instructions::create_market(self.owner, self.mint, FEE_WALLET, args)
+    }
+
+    pub fn buy_sell_accounts(
+        &self,
+        user: Pubkey,
+        user_token_account: Pubkey,
+    ) -> celebrity_exchange::accounts::BuySell {
+        celebrity_exchange::accounts::BuySell {
+            user,
+            market: self.market,
+            escrow_authority: self.escrow_authority,
+            escrow_token_account: self.escrow,
+            treasury: self.treasury,
+            user_token_account,
+            config: config_address(),
+            platform_fee_wallet: FEE_WALLET,
+            creator_wallet: self.owner,
+            token_program: spl_token::ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+    }
+}
+
+/// A started bank with the config, funded admin, owner and user keypairs, and the
+/// accounts `add_accounts` gave a market of the `mint` keypair; the user holds
+/// `user_tokens` of the mint in its associated token account.
+pub struct MarketSetup {
+    pub context: ProgramTestContext,
+    pub admin: Keypair,
+    pub owner: Keypair,
+    pub user: Keypair,
+    pub mint: Keypair,
+    pub market: MarketFixture,
+    pub addresses: MarketAddresses,
+    pub user_token_account: Pubkey,
+}
+
+impl MarketSetup {
+    /// A market of `total_supply` on the default curve, none of it sold.
+    pub async fn start(total_supply: u64) -> Self {
+        Self::start_with(0, |program_test, market| {
+            market.add_to(program_test, total_supply)
+        })
+        .await
+    }
+
+    /// Same as `start`, with `add_accounts` adding the market, or none for a market
+    /// the test creates itself.
+    pub async fn start_with(
+        user_tokens: u64,
+        add_accounts: impl FnOnce(&mut ProgramTest, &MarketFixture),
+    ) -> Self {
+        let admin = Keypair::new();
+        let owner = Keypair::new();
+        let user = Keypair::new();
+        let mint = Keypair::new();
+        let market = MarketFixture::new(mint.pubkey(), owner.pubkey());
+
+        let mut program_test = program_test();
+        add_config(&mut program_test, admin.pubkey());
+        for signer in [&admin, &owner, &user] {
+            add_system_account(&mut program_test, signer.pubkey(), USER_LAMPORTS);
+        }
+        add_accounts(&mut program_test, &market);
+
+        let user_token_account = get_associated_token_address(&user.pubkey(), &market.mint);
+        add_token_account(
+            &mut program_test,
+            user_token_account,
+            market.mint,
+            user.pubkey(),
+            user_tokens,
+        );
+
+        Self {
+            context: program_test.start_with_context().await,
+            admin,
+            owner,
+            user,
+            addresses: MarketAddresses::new(market.mint),
+            mint,
+            market,
+            user_token_account,
+        }
+    }
+
+    /// Processes `instructions` signed by `signer` on top of the payer.
+    pub async fn send(
+        &mut self,
+        instructions: &[Instruction],
+        signer: &Keypair,
+    ) -> std::result::Result<(), BanksClientError> {
+        process(&mut self.context, instructions, &[signer]).await
+    }
+
+    /// `send` signed by the user.
+    pub async fn send_as_user(
+        &mut self,
+        instructions: &[Instruction],
+    ) -> std::result::Result<(), BanksClientError> {
+        let user = self.user.insecure_clone();
+        self.send(instructions, &user).await
+    }
+
+    /// The user's `BuySell` accounts for the market.
+    pub fn user_accounts(&self) -> celebrity_exchange::accounts::BuySell {
+        self.market
+            .buy_sell_accounts(self.user.pubkey(), self.user_token_account)
+    }
+}
+
+pub fn rent_exempt(len: usize) -> u64 {
+    Rent::default().minimum_balance(len)
+}
+
+pub fn add_system_account(program_test: &mut ProgramTest, address: Pubkey, lamports: u64) {
+    program_test.add_account(
+        address,
+        Account {
+            lamports,
+            owner: anchor_lang::system_program::ID,
+            ..Account::default()
+        },
+    );
+}
+
+pub fn add_mint(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    supply: u64,
+    mint_authority: Pubkey,
+) {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint::pack(
+        spl_token::state::Mint {
+            mint_authority: Some(mint_authority).into(),
+            supply,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: Some(mint_authority).into(),
+        },
+        &mut data,
+    )
+    .unwrap();
+    program_test.add_account(
+        address,
+        Account {
+            lamports: rent_exempt(data.len()),
+            data,
+            owner: spl_token::ID,
+            ..Account::default()
+        },
+    );
+}
+
+pub fn add_token_account(
+    program_test: &mut ProgramTest,
+    address: Pubkey,
+    mint: Pubkey,
+    owner: Pubkey,
+    amount: u64,
+) {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account::pack(
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..spl_token::state::Account::default()
+        },
+        &mut data,
+    )
+    .unwrap();
+    program_test.add_account(
+        address,
+        Account {
+            lamports: rent_exempt(data.len()),
+            data,
+            owner: spl_token::ID,
+            ..Account::default()
+        },
+    );
+}
+
+pub fn buy_ix(
+    accounts: celebrity_exchange::accounts::BuySell,
+    amount: u64,
+    max_cost_lamports: u64,
+) -> Instruction {
+    instructions::buy_tokens(accounts, amount, max_cost_lamports)
+}
+
+pub fn sell_ix(
+    accounts: celebrity_exchange::accounts::BuySell,
+    amount: u64,
+    min_receive_lamports: u64,
+) -> Instruction {
+    instructions::sell_tokens(accounts, amount, min_receive_lamports)
+}
+
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> std::result::Result<(), BanksClientError> {
+    let transaction = sign(context, instructions, signers).await;
+    // Executed on the working bank like `process_with_events`, rather than queued for the
+    // banks server's own thread, whose commits can still hold account locks when the
+    // signature status this would poll for appears
+    context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await?
+        .result
+        .map_err(BanksClientError::TransactionError)
+}
+
+pub fn assert_custom_error(
+    result: std::result::Result<(), BanksClientError>,
+    expected: impl Into<u32>,
+) {
+    let expected = expected.into();
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, expected, "unexpected error code"),
+        other => panic!("expected custom error {expected}, got {other:?}"),
+    }
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+pub async fn lamports(context: &mut ProgramTestContext, address: Pubkey) -> u64 {
+    context.banks_client.get_balance(address).await.unwrap()
+}
+
+/// Moves to the next slot, so later transactions get a fresh blockhash, with the clock
+/// at `unix_timestamp`. The clock is overridden after the warp because every new bank
+/// recomputes it, which would silently undo an override set beforehand.
+pub async fn warp_to_timestamp(context: &mut ProgramTestContext, unix_timestamp: i64) {
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    context.warp_to_slot(clock.slot + 1).unwrap();
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    context.set_sysvar(&clock);
+}
+
+pub async fn fetch<T: AccountDeserialize>(context: &mut ProgramTestContext, address: Pubkey) -> T {
+    let account = context
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Processes the transaction and returns every event of type `T` it emitted.
+pub async fn process_with_events<T: Event + AnchorDeserialize>(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<T> {
+    let transaction = sign(context, instructions, signers).await;
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap();
+
+    result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .filter_map(|log| log.strip_prefix(log_data::PREFIX))
+        .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+        .filter_map(|data| decode_event(&data))
+        .collect()
+}
+
+/// Processes the transaction and returns the compute units it consumed.
+pub async fn process_compute_units(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> u64 {
+    let transaction = sign(context, instructions, signers).await;
+    let result = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    result.result.unwrap();
+    result.metadata.unwrap().compute_units_consumed
+}
+
+/// Simulates the transaction and decodes the program's return data.
+pub async fn simulate_return<T: AnchorDeserialize>(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> T {
+    let transaction = sign(context, instructions, signers).await;
+    let simulation = context
+        .banks_client
+        .simulate_transaction(transaction)
+        .await
+        .unwrap();
+    simulation.result.unwrap().unwrap();
+
+    let return_data = simulation
+        .simulation_details
+        .unwrap()
+        .return_data
+        .expect("no return data");
+    assert_eq!(return_data.program_id, celebrity_exchange::ID);
+    T::try_from_slice(&return_data.data).unwrap()
+}
+
+async fn sign(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Transaction {
+    // The bank rejects a transaction identical to one it has seen under the same
+    // blockhash, so a repeat waits for the next blockhash
+    static SIGNED: Mutex<BTreeSet<Signature>> = Mutex::new(BTreeSet::new());
+
+    let mut blockhash = context.banks_client.get_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![context.payer.insecure_clone()];
+    all_signers.extend(signers.iter().map(|signer| signer.insecure_clone()));
+    loop {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&context.payer.pubkey()),
+            &all_signers.iter().collect::<Vec<_>>(),
+            blockhash,
+        );
+        if SIGNED.lock().unwrap().insert(transaction.signatures[0]) {
+            return transaction;
+        }
+        blockhash = context.get_new_latest_blockhash().await.unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+// Compute is only metered for the SBF build: `cargo test-sbf -p celebrity-exchange`
+#![cfg(feature = "test-sbf")]
+
+mod common;
+
+use celebrity_exchange::{CurveParams, CurveType, K_FACTOR, SCALE_FACTOR};
+use common::*;
+use solana_program_test::find_file;
+
+// The default per-instruction limit, which a single trade has to fit in
+const COMPUTE_BUDGET: u64 = 200_000;
+const SUPPLY: u64 = 100_000_000;
+
+// Cheap enough for the user to buy the whole supply, capped early so the buy crosses both
+// the exponential and the flat part of the curve
+const CURVE: CurveParams = CurveParams {
+    curve_type: CurveType::Exponential,
+    base_price: 1,
+    k_factor: K_FACTOR,
+    scale_factor: SCALE_FACTOR,
+    max_price: 1_000,
+};
+
+#[tokio::test]
+async fn the_largest_trades_fit_the_compute_budget() {
+    assert!(
+        find_file("celebrity_exchange.so").is_some(),
+        "run through `cargo test-sbf` so the program executes as SBF"
+    );
+    let mut setup = MarketSetup::start_with(0, |program_test, market| {
+        market.add_with_curve(program_test, SUPPLY, CURVE)
+    })
+    .await;
+    let user = setup.user.insecure_clone();
+
+    let ix = buy_ix(setup.user_accounts(), SUPPLY, u64::MAX);
+    let bought = process_compute_units(&mut setup.context, &[ix], &[&user]).await;
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        SUPPLY
+    );
+    assert!(
+        bought < COMPUTE_BUDGET,
+        "buying out the curve used {bought} CU"
+    );
+
+    let ix = sell_ix(setup.user_accounts(), SUPPLY, 0);
+    let sold = process_compute_units(&mut setup.context, &[ix], &[&user]).await;
+    assert!(
+        sold < COMPUTE_BUDGET,
+        "selling the whole supply used {sold} CU"
+    );
+}
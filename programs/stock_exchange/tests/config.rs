@@ -0,0 +1,401 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use celebrity_exchange::{ConfigParams, ExchangeError, GlobalConfig, MAX_MIN_TRADE_FEE};
+use common::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn initialize_config_ix(authority: Pubkey, admin: Pubkey, params: ConfigParams) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::InitializeConfig {
+            authority,
+            config: config_address(),
+            program_data: program_data_address(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::InitializeConfig { admin, params }.data(),
+    }
+}
+
+fn update_config_ix(admin: Pubkey, params: ConfigParams) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::UpdateConfig {
+            admin,
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::UpdateConfig { params }.data(),
+    }
+}
+
+fn propose_admin_ix(admin: Pubkey, new_admin: Pubkey) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::UpdateConfig {
+            admin,
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::ProposeAdmin { new_admin }.data(),
+    }
+}
+
+fn accept_admin_ix(pending_admin: Pubkey) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::AcceptAdmin {
+            pending_admin,
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::AcceptAdmin {}.data(),
+    }
+}
+
+async fn start_with_admin(admin: Pubkey) -> ProgramTestContext {
+    let mut program_test = program_test();
+    add_config(&mut program_test, admin);
+    program_test.start_with_context().await
+}
+
+async fn start_with_upgrade_authority(upgrade_authority: &Keypair) -> ProgramTestContext {
+    let mut program_test = program_test();
+    add_program_data(&mut program_test, upgrade_authority.pubkey());
+    add_system_account(&mut program_test, upgrade_authority.pubkey(), USER_LAMPORTS);
+    program_test.start_with_context().await
+}
+
+#[tokio::test]
+async fn upgrade_authority_initializes_config() {
+    let upgrade_authority = Keypair::new();
+    let admin = Pubkey::new_unique();
+    let mut context = start_with_upgrade_authority(&upgrade_authority).await;
+
+    let ix = initialize_config_ix(upgrade_authority.pubkey(), admin, default_config_params());
+    process(&mut context, &[ix], &[&upgrade_authority])
+        .await
+        .unwrap();
+
+    let config: GlobalConfig = fetch(&mut context, config_address()).await;
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.fee_wallet, FEE_WALLET);
+    assert_eq!(config.creation_fee, CREATION_FEE);
+    assert_eq!(config.transaction_fee_bps, TRANSACTION_FEE_BPS);
+    assert_eq!(config.platform_fee_share_bps, PLATFORM_FEE_SHARE_BPS);
+    assert_eq!(config.creator_fee_share_bps, CREATOR_FEE_SHARE_BPS);
+    assert_eq!(config.min_fee, MIN_TRADE_FEE);
+}
+
+#[tokio::test]
+async fn initialize_config_rejects_other_signers() {
+    let upgrade_authority = Keypair::new();
+    let intruder = Keypair::new();
+    let mut context = start_with_upgrade_authority(&upgrade_authority).await;
+
+    let ix = initialize_config_ix(
+        intruder.pubkey(),
+        intruder.pubkey(),
+        default_config_params(),
+    );
+    let payer = context.payer.insecure_clone();
+    let transfer = solana_sdk::system_instruction::transfer(
+        &payer.pubkey(),
+        &intruder.pubkey(),
+        USER_LAMPORTS / 1_000,
+    );
+    let result = process(&mut context, &[transfer, ix], &[&intruder]).await;
+
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
+
+#[tokio::test]
+async fn initialize_config_rejects_fee_shares_not_summing_to_100_percent() {
+    let upgrade_authority = Keypair::new();
+    let mut context = start_with_upgrade_authority(&upgrade_authority).await;
+
+    let params = ConfigParams {
+        creator_fee_share_bps: 2_000,
+        ..default_config_params()
+    };
+    let ix = initialize_config_ix(
+        upgrade_authority.pubkey(),
+        upgrade_authority.pubkey(),
+        params,
+    );
+    let result = process(&mut context, &[ix], &[&upgrade_authority]).await;
+
+    assert_custom_error(result, ExchangeError::InvalidFeeConfig);
+}
+
+#[tokio::test]
+async fn update_config_rejects_minimum_fee_above_cap() {
+    let admin = Keypair::new();
+    let mut context = start_with_admin(admin.pubkey()).await;
+
+    let params = ConfigParams {
+        min_fee: MAX_MIN_TRADE_FEE + 1,
+        ..default_config_params()
+    };
+    let ix = update_config_ix(admin.pubkey(), params);
+    let result = process(&mut context, &[ix], &[&admin]).await;
+
+    assert_custom_error(result, ExchangeError::InvalidFeeConfig);
+}
+
+#[tokio::test]
+async fn update_config_rejects_non_admin() {
+    let admin = Keypair::new();
+    let intruder = Keypair::new();
+    let mut context = start_with_admin(admin.pubkey()).await;
+
+    let ix = update_config_ix(intruder.pubkey(), default_config_params());
+    let result = process(&mut context, &[ix], &[&intruder]).await;
+
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
+
+#[tokio::test]
+async fn admin_handover_takes_effect_on_accept() {
+    let admin = Keypair::new();
+    let new_admin = Keypair::new();
+    let mut context = start_with_admin(admin.pubkey()).await;
+
+    let ix = propose_admin_ix(admin.pubkey(), new_admin.pubkey());
+    process(&mut context, &[ix], &[&admin]).await.unwrap();
+
+    let config: GlobalConfig = fetch(&mut context, config_address()).await;
+    assert_eq!(config.admin, admin.pubkey());
+    assert_eq!(config.pending_admin, Some(new_admin.pubkey()));
+
+    process(
+        &mut context,
+        &[accept_admin_ix(new_admin.pubkey())],
+        &[&new_admin],
+    )
+    .await
+    .unwrap();
+
+    let config: GlobalConfig = fetch(&mut context, config_address()).await;
+    assert_eq!(config.admin, new_admin.pubkey());
+    assert_eq!(config.pending_admin, None);
+
+    // The previous admin has lost its rights
+    let ix = update_config_ix(admin.pubkey(), default_config_params());
+    let result = process(&mut context, &[ix], &[&admin]).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+
+    let ix = update_config_ix(new_admin.pubkey(), default_config_params());
+    process(&mut context, &[ix], &[&new_admin]).await.unwrap();
+}
+
+#[tokio::test]
+async fn accept_admin_rejects_anyone_but_the_pending_admin() {
+    let admin = Keypair::new();
+    let new_admin = Keypair::new();
+    let intruder = Keypair::new();
+    let mut context = start_with_admin(admin.pubkey()).await;
+
+    // Nothing proposed yet
+    let result = process(
+        &mut context,
+        &[accept_admin_ix(new_admin.pubkey())],
+        &[&new_admin],
+    )
+    .await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+
+    let ix = propose_admin_ix(admin.pubkey(), new_admin.pubkey());
+    process(&mut context, &[ix], &[&admin]).await.unwrap();
+
+    let result = process(
+        &mut context,
+        &[accept_admin_ix(intruder.pubkey())],
+        &[&intruder],
+    )
+    .await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
+
+#[tokio::test]
+async fn propose_admin_rejects_non_admin() {
+    let admin = Keypair::new();
+    let intruder = Keypair::new();
+    let mut context = start_with_admin(admin.pubkey()).await;
+
+    let ix = propose_admin_ix(intruder.pubkey(), intruder.pubkey());
+    let result = process(&mut context, &[ix], &[&intruder]).await;
+
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
+
+#[tokio::test]
+async fn trades_use_updated_fees_and_wallet() {
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let new_fee_wallet = Pubkey::new_unique();
+
+    let mut program_test = program_test();
+    add_config(&mut program_test, admin.pubkey());
+    add_system_account(&mut program_test, user.pubkey(), USER_LAMPORTS);
+    add_system_account(&mut program_test, new_fee_wallet, rent_exempt(0));
+    let market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    market.add_to(&mut program_test, 1_000_000);
+    let user_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_token_account,
+        market.mint,
+        user.pubkey(),
+        0,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    // 2% fee, split evenly
+    let params = ConfigParams {
+        fee_wallet: new_fee_wallet,
+        creation_fee: CREATION_FEE,
+        transaction_fee_bps: 200,
+        platform_fee_share_bps: 5_000,
+        creator_fee_share_bps: 5_000,
+        min_fee: MIN_TRADE_FEE,
+    };
+    process(
+        &mut context,
+        &[update_config_ix(admin.pubkey(), params)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // The old wallet is no longer accepted
+    let accounts = market.buy_sell_accounts(user.pubkey(), user_token_account);
+    let result = process(&mut context, &[buy_ix(accounts, 100, u64::MAX)], &[&user]).await;
+    assert_custom_error(result, ExchangeError::InvalidPlatformWallet);
+
+    let mut accounts = market.buy_sell_accounts(user.pubkey(), user_token_account);
+    accounts.platform_fee_wallet = new_fee_wallet;
+    let fee_wallet_before = lamports(&mut context, new_fee_wallet).await;
+    let creator_before = lamports(&mut context, market.owner).await;
+    let treasury_before = lamports(&mut context, market.treasury).await;
+    process(&mut context, &[buy_ix(accounts, 100, u64::MAX)], &[&user])
+        .await
+        .unwrap();
+
+    // The curve cost lands in the treasury, the fee is charged on top of it
+    let total_cost = lamports(&mut context, market.treasury).await - treasury_before;
+    // and the platform takes the odd lamport of an uneven split
+    let total_fee = total_cost * 200 / 10_000;
+    assert_eq!(
+        lamports(&mut context, new_fee_wallet).await - fee_wallet_before,
+        total_fee - total_fee / 2
+    );
+    assert_eq!(
+        lamports(&mut context, market.owner).await - creator_before,
+        total_fee / 2
+    );
+}
+
+#[tokio::test]
+async fn trades_pay_the_configured_minimum_fee() {
+    let admin = Keypair::new();
+    let user = Keypair::new();
+
+    let mut program_test = program_test();
+    add_config(&mut program_test, admin.pubkey());
+    add_system_account(&mut program_test, user.pubkey(), USER_LAMPORTS);
+    let market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    market.add_to(&mut program_test, 1_000_000);
+    let user_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_token_account,
+        market.mint,
+        user.pubkey(),
+        0,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let min_fee = 10 * MIN_TRADE_FEE;
+    let params = ConfigParams {
+        min_fee,
+        ..default_config_params()
+    };
+    process(
+        &mut context,
+        &[update_config_ix(admin.pubkey(), params)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // 1% of one token at the base price falls short of the raised minimum
+    let user_before = lamports(&mut context, user.pubkey()).await;
+    let treasury_before = lamports(&mut context, market.treasury).await;
+    let accounts = market.buy_sell_accounts(user.pubkey(), user_token_account);
+    process(&mut context, &[buy_ix(accounts, 1, u64::MAX)], &[&user])
+        .await
+        .unwrap();
+
+    let cost = lamports(&mut context, market.treasury).await - treasury_before;
+    assert!(cost * TRANSACTION_FEE_BPS / 10_000 < min_fee);
+    assert_eq!(
+        user_before - lamports(&mut context, user.pubkey()).await,
+        cost + min_fee
+    );
+}
+
+#[tokio::test]
+async fn rejects_sales_the_minimum_fee_would_swallow() {
+    let admin = Keypair::new();
+    let user = Keypair::new();
+
+    let mut program_test = program_test();
+    add_config(&mut program_test, admin.pubkey());
+    add_system_account(&mut program_test, user.pubkey(), USER_LAMPORTS);
+    let market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    market.add_to(&mut program_test, 1_000_000);
+    let user_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_token_account,
+        market.mint,
+        user.pubkey(),
+        0,
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let params = ConfigParams {
+        min_fee: MAX_MIN_TRADE_FEE,
+        ..default_config_params()
+    };
+    process(
+        &mut context,
+        &[update_config_ix(admin.pubkey(), params)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let accounts = || market.buy_sell_accounts(user.pubkey(), user_token_account);
+    process(&mut context, &[buy_ix(accounts(), 10, u64::MAX)], &[&user])
+        .await
+        .unwrap();
+
+    // One token is worth less than the minimum, so selling it would pay out nothing
+    let result = process(&mut context, &[sell_ix(accounts(), 1, 0)], &[&user]).await;
+    assert_custom_error(result, ExchangeError::SaleProceedsTooSmall);
+    assert_eq!(token_balance(&mut context, user_token_account).await, 10);
+
+    // Ten are worth more, and the seller keeps what the fee leaves
+    let user_before = lamports(&mut context, user.pubkey()).await;
+    process(&mut context, &[sell_ix(accounts(), 10, 1)], &[&user])
+        .await
+        .unwrap();
+    assert!(lamports(&mut context, user.pubkey()).await > user_before);
+}
@@ -0,0 +1,421 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use celebrity_exchange::{
+    CurveGrowth, CurveType, ExchangeError, GraduationThreshold, Market, MarketCreated,
+    MarketDetails, MarketStatus, TradingMode, DEFAULT_MAX_PRICE_MULTIPLIER, K_FACTOR, SCALE_FACTOR,
+};
+use common::*;
+use mpl_token_metadata::accounts::Metadata;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signer;
+
+const INITIAL_PRICE: u64 = 2_000_000;
+const SUPPLY: u64 = 1_000_000;
+
+// A market the test creates itself
+async fn setup() -> MarketSetup {
+    MarketSetup::start_with(0, |_, _| {}).await
+}
+
+async fn create(
+    setup: &mut MarketSetup,
+    ix: Instruction,
+) -> Result<(), solana_program_test::BanksClientError> {
+    process(&mut setup.context, &[ix], &[&setup.owner, &setup.mint]).await
+}
+
+fn default_ix(market: &MarketFixture) -> Instruction {
+    market.create_market_ix(
+        INITIAL_PRICE,
+        SUPPLY,
+        "Celebrity",
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Exponential,
+        None,
+    )
+}
+
+#[tokio::test]
+async fn creates_market_with_escrowed_supply_and_metadata() {
+    let mut setup = setup().await;
+    let fee_wallet_before = lamports(&mut setup.context, FEE_WALLET).await;
+
+    let ix = default_ix(&setup.market);
+    let signers = [&setup.owner, &setup.mint];
+    let events: Vec<MarketCreated> = process_with_events(&mut setup.context, &[ix], &signers).await;
+
+    assert_eq!(
+        lamports(&mut setup.context, FEE_WALLET).await - fee_wallet_before,
+        CREATION_FEE
+    );
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.owner, setup.owner.pubkey());
+    assert_eq!(market.mint, setup.mint.pubkey());
+    assert_eq!(market.escrow, setup.market.escrow);
+    assert_eq!(market.treasury, setup.market.treasury);
+    // The treasury starts out rent-exempt, before any trade
+    assert_eq!(
+        lamports(&mut setup.context, setup.market.treasury).await,
+        rent_exempt(0)
+    );
+    assert_eq!(market.current_price, INITIAL_PRICE);
+    assert_eq!(market.total_supply, SUPPLY);
+    assert_eq!(market.circulating_supply, 0);
+    assert_eq!(market.curve.curve_type, CurveType::Exponential);
+    assert_eq!(market.curve.base_price, INITIAL_PRICE);
+    assert_eq!(
+        market.curve.max_price,
+        INITIAL_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER
+    );
+    assert_eq!(market.trading_mode, TradingMode::Active);
+    assert!(!market.metadata_locked);
+    assert_eq!(
+        market.graduation,
+        GraduationThreshold::SoldSupply(SUPPLY * 8 / 10)
+    );
+    assert_eq!(market.status, MarketStatus::Active);
+    assert_eq!(market.creator_allocation, 0);
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        SUPPLY
+    );
+
+    let mint = setup
+        .context
+        .banks_client
+        .get_account(setup.mint.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    let mint = spl_token::state::Mint::unpack(&mint.data).unwrap();
+    assert_eq!(mint.supply, SUPPLY);
+    assert_eq!(mint.decimals, 0);
+    assert_eq!(
+        mint.mint_authority,
+        Some(setup.market.mint_authority).into()
+    );
+    assert_eq!(
+        mint.freeze_authority,
+        Some(setup.market.mint_authority).into()
+    );
+
+    let metadata = setup
+        .context
+        .banks_client
+        .get_account(setup.market.metadata)
+        .await
+        .unwrap()
+        .unwrap();
+    let metadata = Metadata::from_bytes(&metadata.data).unwrap();
+    assert_eq!(metadata.mint, setup.mint.pubkey());
+    assert_eq!(metadata.update_authority, setup.market.mint_authority);
+    assert_eq!(metadata.name, "Celebrity");
+    assert_eq!(metadata.symbol, "CELEB");
+    assert_eq!(metadata.uri, "https://example.com/celebrity.json");
+    assert!(metadata.is_mutable);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.market, setup.market.market);
+    assert_eq!(event.owner, setup.owner.pubkey());
+    assert_eq!(event.mint, setup.mint.pubkey());
+    assert_eq!(event.initial_price, INITIAL_PRICE);
+    assert_eq!(event.initial_supply, SUPPLY);
+    assert_eq!(event.curve_type, CurveType::Exponential);
+    assert_eq!(event.base_price, INITIAL_PRICE);
+    assert_eq!(event.k_factor, K_FACTOR);
+    assert_eq!(event.scale_factor, SCALE_FACTOR);
+    assert_eq!(
+        event.max_price,
+        INITIAL_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER
+    );
+    assert_eq!(event.sequence, 0);
+    assert_eq!(event.circulating_supply, 0);
+    assert_eq!(
+        event.treasury_balance,
+        lamports(&mut setup.context, setup.market.treasury).await
+    );
+}
+
+#[tokio::test]
+async fn creates_market_with_custom_price_cap() {
+    let mut setup = setup().await;
+
+    // A flat curve: the cap is the starting price
+    let ix = setup.market.create_market_ix(
+        INITIAL_PRICE,
+        SUPPLY,
+        "Celebrity",
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Exponential,
+        Some(INITIAL_PRICE),
+    );
+    create(&mut setup, ix).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.curve.max_price, INITIAL_PRICE);
+}
+
+#[tokio::test]
+async fn creates_market_with_chosen_curve_type() {
+    let mut setup = setup().await;
+
+    let ix = setup.market.create_market_ix(
+        INITIAL_PRICE,
+        SUPPLY,
+        "Celebrity",
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Sigmoid,
+        None,
+    );
+    let signers = [&setup.owner, &setup.mint];
+    let events: Vec<MarketCreated> = process_with_events(&mut setup.context, &[ix], &signers).await;
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.curve.curve_type, CurveType::Sigmoid);
+    assert_eq!(market.current_price, INITIAL_PRICE);
+    assert_eq!(events[0].curve_type, CurveType::Sigmoid);
+}
+
+fn growth_ix(market: &MarketFixture, curve_type: CurveType, growth: CurveGrowth) -> Instruction {
+    market.create_market_with(celebrity_exchange::instruction::CreateMarket {
+        initial_price_lamports: INITIAL_PRICE,
+        initial_supply: SUPPLY,
+        name: "Celebrity".to_string(),
+        symbol: "CELEB".to_string(),
+        uri: "https://example.com/celebrity.json".to_string(),
+        curve_type,
+        max_price_lamports: None,
+        graduation_threshold: None,
+        creator_allocation: None,
+        curve_growth: Some(growth),
+    })
+}
+
+#[tokio::test]
+async fn creates_market_with_chosen_growth() {
+    let mut setup = setup().await;
+
+    // A sigmoid ramping over half the supply instead of SCALE_FACTOR / K_FACTOR tokens
+    let growth = CurveGrowth::Rate {
+        k_factor: 1,
+        scale_factor: SUPPLY / 2,
+    };
+    let ix = growth_ix(&setup.market, CurveType::Sigmoid, growth);
+    create(&mut setup, ix).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.curve.k_factor, 1);
+    assert_eq!(market.curve.scale_factor, SUPPLY / 2);
+    assert_eq!(market.current_price, INITIAL_PRICE);
+}
+
+#[tokio::test]
+async fn creates_constant_product_market_with_virtual_reserves() {
+    let mut setup = setup().await;
+
+    let growth = CurveGrowth::VirtualReserves {
+        token_reserve: SUPPLY * 2,
+    };
+    let ix = growth_ix(&setup.market, CurveType::ConstantProduct, growth);
+    create(&mut setup, ix).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.curve.k_factor, 1);
+    assert_eq!(market.curve.scale_factor, SUPPLY * 2);
+    assert_eq!(market.current_price, INITIAL_PRICE);
+}
+
+#[tokio::test]
+async fn rejects_invalid_growth() {
+    let invalid = [
+        // Virtual reserves only describe a constant-product pool
+        (
+            CurveType::Exponential,
+            CurveGrowth::VirtualReserves {
+                token_reserve: SUPPLY,
+            },
+        ),
+        (
+            CurveType::Linear,
+            CurveGrowth::Rate {
+                k_factor: 1,
+                scale_factor: 0,
+            },
+        ),
+        // Ramps and virtual pools shorter than one token
+        (
+            CurveType::Sigmoid,
+            CurveGrowth::Rate {
+                k_factor: 2,
+                scale_factor: 1,
+            },
+        ),
+        (
+            CurveType::ConstantProduct,
+            CurveGrowth::VirtualReserves { token_reserve: 0 },
+        ),
+    ];
+
+    for (curve_type, growth) in invalid {
+        let mut setup = setup().await;
+        let ix = growth_ix(&setup.market, curve_type, growth);
+        let result = create(&mut setup, ix).await;
+        assert_custom_error(result, ExchangeError::InvalidCurveParams);
+    }
+}
+
+#[tokio::test]
+async fn rejects_price_cap_below_initial_price() {
+    let mut setup = setup().await;
+
+    let ix = setup.market.create_market_ix(
+        INITIAL_PRICE,
+        SUPPLY,
+        "Celebrity",
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Exponential,
+        Some(INITIAL_PRICE - 1),
+    );
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidCurveParams);
+}
+
+#[tokio::test]
+async fn rejects_graduation_threshold_beyond_supply() {
+    let mut setup = setup().await;
+
+    let ix = setup
+        .market
+        .create_market_with(celebrity_exchange::instruction::CreateMarket {
+            initial_price_lamports: INITIAL_PRICE,
+            initial_supply: SUPPLY,
+            name: "Celebrity".to_string(),
+            symbol: "CELEB".to_string(),
+            uri: "https://example.com/celebrity.json".to_string(),
+            curve_type: CurveType::Exponential,
+            max_price_lamports: None,
+            graduation_threshold: Some(GraduationThreshold::SoldSupply(SUPPLY)),
+            creator_allocation: None,
+            curve_growth: None,
+        });
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidGraduationThreshold);
+}
+
+#[tokio::test]
+async fn rejects_wrong_platform_wallet() {
+    let mut setup = setup().await;
+
+    let mut ix = default_ix(&setup.market);
+    let impostor = Pubkey::new_unique();
+    for meta in ix.accounts.iter_mut() {
+        if meta.pubkey == FEE_WALLET {
+            meta.pubkey = impostor;
+        }
+    }
+
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidPlatformWallet);
+}
+
+#[tokio::test]
+async fn rejects_oversized_metadata() {
+    let mut setup = setup().await;
+
+    let long_name = "x".repeat(33);
+    let ix = setup.market.create_market_ix(
+        INITIAL_PRICE,
+        SUPPLY,
+        &long_name,
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Exponential,
+        None,
+    );
+
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidMetadata);
+}
+
+#[tokio::test]
+async fn rejects_zero_initial_price() {
+    let mut setup = setup().await;
+
+    let ix = setup.market.create_market_ix(
+        0,
+        SUPPLY,
+        "Celebrity",
+        "CELEB",
+        "https://example.com/celebrity.json",
+        CurveType::Exponential,
+        None,
+    );
+
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidCurveParams);
+}
+
+#[tokio::test]
+async fn rejected_while_globally_paused() {
+    let mut setup = setup().await;
+
+    let pause = Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::UpdateConfig {
+            admin: setup.admin.pubkey(),
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::SetGlobalTradingMode {
+            mode: TradingMode::SellOnly,
+        }
+        .data(),
+    };
+    process(&mut setup.context, &[pause], &[&setup.admin])
+        .await
+        .unwrap();
+
+    let ix = default_ix(&setup.market);
+    let result = create(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::Paused);
+}
+
+#[tokio::test]
+async fn get_market_details_returns_market_state() {
+    let mut setup = setup().await;
+    let ix = default_ix(&setup.market);
+    create(&mut setup, ix).await.unwrap();
+
+    let ix = Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::GetMarket {
+            market: setup.market.market,
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::GetMarketDetails {}.data(),
+    };
+    let details: MarketDetails = simulate_return(&mut setup.context, &[ix], &[]).await;
+
+    assert_eq!(details.owner, setup.owner.pubkey());
+    assert_eq!(details.mint, setup.mint.pubkey());
+    assert_eq!(details.escrow, setup.market.escrow);
+    assert_eq!(details.treasury, setup.market.treasury);
+    assert_eq!(details.current_price, INITIAL_PRICE);
+    assert_eq!(details.total_supply, SUPPLY);
+    assert_eq!(details.circulating_supply, 0);
+    assert_eq!(details.trade_count, 0);
+    assert_eq!(details.name, "Celebrity");
+    assert_eq!(details.symbol, "CELEB");
+    assert_eq!(details.uri, "https://example.com/celebrity.json");
+}
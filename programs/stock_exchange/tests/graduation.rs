@@ -0,0 +1,285 @@
+mod common;
+
+use celebrity_exchange::{
+    ExchangeError, GraduationThreshold, Market, MarketGraduated, MarketStatus, Pool,
+    SolvencyReport, TradeExecuted, TradeKind, TradingMode,
+};
+use celebrity_exchange_math::{calculate_current_price, initial_liquidity};
+use celebrity_exchange_sdk::instructions;
+use common::*;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signer;
+
+const SUPPLY: u64 = 1_000;
+
+async fn setup(graduation: GraduationThreshold) -> MarketSetup {
+    MarketSetup::start_with(0, |program_test, market| {
+        market.add_with_graduation(program_test, SUPPLY, default_curve(), graduation)
+    })
+    .await
+}
+
+fn buy(setup: &MarketSetup, amount: u64) -> Instruction {
+    buy_ix(setup.user_accounts(), amount, u64::MAX)
+}
+
+fn sell(setup: &MarketSetup, amount: u64) -> Instruction {
+    sell_ix(setup.user_accounts(), amount, 0)
+}
+
+fn graduate(setup: &MarketSetup) -> Instruction {
+    instructions::graduate_market(setup.user.pubkey(), setup.market.mint)
+}
+
+#[tokio::test]
+async fn graduates_into_a_pool_once_enough_supply_has_sold() {
+    let mut setup = setup(GraduationThreshold::SoldSupply(800)).await;
+    let ix = buy(&setup, 799);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let ix = graduate(&setup);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::GraduationThresholdNotReached);
+
+    // The trade that reaches the threshold is the curve's last
+    let ix = buy(&setup, 1);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let ix = buy(&setup, 1);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::CurveComplete);
+    let ix = sell(&setup, 1);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::CurveComplete);
+
+    let treasury = lamports(&mut setup.context, setup.market.treasury).await;
+    let ix = graduate(&setup);
+    let user = setup.user.insecure_clone();
+    let events: Vec<MarketGraduated> =
+        process_with_events(&mut setup.context, &[ix], &[&user]).await;
+
+    // Everything above rent moves to the pool along with the unsold tokens
+    let sol_reserve = treasury - rent_exempt(0);
+    assert_eq!(
+        lamports(&mut setup.context, setup.market.treasury).await,
+        rent_exempt(0)
+    );
+    assert_eq!(
+        lamports(&mut setup.context, setup.addresses.pool.sol_vault).await,
+        rent_exempt(0) + sol_reserve
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        0
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.pool.token_vault).await,
+        200
+    );
+
+    let pool: Pool = fetch(&mut setup.context, setup.addresses.pool.pool).await;
+    assert_eq!(pool.market, Some(setup.market.market));
+    assert_eq!(pool.mint, setup.market.mint);
+    assert_eq!(pool.creator, setup.market.owner);
+    assert_eq!(pool.token_vault, setup.addresses.pool.token_vault);
+    assert_eq!(pool.sol_vault, setup.addresses.pool.sol_vault);
+    assert_eq!(pool.lp_mint, setup.addresses.pool.lp_mint);
+    assert_eq!(pool.locked_liquidity, initial_liquidity(sol_reserve, 200));
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.status, MarketStatus::Graduated);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.market, setup.market.market);
+    assert_eq!(event.pool, setup.addresses.pool.pool);
+    assert_eq!(event.circulating_supply, 800);
+    assert_eq!(
+        event.final_price,
+        calculate_current_price(800, &default_curve().into()).unwrap()
+    );
+    assert_eq!((event.sol_reserve, event.token_reserve), (sol_reserve, 200));
+    assert_eq!(event.locked_liquidity, pool.locked_liquidity);
+    assert_eq!(event.sequence, market.trade_count);
+    assert_eq!(event.sequence, 3);
+    assert_eq!(event.treasury_balance, rent_exempt(0));
+
+    // Holders are owed nothing by the treasury once their tokens trade in the pool
+    let ix = instructions::verify_market(setup.market.market);
+    let report: SolvencyReport = simulate_return(&mut setup.context, &[ix], &[]).await;
+    assert_eq!((report.curve_reserve, report.shortfall), (0, 0));
+
+    // A market graduates once
+    let ix = graduate(&setup);
+    assert!(setup.send_as_user(&[ix]).await.is_err());
+}
+
+#[tokio::test]
+async fn graduates_once_the_market_cap_is_reached() {
+    let curve = default_curve().into();
+    let cap = calculate_current_price(500, &curve).unwrap() * 500;
+    let mut setup = setup(GraduationThreshold::MarketCap(cap)).await;
+
+    let ix = buy(&setup, 499);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert!(market.market_cap() < cap as u128);
+
+    let ix = buy(&setup, 1);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let ix = graduate(&setup);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.pool.token_vault).await,
+        500
+    );
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.status, MarketStatus::Graduated);
+}
+
+#[tokio::test]
+async fn buys_past_the_threshold_are_rejected() {
+    let mut setup = setup(GraduationThreshold::SoldSupply(800)).await;
+
+    // Rather than part-filled
+    let ix = buy(&setup, 801);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+
+    let ix = buy(&setup, 800);
+    let user = setup.user.insecure_clone();
+    let events: Vec<TradeExecuted> = process_with_events(&mut setup.context, &[ix], &[&user]).await;
+    assert_eq!(events[0].tokens, 800);
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        800
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        200
+    );
+
+    let ix = graduate(&setup);
+    setup.send_as_user(&[ix]).await.unwrap();
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.pool.token_vault).await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn market_cap_curves_keep_tokens_back_for_the_pool() {
+    let mut setup = setup(GraduationThreshold::MarketCap(u64::MAX)).await;
+
+    // Spending without limit buys the default graduation share, far short of the cap
+    let ix = instructions::buy_tokens_exact_in(setup.user_accounts(), u64::MAX / 2, 1);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.circulating_supply, 800);
+    assert!(market.market_cap() < u64::MAX as u128);
+
+    let ix = graduate(&setup);
+    setup.send_as_user(&[ix]).await.unwrap();
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.pool.token_vault).await,
+        200
+    );
+}
+
+#[tokio::test]
+async fn graduated_tokens_keep_trading_in_their_pool() {
+    let mut setup = setup(GraduationThreshold::SoldSupply(800)).await;
+    let ix = buy(&setup, 800);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let ix = graduate(&setup);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let pool: Pool = fetch(&mut setup.context, setup.addresses.pool.pool).await;
+    let mut accounts = instructions::swap_accounts(
+        setup.user.pubkey(),
+        setup.addresses.pool.pool,
+        &pool,
+        FEE_WALLET,
+    );
+    accounts.user_token_account = setup.user_token_account;
+
+    // The market owner keeps earning the creator share
+    let owner_before = lamports(&mut setup.context, setup.market.owner).await;
+    let ix = instructions::swap(accounts, TradeKind::Sell, 100, 0);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        700
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.pool.token_vault).await,
+        300
+    );
+    assert!(lamports(&mut setup.context, setup.market.owner).await > owner_before);
+}
+
+#[tokio::test]
+async fn graduated_pools_follow_their_market_trading_mode() {
+    let mut setup = setup(GraduationThreshold::SoldSupply(800)).await;
+    let admin = setup.admin.insecure_clone();
+    let market = setup.market.market;
+    let ix = buy(&setup, 800);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let ix = instructions::set_market_trading_mode(admin.pubkey(), market, TradingMode::Paused);
+    setup.send(&[ix], &admin).await.unwrap();
+    let ix = graduate(&setup);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::Paused);
+
+    let ix = instructions::set_market_trading_mode(admin.pubkey(), market, TradingMode::Active);
+    setup.send(&[ix], &admin).await.unwrap();
+    let ix = graduate(&setup);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let pool: Pool = fetch(&mut setup.context, setup.addresses.pool.pool).await;
+    let user = setup.user.pubkey();
+    let pool_address = setup.addresses.pool.pool;
+    let user_token_account = setup.user_token_account;
+    let swap_accounts = || {
+        let mut accounts = instructions::swap_accounts(user, pool_address, &pool, FEE_WALLET);
+        accounts.user_token_account = user_token_account;
+        accounts
+    };
+    let liquidity_accounts = || {
+        let mut accounts = instructions::liquidity_accounts(user, pool_address, &pool);
+        accounts.user_token_account = user_token_account;
+        accounts
+    };
+    let ix = instructions::create_user_token_account(user, user, pool.lp_mint);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    // Sell-only on the market stops buys and deposits in its pool, but holders can exit
+    let ix = instructions::set_market_trading_mode(admin.pubkey(), market, TradingMode::SellOnly);
+    setup.send(&[ix], &admin).await.unwrap();
+    let ix = instructions::swap(swap_accounts(), TradeKind::Buy, 1_000_000, 0);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::Paused);
+    let ix = instructions::add_liquidity(liquidity_accounts(), 1, u64::MAX, u64::MAX);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::Paused);
+    let ix = instructions::swap(swap_accounts(), TradeKind::Sell, 100, 0);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let ix = instructions::set_market_trading_mode(admin.pubkey(), market, TradingMode::Paused);
+    setup.send(&[ix], &admin).await.unwrap();
+    let ix = instructions::swap(swap_accounts(), TradeKind::Sell, 100, 0);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::Paused);
+    let ix = instructions::remove_liquidity(liquidity_accounts(), 1, 0, 0);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::Paused);
+
+    // Leaving the market out does not dodge its mode
+    let mut accounts = swap_accounts();
+    accounts.market = None;
+    let ix = instructions::swap(accounts, TradeKind::Sell, 100, 0);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::InvalidMarket);
+}
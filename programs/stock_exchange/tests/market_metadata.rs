@@ -0,0 +1,295 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use celebrity_exchange::{
+    CurveParams, CurveType, ExchangeError, GraduationThreshold, Market, MarketMetadataUpdated,
+    MarketStatus, MintAuthorityMigrated, BASE_PRICE, DEFAULT_MAX_PRICE_MULTIPLIER, K_FACTOR,
+    SCALE_FACTOR,
+};
+use celebrity_exchange_math::calculate_sell_value;
+use common::*;
+use mpl_token_metadata::accounts::Metadata;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+const SUPPLY: u64 = 1_000_000;
+const LEGACY_CIRCULATING: u64 = 250;
+
+fn legacy_mint_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"mint-authority"], &celebrity_exchange::ID).0
+}
+
+// `legacy` seeds the market as it was created before mint authorities were per market
+async fn setup(legacy: bool) -> MarketSetup {
+    MarketSetup::start_with(0, |program_test, market| {
+        if legacy {
+            market.add_legacy_to(
+                program_test,
+                SUPPLY,
+                LEGACY_CIRCULATING,
+                legacy_mint_authority(),
+            );
+            market.add_metadata_to(program_test, legacy_mint_authority());
+        } else {
+            market.add_to(program_test, SUPPLY);
+            market.add_metadata_to(program_test, market.mint_authority);
+        }
+    })
+    .await
+}
+
+fn update_ix(setup: &MarketSetup, owner: Pubkey, name: &str, lock: bool) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::UpdateMarketMetadata {
+            owner,
+            market: setup.market.market,
+            mint: setup.market.mint,
+            mint_authority: setup.market.mint_authority,
+            metadata: setup.market.metadata,
+            metadata_program: mpl_token_metadata::ID,
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::UpdateMarketMetadata {
+            name: name.to_string(),
+            symbol: "NEW".to_string(),
+            uri: "https://example.com/new.json".to_string(),
+            lock,
+        }
+        .data(),
+    }
+}
+
+fn migrate_ix(setup: &MarketSetup, admin: Pubkey) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::MigrateMintAuthority {
+            admin,
+            config: config_address(),
+            market: setup.market.market,
+            treasury: setup.market.treasury,
+            mint: setup.market.mint,
+            legacy_mint_authority: legacy_mint_authority(),
+            mint_authority: setup.market.mint_authority,
+            metadata: setup.market.metadata,
+            token_program: spl_token::ID,
+            metadata_program: mpl_token_metadata::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::MigrateMintAuthority {}.data(),
+    }
+}
+
+async fn metadata(setup: &mut MarketSetup) -> Metadata {
+    let account = setup
+        .context
+        .banks_client
+        .get_account(setup.market.metadata)
+        .await
+        .unwrap()
+        .unwrap();
+    Metadata::from_bytes(&account.data).unwrap()
+}
+
+async fn send(
+    setup: &mut MarketSetup,
+    ix: Instruction,
+    signer: &Keypair,
+) -> Result<(), BanksClientError> {
+    process(&mut setup.context, &[ix], &[signer]).await
+}
+
+#[tokio::test]
+async fn owner_updates_market_and_token_metadata() {
+    let mut setup = setup(false).await;
+
+    let ix = update_ix(&setup, setup.owner.pubkey(), "Renamed", false);
+    let owner = setup.owner.insecure_clone();
+    let events: Vec<MarketMetadataUpdated> =
+        process_with_events(&mut setup.context, &[ix], &[&owner]).await;
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.name, "Renamed");
+    assert_eq!(market.symbol, "NEW");
+    assert_eq!(market.uri, "https://example.com/new.json");
+    assert!(!market.metadata_locked);
+
+    let metadata = metadata(&mut setup).await;
+    assert_eq!(metadata.name, "Renamed");
+    assert_eq!(metadata.symbol, "NEW");
+    assert_eq!(metadata.uri, "https://example.com/new.json");
+    assert!(metadata.is_mutable);
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].market, setup.market.market);
+    assert_eq!(events[0].sequence, 1);
+    assert_eq!(market.trade_count, 1);
+    assert_eq!(events[0].name, "Renamed");
+    assert!(!events[0].locked);
+}
+
+#[tokio::test]
+async fn rejects_update_by_non_owner() {
+    let mut setup = setup(false).await;
+    let stranger = Keypair::new();
+
+    let ix = update_ix(&setup, stranger.pubkey(), "Hijacked", false);
+    let result = send(&mut setup, ix, &stranger).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
+
+#[tokio::test]
+async fn lock_freezes_metadata_for_good() {
+    let mut setup = setup(false).await;
+    let owner = setup.owner.insecure_clone();
+
+    let ix = update_ix(&setup, owner.pubkey(), "Final", true);
+    send(&mut setup, ix, &owner).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert!(market.metadata_locked);
+    let metadata = metadata(&mut setup).await;
+    assert_eq!(metadata.name, "Final");
+    assert!(!metadata.is_mutable);
+
+    let ix = update_ix(&setup, owner.pubkey(), "Again", false);
+    let result = send(&mut setup, ix, &owner).await;
+    assert_custom_error(result, ExchangeError::MetadataLocked);
+}
+
+#[tokio::test]
+async fn migrates_legacy_mint_authority() {
+    let mut setup = setup(true).await;
+
+    let ix = migrate_ix(&setup, setup.admin.pubkey());
+    let admin = setup.admin.insecure_clone();
+    let events: Vec<MintAuthorityMigrated> =
+        process_with_events(&mut setup.context, &[ix], &[&admin]).await;
+
+    // The account is rewritten in the current layout, rent-exempt at its new size
+    let account = setup
+        .context
+        .banks_client
+        .get_account(setup.market.market)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 8 + Market::MAX_SIZE);
+    assert_eq!(account.lamports, rent_exempt(8 + Market::MAX_SIZE));
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.owner, setup.owner.pubkey());
+    assert_eq!(market.mint, setup.market.mint);
+    assert_eq!(market.escrow, setup.market.escrow);
+    assert_eq!(market.treasury, setup.market.treasury);
+    assert_eq!(market.current_price, BASE_PRICE);
+    assert_eq!(market.total_supply, SUPPLY);
+    assert_eq!(market.circulating_supply, LEGACY_CIRCULATING);
+    // Numbered after the legacy market's seven trades
+    assert_eq!(market.trade_count, 8);
+    assert_eq!(market.name, "Test Celebrity");
+    assert_eq!(market.symbol, "TEST");
+    assert_eq!(market.uri, "https://example.com/test.json");
+    assert_eq!(
+        market.curve,
+        CurveParams {
+            curve_type: CurveType::Exponential,
+            base_price: BASE_PRICE,
+            k_factor: K_FACTOR,
+            scale_factor: SCALE_FACTOR,
+            max_price: BASE_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER,
+        }
+    );
+    assert_eq!(market.graduation, GraduationThreshold::SoldSupply(800_000));
+    assert_eq!(market.status, MarketStatus::Active);
+    assert_eq!(market.creator_allocation, 0);
+
+    let mint = setup
+        .context
+        .banks_client
+        .get_account(setup.market.mint)
+        .await
+        .unwrap()
+        .unwrap();
+    let mint = spl_token::state::Mint::unpack(&mint.data).unwrap();
+    assert_eq!(
+        mint.mint_authority,
+        Some(setup.market.mint_authority).into()
+    );
+    assert_eq!(
+        mint.freeze_authority,
+        Some(setup.market.mint_authority).into()
+    );
+    assert_eq!(
+        metadata(&mut setup).await.update_authority,
+        setup.market.mint_authority
+    );
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].market, setup.market.market);
+    assert_eq!(events[0].mint_authority, setup.market.mint_authority);
+    assert_eq!(events[0].sequence, 8);
+    assert_eq!(
+        events[0].treasury_balance,
+        lamports(&mut setup.context, setup.market.treasury).await
+    );
+
+    // The creator can now update through the per-market authority
+    let owner = setup.owner.insecure_clone();
+    let ix = update_ix(&setup, owner.pubkey(), "Migrated", false);
+    send(&mut setup, ix, &owner).await.unwrap();
+}
+
+#[tokio::test]
+async fn migrated_market_trades_against_a_solvent_treasury() {
+    let mut setup = setup(true).await;
+    let admin = setup.admin.insecure_clone();
+
+    let ix = migrate_ix(&setup, admin.pubkey());
+    send(&mut setup, ix, &admin).await.unwrap();
+
+    // The admin topped the treasury up to back the legacy supply on the new curve
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(
+        lamports(&mut setup.context, setup.market.treasury).await,
+        rent_exempt(0) + calculate_sell_value(LEGACY_CIRCULATING, 0, &market.curve.into()).unwrap()
+    );
+
+    let ix = buy_ix(setup.user_accounts(), 100, u64::MAX);
+    setup.send_as_user(&[ix]).await.unwrap();
+    let ix = sell_ix(setup.user_accounts(), 100, 0);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.circulating_supply, LEGACY_CIRCULATING);
+}
+
+#[tokio::test]
+async fn rejects_migrating_twice() {
+    let mut setup = setup(true).await;
+    let admin = setup.admin.insecure_clone();
+
+    let ix = migrate_ix(&setup, admin.pubkey());
+    send(&mut setup, ix, &admin).await.unwrap();
+
+    // Fresh blockhash so the retry is not deduplicated
+    setup.context.get_new_latest_blockhash().await.unwrap();
+    let ix = migrate_ix(&setup, admin.pubkey());
+    let result = send(&mut setup, ix, &admin).await;
+    assert_custom_error(result, ExchangeError::AlreadyMigrated);
+}
+
+#[tokio::test]
+async fn rejects_migration_by_non_admin() {
+    let mut setup = setup(true).await;
+    let stranger = Keypair::new();
+
+    let ix = migrate_ix(&setup, stranger.pubkey());
+    let result = send(&mut setup, ix, &stranger).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use celebrity_exchange::{ExchangeError, GlobalConfig, Market, MarketPaused, TradingMode};
+use common::*;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::{Keypair, Signer};
+
+struct Setup {
+    context: ProgramTestContext,
+    admin: Keypair,
+    user: Keypair,
+    market: MarketFixture,
+    other_market: MarketFixture,
+    user_token_account: Pubkey,
+    user_other_token_account: Pubkey,
+}
+
+async fn setup() -> Setup {
+    let admin = Keypair::new();
+    let user = Keypair::new();
+    let mut program_test = program_test();
+    add_config(&mut program_test, admin.pubkey());
+    add_system_account(&mut program_test, user.pubkey(), USER_LAMPORTS);
+
+    let market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    market.add_to(&mut program_test, 1_000_000);
+    let other_market = MarketFixture::new(Pubkey::new_unique(), Pubkey::new_unique());
+    other_market.add_to(&mut program_test, 1_000_000);
+
+    let user_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_token_account,
+        market.mint,
+        user.pubkey(),
+        0,
+    );
+    let user_other_token_account = Pubkey::new_unique();
+    add_token_account(
+        &mut program_test,
+        user_other_token_account,
+        other_market.mint,
+        user.pubkey(),
+        0,
+    );
+
+    let mut setup = Setup {
+        context: program_test.start_with_context().await,
+        admin,
+        user,
+        market,
+        other_market,
+        user_token_account,
+        user_other_token_account,
+    };
+
+    // Give the user something to sell
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    process(
+        &mut setup.context,
+        &[buy_ix(accounts, 1_000, u64::MAX)],
+        &[&setup.user],
+    )
+    .await
+    .unwrap();
+
+    setup
+}
+
+fn set_global_mode_ix(admin: Pubkey, mode: TradingMode) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::UpdateConfig {
+            admin,
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::SetGlobalTradingMode { mode }.data(),
+    }
+}
+
+fn set_market_mode_ix(admin: Pubkey, market: Pubkey, mode: TradingMode) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: celebrity_exchange::accounts::SetMarketTradingMode {
+            admin,
+            config: config_address(),
+            market,
+        }
+        .to_account_metas(None),
+        data: celebrity_exchange::instruction::SetMarketTradingMode { mode }.data(),
+    }
+}
+
+async fn try_buy(
+    setup: &mut Setup,
+    amount: u64,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    process(
+        &mut setup.context,
+        &[buy_ix(accounts, amount, u64::MAX)],
+        &[&setup.user],
+    )
+    .await
+}
+
+async fn try_sell(
+    setup: &mut Setup,
+    amount: u64,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    process(
+        &mut setup.context,
+        &[sell_ix(accounts, amount, 0)],
+        &[&setup.user],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn global_pause_stops_buys_and_sells_until_resumed() {
+    let mut setup = setup().await;
+
+    let ix = set_global_mode_ix(setup.admin.pubkey(), TradingMode::Paused);
+    process(&mut setup.context, &[ix], &[&setup.admin])
+        .await
+        .unwrap();
+
+    assert_custom_error(try_buy(&mut setup, 10).await, ExchangeError::Paused);
+    assert_custom_error(try_sell(&mut setup, 10).await, ExchangeError::Paused);
+
+    let ix = set_global_mode_ix(setup.admin.pubkey(), TradingMode::Active);
+    process(&mut setup.context, &[ix], &[&setup.admin])
+        .await
+        .unwrap();
+
+    let config: GlobalConfig = fetch(&mut setup.context, config_address()).await;
+    assert_eq!(config.trading_mode, TradingMode::Active);
+    try_buy(&mut setup, 10).await.unwrap();
+    try_sell(&mut setup, 10).await.unwrap();
+}
+
+#[tokio::test]
+async fn global_sell_only_lets_holders_exit() {
+    let mut setup = setup().await;
+
+    let ix = set_global_mode_ix(setup.admin.pubkey(), TradingMode::SellOnly);
+    process(&mut setup.context, &[ix], &[&setup.admin])
+        .await
+        .unwrap();
+
+    assert_custom_error(try_buy(&mut setup, 10).await, ExchangeError::Paused);
+    try_sell(&mut setup, 1_000).await.unwrap();
+}
+
+#[tokio::test]
+async fn market_pause_only_affects_that_market() {
+    let mut setup = setup().await;
+
+    let ix = set_market_mode_ix(
+        setup.admin.pubkey(),
+        setup.market.market,
+        TradingMode::Paused,
+    );
+    let admin = setup.admin.insecure_clone();
+    let events: Vec<MarketPaused> = process_with_events(&mut setup.context, &[ix], &[&admin]).await;
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.trading_mode, TradingMode::Paused);
+    // Numbered after the setup's buy
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].market, Some(setup.market.market));
+    assert_eq!(events[0].sequence, Some(2));
+    assert_eq!(market.trade_count, 2);
+    assert_custom_error(try_buy(&mut setup, 10).await, ExchangeError::Paused);
+    assert_custom_error(try_sell(&mut setup, 10).await, ExchangeError::Paused);
+
+    let accounts = setup
+        .other_market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_other_token_account);
+    process(
+        &mut setup.context,
+        &[buy_ix(accounts, 10, u64::MAX)],
+        &[&setup.user],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn stricter_mode_wins() {
+    let mut setup = setup().await;
+
+    // Market sell-only, global active: buys blocked
+    let ix = set_market_mode_ix(
+        setup.admin.pubkey(),
+        setup.market.market,
+        TradingMode::SellOnly,
+    );
+    process(&mut setup.context, &[ix], &[&setup.admin])
+        .await
+        .unwrap();
+    assert_custom_error(try_buy(&mut setup, 10).await, ExchangeError::Paused);
+    try_sell(&mut setup, 10).await.unwrap();
+
+    // Global paused overrides the market's sell-only mode
+    let ix = set_global_mode_ix(setup.admin.pubkey(), TradingMode::Paused);
+    process(&mut setup.context, &[ix], &[&setup.admin])
+        .await
+        .unwrap();
+    assert_custom_error(try_sell(&mut setup, 10).await, ExchangeError::Paused);
+}
+
+#[tokio::test]
+async fn only_admin_can_pause() {
+    let mut setup = setup().await;
+    let intruder = Keypair::new();
+
+    let ix = set_global_mode_ix(intruder.pubkey(), TradingMode::Paused);
+    let result = process(&mut setup.context, &[ix], &[&intruder]).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+
+    let ix = set_market_mode_ix(intruder.pubkey(), setup.market.market, TradingMode::Paused);
+    let result = process(&mut setup.context, &[ix], &[&intruder]).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+}
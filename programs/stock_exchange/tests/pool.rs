@@ -0,0 +1,303 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address;
+use celebrity_exchange::{
+    ExchangeError, GlobalConfig, LiquidityAdded, LiquidityRemoved, Pool, PoolInitialized,
+    SwapExecuted, TradeKind, TradingMode, MINIMUM_LIQUIDITY,
+};
+use celebrity_exchange_math::{deposit_for_liquidity, initial_liquidity, withdrawal_for_liquidity};
+use celebrity_exchange_sdk::{instructions, state};
+use common::*;
+use solana_sdk::signature::{Keypair, Signer};
+
+const TOKENS: u64 = 1_000_000_000;
+const POOL_SOL: u64 = 10_000_000_000;
+const POOL_TOKENS: u64 = 1_000_000;
+
+// The owner and user each hold `TOKENS` of a mint no market trades
+async fn setup() -> MarketSetup {
+    MarketSetup::start_with(TOKENS, |program_test, market| {
+        add_mint(program_test, market.mint, 2 * TOKENS, Pubkey::new_unique());
+        add_token_account(
+            program_test,
+            get_associated_token_address(&market.owner, &market.mint),
+            market.mint,
+            market.owner,
+            TOKENS,
+        );
+    })
+    .await
+}
+
+async fn open_pool(setup: &mut MarketSetup) -> Pool {
+    let creator = setup.owner.insecure_clone();
+    let ix =
+        instructions::initialize_pool(creator.pubkey(), setup.market.mint, POOL_SOL, POOL_TOKENS);
+    setup.send(&[ix], &creator).await.unwrap();
+    fetch(&mut setup.context, setup.addresses.pool.pool).await
+}
+
+async fn reserves(setup: &mut MarketSetup) -> (u64, u64) {
+    let sol = lamports(&mut setup.context, setup.addresses.pool.sol_vault).await;
+    let tokens = token_balance(&mut setup.context, setup.addresses.pool.token_vault).await;
+    (sol - rent_exempt(0), tokens)
+}
+
+fn lp_account(setup: &MarketSetup, user: &Keypair) -> Pubkey {
+    get_associated_token_address(&user.pubkey(), &setup.addresses.pool.lp_mint)
+}
+
+#[tokio::test]
+async fn opens_a_pool_and_locks_the_minimum_liquidity() {
+    let mut setup = setup().await;
+    let creator = setup.owner.insecure_clone();
+    let creator_tokens = get_associated_token_address(&creator.pubkey(), &setup.market.mint);
+
+    let ix =
+        instructions::initialize_pool(creator.pubkey(), setup.market.mint, POOL_SOL, POOL_TOKENS);
+    let events: Vec<PoolInitialized> =
+        process_with_events(&mut setup.context, &[ix], &[&creator]).await;
+
+    let pool: Pool = fetch(&mut setup.context, setup.addresses.pool.pool).await;
+    assert_eq!(pool.market, None);
+    assert_eq!(pool.mint, setup.market.mint);
+    assert_eq!(pool.creator, creator.pubkey());
+    assert_eq!(pool.token_vault, setup.addresses.pool.token_vault);
+    assert_eq!(pool.sol_vault, setup.addresses.pool.sol_vault);
+    assert_eq!(pool.lp_mint, setup.addresses.pool.lp_mint);
+    assert_eq!(pool.locked_liquidity, MINIMUM_LIQUIDITY);
+
+    assert_eq!(reserves(&mut setup).await, (POOL_SOL, POOL_TOKENS));
+    assert_eq!(
+        token_balance(&mut setup.context, creator_tokens).await,
+        TOKENS - POOL_TOKENS
+    );
+    let liquidity = initial_liquidity(POOL_SOL, POOL_TOKENS);
+    let creator_lp = lp_account(&setup, &creator);
+    assert_eq!(
+        token_balance(&mut setup.context, creator_lp).await,
+        liquidity - MINIMUM_LIQUIDITY
+    );
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.pool, setup.addresses.pool.pool);
+    assert_eq!(event.creator, creator.pubkey());
+    assert_eq!(
+        (event.sol_reserve, event.token_reserve),
+        (POOL_SOL, POOL_TOKENS)
+    );
+    assert_eq!(event.liquidity, liquidity);
+    assert_eq!(event.locked_liquidity, MINIMUM_LIQUIDITY);
+}
+
+#[tokio::test]
+async fn rejects_pools_too_small_to_lock_the_minimum() {
+    let mut setup = setup().await;
+    let creator = setup.owner.insecure_clone();
+
+    // √(1,000 × 1,000) leaves nothing above the locked minimum
+    let ix = instructions::initialize_pool(creator.pubkey(), setup.market.mint, 1_000, 1_000);
+    let result = setup.send(&[ix], &creator).await;
+    assert_custom_error(result, ExchangeError::InsufficientLiquidity);
+}
+
+#[tokio::test]
+async fn rejects_pools_for_market_tokens() {
+    let mut setup = MarketSetup::start_with(POOL_TOKENS, |program_test, market| {
+        market.add_to(program_test, TOKENS)
+    })
+    .await;
+
+    let user = setup.user.pubkey();
+    let ix = instructions::initialize_pool(user, setup.market.mint, POOL_SOL, POOL_TOKENS);
+    let result = setup.send_as_user(&[ix]).await;
+    assert_custom_error(result, ExchangeError::PoolReservedForMarket);
+}
+
+#[tokio::test]
+async fn swaps_follow_the_constant_product_and_split_fees() {
+    let mut setup = setup().await;
+    let pool = open_pool(&mut setup).await;
+    let trader = setup.user.insecure_clone();
+    let trader_tokens = get_associated_token_address(&trader.pubkey(), &setup.market.mint);
+    let config: GlobalConfig = fetch(&mut setup.context, config_address()).await;
+    let pool_address = setup.addresses.pool.pool;
+    let accounts =
+        || instructions::swap_accounts(trader.pubkey(), pool_address, &pool, config.fee_wallet);
+
+    // Buy with 1 SOL
+    let (sol, tokens) = reserves(&mut setup).await;
+    let quote = state::quote_swap(&config, TradeKind::Buy, 1_000_000_000, sol, tokens).unwrap();
+    let platform_before = lamports(&mut setup.context, FEE_WALLET).await;
+    let creator_before = lamports(&mut setup.context, pool.creator).await;
+
+    let ix = instructions::swap(
+        accounts(),
+        TradeKind::Buy,
+        1_000_000_000,
+        quote.amount_out + 1,
+    );
+    let result = setup.send(&[ix], &trader).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+
+    let ix = instructions::swap(accounts(), TradeKind::Buy, 1_000_000_000, quote.amount_out);
+    let events: Vec<SwapExecuted> =
+        process_with_events(&mut setup.context, &[ix], &[&trader]).await;
+
+    assert_eq!(
+        token_balance(&mut setup.context, trader_tokens).await,
+        TOKENS + quote.amount_out
+    );
+    assert_eq!(
+        lamports(&mut setup.context, FEE_WALLET).await - platform_before,
+        quote.platform_fee
+    );
+    assert_eq!(
+        lamports(&mut setup.context, pool.creator).await - creator_before,
+        quote.creator_fee
+    );
+    let after = reserves(&mut setup).await;
+    assert_eq!(
+        after,
+        (sol + quote.sol_reserve_change, tokens - quote.amount_out)
+    );
+    assert!(after.0 as u128 * after.1 as u128 >= sol as u128 * tokens as u128);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.kind, TradeKind::Buy);
+    assert_eq!(event.user, trader.pubkey());
+    assert_eq!(
+        (event.amount_in, event.amount_out),
+        (1_000_000_000, quote.amount_out)
+    );
+    assert_eq!((event.sol_reserve, event.token_reserve), after);
+
+    // Sell the same tokens back: the fees of both legs are lost
+    let (sol, tokens) = after;
+    let quote_sell =
+        state::quote_swap(&config, TradeKind::Sell, quote.amount_out, sol, tokens).unwrap();
+    let trader_before = lamports(&mut setup.context, trader.pubkey()).await;
+    let ix = instructions::swap(accounts(), TradeKind::Sell, quote.amount_out, 0);
+    let events: Vec<SwapExecuted> =
+        process_with_events(&mut setup.context, &[ix], &[&trader]).await;
+
+    assert_eq!(
+        lamports(&mut setup.context, trader.pubkey()).await - trader_before,
+        quote_sell.amount_out
+    );
+    assert!(quote_sell.amount_out < 1_000_000_000);
+    assert_eq!(
+        reserves(&mut setup).await,
+        (
+            sol - quote_sell.sol_reserve_change,
+            tokens + quote.amount_out
+        )
+    );
+    assert_eq!(events[0].platform_fee, quote_sell.platform_fee);
+    assert_eq!(events[0].creator_fee, quote_sell.creator_fee);
+}
+
+#[tokio::test]
+async fn swaps_follow_the_global_trading_mode() {
+    let mut setup = setup().await;
+    let pool = open_pool(&mut setup).await;
+    let trader = setup.user.insecure_clone();
+    let admin = setup.admin.insecure_clone();
+    let pool_address = setup.addresses.pool.pool;
+    let accounts = || instructions::swap_accounts(trader.pubkey(), pool_address, &pool, FEE_WALLET);
+
+    let ix = instructions::set_global_trading_mode(admin.pubkey(), TradingMode::SellOnly);
+    setup.send(&[ix], &admin).await.unwrap();
+
+    let ix = instructions::swap(accounts(), TradeKind::Buy, 1_000_000_000, 0);
+    let result = setup.send(&[ix], &trader).await;
+    assert_custom_error(result, ExchangeError::Paused);
+    // Holders can still sell
+    let ix = instructions::swap(accounts(), TradeKind::Sell, 1_000, 0);
+    setup.send(&[ix], &trader).await.unwrap();
+
+    let ix = instructions::set_global_trading_mode(admin.pubkey(), TradingMode::Paused);
+    setup.send(&[ix], &admin).await.unwrap();
+    let ix = instructions::swap(accounts(), TradeKind::Sell, 1_000, 0);
+    let result = setup.send(&[ix], &trader).await;
+    assert_custom_error(result, ExchangeError::Paused);
+}
+
+#[tokio::test]
+async fn liquidity_moves_in_proportion_to_the_reserves() {
+    let mut setup = setup().await;
+    let pool = open_pool(&mut setup).await;
+    let trader = setup.user.insecure_clone();
+    let trader_tokens = get_associated_token_address(&trader.pubkey(), &setup.market.mint);
+    let trader_lp = lp_account(&setup, &trader);
+    let pool_address = setup.addresses.pool.pool;
+    let accounts = || instructions::liquidity_accounts(trader.pubkey(), pool_address, &pool);
+
+    let (sol, tokens) = reserves(&mut setup).await;
+    let lp_supply = initial_liquidity(POOL_SOL, POOL_TOKENS);
+    let liquidity = 1_000_000;
+    let sol_in = deposit_for_liquidity(liquidity, sol, lp_supply).unwrap();
+    let tokens_in = deposit_for_liquidity(liquidity, tokens, lp_supply).unwrap();
+
+    let create_lp_account =
+        instructions::create_user_token_account(trader.pubkey(), trader.pubkey(), pool.lp_mint);
+    let ix = instructions::add_liquidity(accounts(), liquidity, sol_in - 1, tokens_in);
+    let result = setup.send(&[create_lp_account.clone(), ix], &trader).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+
+    let ix = instructions::add_liquidity(accounts(), liquidity, sol_in, tokens_in);
+    let events: Vec<LiquidityAdded> =
+        process_with_events(&mut setup.context, &[create_lp_account, ix], &[&trader]).await;
+
+    assert_eq!(
+        token_balance(&mut setup.context, trader_lp).await,
+        liquidity
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, trader_tokens).await,
+        TOKENS - tokens_in
+    );
+    assert_eq!(
+        reserves(&mut setup).await,
+        (sol + sol_in, tokens + tokens_in)
+    );
+    assert_eq!(events[0].lp_supply, lp_supply + liquidity);
+    assert_eq!(
+        (events[0].sol_amount, events[0].token_amount),
+        (sol_in, tokens_in)
+    );
+
+    // Withdrawing right away returns no more than went in
+    let (sol, tokens) = reserves(&mut setup).await;
+    let lp_supply = lp_supply + liquidity;
+    let sol_out = withdrawal_for_liquidity(liquidity, sol, lp_supply).unwrap();
+    let tokens_out = withdrawal_for_liquidity(liquidity, tokens, lp_supply).unwrap();
+    assert!(sol_out <= sol_in && tokens_out <= tokens_in);
+
+    let ix = instructions::remove_liquidity(accounts(), liquidity, sol_out + 1, tokens_out);
+    let result = setup.send(&[ix], &trader).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+
+    let trader_before = lamports(&mut setup.context, trader.pubkey()).await;
+    let ix = instructions::remove_liquidity(accounts(), liquidity, sol_out, tokens_out);
+    let events: Vec<LiquidityRemoved> =
+        process_with_events(&mut setup.context, &[ix], &[&trader]).await;
+
+    assert_eq!(token_balance(&mut setup.context, trader_lp).await, 0);
+    assert_eq!(
+        lamports(&mut setup.context, trader.pubkey()).await - trader_before,
+        sol_out
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, trader_tokens).await,
+        TOKENS - tokens_in + tokens_out
+    );
+    assert_eq!(
+        reserves(&mut setup).await,
+        (sol - sol_out, tokens - tokens_out)
+    );
+    assert_eq!(events[0].lp_supply, lp_supply - liquidity);
+}
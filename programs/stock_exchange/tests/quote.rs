@@ -0,0 +1,113 @@
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use celebrity_exchange::{ExchangeError, Market, TradeQuote};
+use common::*;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::Signer;
+
+const SUPPLY: u64 = 1_000_000;
+
+fn quote_accounts(setup: &MarketSetup) -> Vec<AccountMeta> {
+    celebrity_exchange::accounts::Quote {
+        market: setup.market.market,
+        config: config_address(),
+    }
+    .to_account_metas(None)
+}
+
+fn quote_buy_ix(setup: &MarketSetup, amount: u64) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: quote_accounts(setup),
+        data: celebrity_exchange::instruction::QuoteBuy { amount }.data(),
+    }
+}
+
+fn quote_sell_ix(setup: &MarketSetup, amount: u64) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: quote_accounts(setup),
+        data: celebrity_exchange::instruction::QuoteSell { amount }.data(),
+    }
+}
+
+async fn trade(setup: &mut MarketSetup, ix: Instruction) {
+    setup.send_as_user(&[ix]).await.unwrap();
+}
+
+#[tokio::test]
+async fn quote_buy_matches_the_executed_buy() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+
+    let ix = quote_buy_ix(&setup, 1_000);
+    let quote: TradeQuote = simulate_return(&mut setup.context, &[ix], &[]).await;
+    assert_eq!(quote.tokens, 1_000);
+    assert_eq!(
+        quote.net_lamports,
+        quote.total_cost + quote.platform_fee + quote.creator_fee
+    );
+
+    let user_before = lamports(&mut setup.context, setup.user.pubkey()).await;
+    let treasury_before = lamports(&mut setup.context, setup.market.treasury).await;
+
+    // The quoted charge is exactly enough to pass the slippage check
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    trade(&mut setup, buy_ix(accounts, 1_000, quote.net_lamports)).await;
+
+    assert_eq!(
+        user_before - lamports(&mut setup.context, setup.user.pubkey()).await,
+        quote.net_lamports
+    );
+    assert_eq!(
+        lamports(&mut setup.context, setup.market.treasury).await - treasury_before,
+        quote.total_cost
+    );
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.current_price, quote.price_after);
+}
+
+#[tokio::test]
+async fn quote_sell_matches_the_executed_sell() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    trade(&mut setup, buy_ix(accounts, 1_000, u64::MAX)).await;
+
+    let ix = quote_sell_ix(&setup, 400);
+    let quote: TradeQuote = simulate_return(&mut setup.context, &[ix], &[]).await;
+    assert_eq!(quote.tokens, 400);
+
+    let user_before = lamports(&mut setup.context, setup.user.pubkey()).await;
+
+    // The quoted proceeds are exactly enough to pass the slippage check
+    let accounts = setup
+        .market
+        .buy_sell_accounts(setup.user.pubkey(), setup.user_token_account);
+    trade(&mut setup, sell_ix(accounts, 400, quote.net_lamports)).await;
+
+    assert_eq!(
+        lamports(&mut setup.context, setup.user.pubkey()).await - user_before,
+        quote.net_lamports
+    );
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.current_price, quote.price_after);
+    assert_eq!(market.circulating_supply, 600);
+}
+
+#[tokio::test]
+async fn quotes_reject_amounts_that_cannot_trade() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+
+    for ix in [
+        quote_buy_ix(&setup, 0),
+        quote_buy_ix(&setup, SUPPLY + 1),
+        quote_sell_ix(&setup, 1),
+    ] {
+        let result = process(&mut setup.context, &[ix], &[]).await;
+        assert_custom_error(result, ExchangeError::InvalidAmount);
+    }
+}
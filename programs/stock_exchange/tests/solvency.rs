@@ -0,0 +1,111 @@
+mod common;
+
+use celebrity_exchange::{ExchangeError, SolvencyReport};
+use celebrity_exchange_math::calculate_sell_value;
+use celebrity_exchange_sdk::instructions;
+use common::*;
+use solana_sdk::account::Account;
+
+const SUPPLY: u64 = 1_000_000;
+
+async fn verify(setup: &mut MarketSetup) -> SolvencyReport {
+    let ix = instructions::verify_market(setup.market.market);
+    simulate_return(&mut setup.context, &[ix], &[]).await
+}
+
+async fn trade(
+    setup: &mut MarketSetup,
+    buy: bool,
+    amount: u64,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let accounts = setup.user_accounts();
+    let ix = if buy {
+        buy_ix(accounts, amount, u64::MAX)
+    } else {
+        sell_ix(accounts, amount, 0)
+    };
+    setup.send_as_user(&[ix]).await
+}
+
+// Overwrites the treasury balance, as for a market whose treasury was never funded
+fn set_treasury(setup: &mut MarketSetup, lamports: u64) {
+    setup.context.set_account(
+        &setup.market.treasury,
+        &Account {
+            lamports,
+            owner: anchor_lang::system_program::ID,
+            ..Account::default()
+        }
+        .into(),
+    );
+}
+
+#[tokio::test]
+async fn treasury_covers_every_circulating_token() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    assert_eq!(
+        verify(&mut setup).await,
+        SolvencyReport {
+            treasury_balance: rent_exempt(0),
+            rent_reserve: rent_exempt(0),
+            curve_reserve: 0,
+            shortfall: 0,
+        }
+    );
+
+    trade(&mut setup, true, 1_000).await.unwrap();
+    trade(&mut setup, false, 400).await.unwrap();
+
+    let report = verify(&mut setup).await;
+    assert_eq!(
+        report.treasury_balance,
+        lamports(&mut setup.context, setup.market.treasury).await
+    );
+    assert_eq!(
+        report.curve_reserve,
+        calculate_sell_value(600, 0, &default_curve().into()).unwrap()
+    );
+    assert!(report.treasury_balance >= report.rent_reserve + report.curve_reserve);
+    assert_eq!(report.shortfall, 0);
+}
+
+#[tokio::test]
+async fn selling_every_token_leaves_the_treasury_rent_exempt() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    trade(&mut setup, true, 1_000).await.unwrap();
+    trade(&mut setup, false, 1_000).await.unwrap();
+
+    let report = verify(&mut setup).await;
+    assert_eq!(report.curve_reserve, 0);
+    assert_eq!(report.shortfall, 0);
+    assert!(lamports(&mut setup.context, setup.market.treasury).await >= rent_exempt(0));
+}
+
+#[tokio::test]
+async fn trades_fail_while_the_treasury_is_short() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    set_treasury(&mut setup, 0);
+
+    let report = verify(&mut setup).await;
+    assert_eq!(report.shortfall, rent_exempt(0));
+    let result = trade(&mut setup, true, 10).await;
+    assert_custom_error(result, ExchangeError::TreasuryInsolvent);
+
+    // Topping the treasury up by the reported shortfall puts the market right
+    set_treasury(&mut setup, report.shortfall);
+    trade(&mut setup, true, 10).await.unwrap();
+    assert_eq!(verify(&mut setup).await.shortfall, 0);
+}
+
+#[tokio::test]
+async fn verify_reports_lamports_missing_from_the_curve_reserve() {
+    let mut setup = MarketSetup::start(SUPPLY).await;
+    trade(&mut setup, true, 1_000).await.unwrap();
+
+    let report = verify(&mut setup).await;
+    set_treasury(&mut setup, report.rent_reserve + report.curve_reserve - 1);
+    assert_eq!(verify(&mut setup).await.shortfall, 1);
+
+    let result = trade(&mut setup, false, 100).await;
+    assert_custom_error(result, ExchangeError::TreasuryInsolvent);
+}
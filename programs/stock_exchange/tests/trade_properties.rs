@@ -0,0 +1,167 @@
+use anchor_lang::prelude::Pubkey;
+use celebrity_exchange::{
+    CurveParams, CurveType, GraduationThreshold, Market, MarketStatus, TradingMode,
+    DEFAULT_MAX_PRICE_MULTIPLIER,
+};
+use celebrity_exchange_math::{calculate_buy_cost, calculate_current_price, calculate_sell_value};
+use proptest::prelude::*;
+
+// Lamports the treasury holds before any trade
+const TREASURY_RENT: u128 = 890_880;
+
+fn curve_types() -> impl Strategy<Value = CurveType> {
+    prop::sample::select(vec![
+        CurveType::Exponential,
+        CurveType::Linear,
+        CurveType::Sigmoid,
+        CurveType::ConstantProduct,
+    ])
+}
+
+fn curves() -> impl Strategy<Value = CurveParams> {
+    (
+        curve_types(),
+        1u64..=u64::MAX / DEFAULT_MAX_PRICE_MULTIPLIER,
+        1u64..=DEFAULT_MAX_PRICE_MULTIPLIER,
+        1u64..=1_000,
+        1u64..=1_000_000,
+    )
+        .prop_map(
+            |(curve_type, base_price, multiplier, k_factor, scale_factor)| CurveParams {
+                curve_type,
+                base_price,
+                k_factor,
+                scale_factor,
+                max_price: base_price * multiplier,
+            },
+        )
+        .prop_filter("curve must validate", |curve| curve.validate().is_ok())
+}
+
+// Curves cheap enough that a few million tokens never cost more than a u64 holds
+fn cheap_curves() -> impl Strategy<Value = CurveParams> {
+    (
+        curve_types(),
+        1u64..=1_000_000,
+        1u64..=100,
+        1_000u64..=100_000,
+    )
+        .prop_map(
+            |(curve_type, base_price, k_factor, scale_factor)| CurveParams {
+                curve_type,
+                base_price,
+                k_factor,
+                scale_factor,
+                max_price: base_price * DEFAULT_MAX_PRICE_MULTIPLIER,
+            },
+        )
+}
+
+fn market(curve: CurveParams) -> Market {
+    Market {
+        owner: Pubkey::default(),
+        mint: Pubkey::default(),
+        escrow: Pubkey::default(),
+        treasury: Pubkey::default(),
+        current_price: curve.base_price,
+        total_supply: u64::MAX,
+        circulating_supply: 0,
+        trade_count: 0,
+        curve,
+        trading_mode: TradingMode::Active,
+        metadata_locked: false,
+        name: String::new(),
+        symbol: String::new(),
+        uri: String::new(),
+        graduation: GraduationThreshold::SoldSupply(u64::MAX),
+        status: MarketStatus::Active,
+        closing_started_at: 0,
+        creator_allocation: 0,
+        escrow_closed: false,
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Trade {
+    Buy(u64),
+    Sell(u64),
+}
+
+fn trades() -> impl Strategy<Value = Vec<Trade>> {
+    prop::collection::vec(
+        prop_oneof![
+            (1u64..50_000).prop_map(Trade::Buy),
+            (1u64..50_000).prop_map(Trade::Sell),
+        ],
+        1..64,
+    )
+}
+
+proptest! {
+    #[test]
+    fn buy_then_sell_returns_no_more_than_was_paid(
+        curve in cheap_curves(),
+        start in 0u64..1_000_000,
+        amount in 1u64..1_000_000,
+    ) {
+        let mut market = market(curve);
+        market.circulating_supply = start;
+
+        let paid = market.apply_buy(amount).unwrap();
+        let received = market.apply_sell(amount).unwrap();
+
+        prop_assert!(received <= paid);
+        prop_assert_eq!(market.circulating_supply, start);
+        prop_assert_eq!(calculate_sell_value(start + amount, start, &curve.into()).unwrap(), received);
+    }
+
+    #[test]
+    fn treasury_covers_outstanding_supply(curve in curves(), trades in trades()) {
+        let mut market = market(curve);
+        let mut treasury = TREASURY_RENT;
+
+        for trade in trades {
+            match trade {
+                Trade::Buy(amount) => {
+                    let before = market.circulating_supply;
+                    match market.apply_buy(amount) {
+                        Ok(cost) => treasury += cost as u128,
+                        // Too expensive to represent; the market must be left untouched
+                        Err(_) => {
+                            prop_assert_eq!(market.circulating_supply, before);
+                            continue;
+                        }
+                    }
+                }
+                Trade::Sell(amount) => {
+                    let amount = amount.min(market.circulating_supply);
+                    if amount == 0 {
+                        continue;
+                    }
+                    let before = market.circulating_supply;
+                    match market.apply_sell(amount) {
+                        Ok(value) => {
+                            prop_assert!(value as u128 <= treasury - TREASURY_RENT);
+                            treasury -= value as u128;
+                        }
+                        // Several buys that each fit can add up to a sale that does not,
+                        // which no real treasury could hold anyway
+                        Err(_) => {
+                            prop_assert!(treasury - TREASURY_RENT > u64::MAX as u128);
+                            prop_assert_eq!(market.circulating_supply, before);
+                            continue;
+                        }
+                    }
+                }
+            }
+
+            prop_assert_eq!(
+                market.current_price,
+                calculate_current_price(market.circulating_supply, &curve.into()).unwrap()
+            );
+            if let Ok(integral) = calculate_buy_cost(0, market.circulating_supply, &curve.into()) {
+                prop_assert!(treasury - TREASURY_RENT >= integral as u128);
+            }
+        }
+    }
+}
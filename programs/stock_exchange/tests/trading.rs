@@ -0,0 +1,452 @@
+mod common;
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use celebrity_exchange::{
+    CurveParams, CurveType, ExchangeError, Market, TradeExecuted, TradeKind, BASE_PRICE,
+    DEFAULT_MAX_PRICE_MULTIPLIER, FEE_PRECISION, K_FACTOR, SCALE_FACTOR,
+};
+use celebrity_exchange_math::{
+    calculate_buy_cost, calculate_current_price, calculate_fees, calculate_sell_value, FeeSchedule,
+};
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::signature::Signer;
+
+const SUPPLY: u64 = 1_000_000;
+
+// The curve MarketFixture seeds
+const CURVE: CurveParams = CurveParams {
+    curve_type: CurveType::Exponential,
+    base_price: BASE_PRICE,
+    k_factor: K_FACTOR,
+    scale_factor: SCALE_FACTOR,
+    max_price: BASE_PRICE * DEFAULT_MAX_PRICE_MULTIPLIER,
+};
+
+async fn setup(supply: u64) -> MarketSetup {
+    setup_with_curve(supply, CURVE).await
+}
+
+async fn setup_with_curve(supply: u64, curve: CurveParams) -> MarketSetup {
+    MarketSetup::start_with(0, |program_test, market| {
+        market.add_with_curve(program_test, supply, curve)
+    })
+    .await
+}
+
+fn cost(from: u64, to: u64) -> u64 {
+    calculate_buy_cost(from, to, &CURVE.into()).unwrap()
+}
+
+fn price(supply: u64) -> u64 {
+    calculate_current_price(supply, &CURVE.into()).unwrap()
+}
+
+// The fees add_config sets up
+const FEES: FeeSchedule = FeeSchedule {
+    transaction_fee_bps: TRANSACTION_FEE_BPS,
+    platform_fee_share_bps: PLATFORM_FEE_SHARE_BPS,
+    creator_fee_share_bps: CREATOR_FEE_SHARE_BPS,
+    min_fee: MIN_TRADE_FEE,
+};
+
+// The fee on a trade of `amount` lamports; `fees` gives the shares it splits into
+fn total_fee(amount: u64) -> u64 {
+    calculate_fees(amount, &FEES).unwrap().0
+}
+
+fn fees(amount: u64) -> (u64, u64) {
+    let (total, platform_fee, creator_fee) = calculate_fees(amount, &FEES).unwrap();
+    assert_eq!(platform_fee + creator_fee, total);
+    (platform_fee, creator_fee)
+}
+
+async fn send(setup: &mut MarketSetup, ix: Instruction) -> Result<(), BanksClientError> {
+    setup.send_as_user(&[ix]).await
+}
+
+fn buy(setup: &MarketSetup, amount: u64, max_cost: u64) -> Instruction {
+    buy_ix(setup.user_accounts(), amount, max_cost)
+}
+
+fn sell(setup: &MarketSetup, amount: u64, min_receive: u64) -> Instruction {
+    sell_ix(setup.user_accounts(), amount, min_receive)
+}
+
+fn buy_exact_in(setup: &MarketSetup, max_spend: u64, min_tokens_out: u64) -> Instruction {
+    Instruction {
+        program_id: celebrity_exchange::ID,
+        accounts: setup.user_accounts().to_account_metas(None),
+        data: celebrity_exchange::instruction::BuyTokensExactIn {
+            max_spend_lamports: max_spend,
+            min_tokens_out,
+        }
+        .data(),
+    }
+}
+
+struct Balances {
+    user: u64,
+    treasury: u64,
+    fee_wallet: u64,
+    creator: u64,
+}
+
+async fn balances(setup: &mut MarketSetup) -> Balances {
+    Balances {
+        user: lamports(&mut setup.context, setup.user.pubkey()).await,
+        treasury: lamports(&mut setup.context, setup.market.treasury).await,
+        fee_wallet: lamports(&mut setup.context, FEE_WALLET).await,
+        creator: lamports(&mut setup.context, setup.market.owner).await,
+    }
+}
+
+#[tokio::test]
+async fn buy_pays_treasury_and_splits_fees() {
+    let mut setup = setup(SUPPLY).await;
+    let before = balances(&mut setup).await;
+
+    let ix = buy(&setup, 100, u64::MAX);
+    let user = setup.user.insecure_clone();
+    let events: Vec<TradeExecuted> = process_with_events(&mut setup.context, &[ix], &[&user]).await;
+
+    let cost = cost(0, 100);
+    let (platform_fee, creator_fee) = fees(cost);
+    let after = balances(&mut setup).await;
+    assert_eq!(before.user - after.user, cost + platform_fee + creator_fee);
+    assert_eq!(after.treasury - before.treasury, cost);
+    assert_eq!(after.fee_wallet - before.fee_wallet, platform_fee);
+    assert_eq!(after.creator - before.creator, creator_fee);
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        100
+    );
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.circulating_supply, 100);
+    assert_eq!(market.trade_count, 1);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.market, setup.market.market);
+    assert_eq!(event.buyer, Some(setup.user.pubkey()));
+    assert_eq!(event.seller, None);
+    assert_eq!(event.tokens, 100);
+    assert_eq!(event.price, price(100));
+    assert_eq!(market.current_price, price(100));
+    assert_eq!(event.total_cost, cost);
+    assert_eq!(event.platform_fee, platform_fee);
+    assert_eq!(event.creator_fee, creator_fee);
+    let unrounded = u128::from(cost) * u128::from(TRANSACTION_FEE_BPS) * 10_000;
+    assert_eq!(event.fee_unrounded, unrounded);
+    assert_eq!(
+        event.platform_fee_unrounded,
+        unrounded * u128::from(PLATFORM_FEE_SHARE_BPS) / 10_000
+    );
+    assert_eq!(
+        event.platform_fee_unrounded + event.creator_fee_unrounded,
+        event.fee_unrounded
+    );
+    assert!(event.kind == TradeKind::Buy);
+    assert_eq!(event.sequence, 1);
+    assert_eq!(event.circulating_supply_before, 0);
+    assert_eq!(event.circulating_supply_after, 100);
+    assert_eq!(event.treasury_balance, after.treasury);
+}
+
+#[tokio::test]
+async fn every_token_bought_raises_the_price() {
+    let mut setup = setup(SUPPLY).await;
+    let mut last_price = BASE_PRICE;
+
+    for _ in 0..3 {
+        let ix = buy(&setup, 1, u64::MAX);
+        send(&mut setup, ix).await.unwrap();
+        setup.context.get_new_latest_blockhash().await.unwrap();
+
+        let market: Market = fetch(&mut setup.context, setup.market.market).await;
+        assert!(market.current_price > last_price);
+        last_price = market.current_price;
+    }
+
+    // One purchase of three costs the same as three purchases of one
+    let treasury = lamports(&mut setup.context, setup.market.treasury).await;
+    assert_eq!(treasury - rent_exempt(0), cost(0, 3));
+}
+
+#[tokio::test]
+async fn trades_follow_the_market_curve_type() {
+    let curve = CurveParams {
+        curve_type: CurveType::ConstantProduct,
+        ..CURVE
+    };
+    let mut setup = setup_with_curve(SUPPLY, curve).await;
+    let before = balances(&mut setup).await;
+
+    let ix = buy(&setup, 1_000, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+
+    // Half the virtual reserve is gone, so the price has quadrupled
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.current_price, 4 * BASE_PRICE);
+    let after = balances(&mut setup).await;
+    assert_eq!(
+        after.treasury - before.treasury,
+        calculate_buy_cost(0, 1_000, &curve.into()).unwrap()
+    );
+
+    let ix = sell(&setup, 400, 0);
+    send(&mut setup, ix).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(
+        market.current_price,
+        calculate_current_price(600, &curve.into()).unwrap()
+    );
+    let value = calculate_sell_value(1_000, 600, &curve.into()).unwrap();
+    assert_eq!(after.treasury - balances(&mut setup).await.treasury, value);
+}
+
+#[tokio::test]
+async fn buy_rejects_charge_above_max_cost() {
+    let mut setup = setup(SUPPLY).await;
+    let cost = cost(0, 100);
+    let (platform_fee, creator_fee) = fees(cost);
+    let charge = cost + platform_fee + creator_fee;
+
+    let ix = buy(&setup, 100, charge - 1);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+
+    let ix = buy(&setup, 100, charge);
+    send(&mut setup, ix).await.unwrap();
+}
+
+#[tokio::test]
+async fn buy_rejects_zero_amount() {
+    let mut setup = setup(SUPPLY).await;
+
+    let ix = buy(&setup, 0, u64::MAX);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn exact_in_buys_the_most_tokens_the_budget_covers() {
+    let mut setup = setup(SUPPLY).await;
+    let cost = cost(0, 100);
+    let (platform_fee, creator_fee) = fees(cost);
+    let charge = cost + platform_fee + creator_fee;
+    let before = balances(&mut setup).await;
+
+    // Not quite enough for a 101st token
+    let ix = buy_exact_in(&setup, charge + price(100), 100);
+    send(&mut setup, ix).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        100
+    );
+    let after = balances(&mut setup).await;
+    assert_eq!(before.user - after.user, charge);
+}
+
+#[tokio::test]
+async fn exact_in_rejects_fewer_tokens_than_min_out() {
+    let mut setup = setup(SUPPLY).await;
+    let cost = cost(0, 100);
+    let (platform_fee, creator_fee) = fees(cost);
+
+    let ix = buy_exact_in(&setup, cost + platform_fee + creator_fee, 101);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+}
+
+#[tokio::test]
+async fn exact_in_rejects_budget_below_one_token() {
+    let mut setup = setup(SUPPLY).await;
+
+    let ix = buy_exact_in(&setup, BASE_PRICE, 0);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn sell_pays_seller_from_treasury_and_splits_fees() {
+    let mut setup = setup(SUPPLY).await;
+    let ix = buy(&setup, 1_000, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+    let before = balances(&mut setup).await;
+
+    let ix = sell(&setup, 400, 0);
+    let user = setup.user.insecure_clone();
+    let events: Vec<TradeExecuted> = process_with_events(&mut setup.context, &[ix], &[&user]).await;
+
+    let value = cost(600, 1_000);
+    let (platform_fee, creator_fee) = fees(value);
+    let proceeds = value - total_fee(value);
+    let after = balances(&mut setup).await;
+    assert_eq!(after.user - before.user, proceeds);
+    // The treasury pays out the curve value exactly, fees included
+    assert_eq!(before.treasury - after.treasury, value);
+    assert_eq!(proceeds + platform_fee + creator_fee, value);
+    assert_eq!(after.fee_wallet - before.fee_wallet, platform_fee);
+    assert_eq!(after.creator - before.creator, creator_fee);
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        600
+    );
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.circulating_supply, 600);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.buyer, None);
+    assert_eq!(event.seller, Some(setup.user.pubkey()));
+    assert_eq!(event.tokens, 400);
+    assert_eq!(event.total_cost, value);
+    assert_eq!(event.platform_fee, platform_fee);
+    assert_eq!(event.creator_fee, creator_fee);
+    assert!(event.kind == TradeKind::Sell);
+    assert_eq!(event.sequence, 2);
+    assert_eq!(event.circulating_supply_before, 1_000);
+    assert_eq!(event.circulating_supply_after, 600);
+    assert_eq!(event.treasury_balance, after.treasury);
+}
+
+#[tokio::test]
+async fn trade_events_form_a_gapless_sequence() {
+    let mut setup = setup(SUPPLY).await;
+    let user = setup.user.insecure_clone();
+
+    // Several trades in one transaction land in the same slot; only the sequence orders them
+    let ixs = [
+        buy(&setup, 300, u64::MAX),
+        buy(&setup, 200, u64::MAX),
+        sell(&setup, 150, 0),
+        buy(&setup, 50, u64::MAX),
+    ];
+    let events: Vec<TradeExecuted> = process_with_events(&mut setup.context, &ixs, &[&user]).await;
+
+    assert_eq!(events.len(), 4);
+    let mut supply = 0;
+    for (expected, event) in (1..).zip(&events) {
+        assert_eq!(event.sequence, expected);
+        assert_eq!(event.circulating_supply_before, supply);
+        supply = event.circulating_supply_after;
+    }
+    assert_eq!(supply, 400);
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.trade_count, 4);
+    assert_eq!(market.circulating_supply, supply);
+    assert_eq!(
+        events[3].treasury_balance,
+        lamports(&mut setup.context, setup.market.treasury).await
+    );
+}
+
+#[tokio::test]
+async fn small_trades_pay_the_minimum_fee() {
+    let curve = CurveParams {
+        curve_type: CurveType::Linear,
+        base_price: 10_000,
+        k_factor: 1,
+        scale_factor: 10_000,
+        max_price: 1_000_000,
+    };
+    let mut setup = setup_with_curve(SUPPLY, curve).await;
+    let before = balances(&mut setup).await;
+
+    let ix = buy(&setup, 1, u64::MAX);
+    let user = setup.user.insecure_clone();
+    let events: Vec<TradeExecuted> = process_with_events(&mut setup.context, &[ix], &[&user]).await;
+
+    // 1% of the token's cost comes to far less than the minimum
+    let cost = calculate_buy_cost(0, 1, &curve.into()).unwrap();
+    assert!(cost * TRANSACTION_FEE_BPS / 10_000 < MIN_TRADE_FEE);
+    let after = balances(&mut setup).await;
+    assert_eq!(before.user - after.user, cost + MIN_TRADE_FEE);
+    assert_eq!(
+        (after.fee_wallet - before.fee_wallet) + (after.creator - before.creator),
+        MIN_TRADE_FEE
+    );
+
+    let event = &events[0];
+    assert_eq!(event.platform_fee + event.creator_fee, MIN_TRADE_FEE);
+    // The event still records what the percentage alone would have charged
+    assert_eq!(
+        event.fee_unrounded,
+        u128::from(cost * TRANSACTION_FEE_BPS) * FEE_PRECISION / 10_000
+    );
+}
+
+#[tokio::test]
+async fn sell_rejects_proceeds_below_min_receive() {
+    let mut setup = setup(SUPPLY).await;
+    let ix = buy(&setup, 1_000, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+
+    let value = cost(600, 1_000);
+    let proceeds = value - total_fee(value);
+
+    let ix = sell(&setup, 400, proceeds + 1);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::SlippageExceeded);
+
+    let ix = sell(&setup, 400, proceeds);
+    send(&mut setup, ix).await.unwrap();
+}
+
+#[tokio::test]
+async fn sell_rejects_more_than_circulating() {
+    let mut setup = setup(SUPPLY).await;
+    let ix = buy(&setup, 1_000, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+
+    let ix = sell(&setup, 1_001, 0);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn buying_out_the_escrow_exhausts_supply() {
+    let mut setup = setup(1_000).await;
+
+    let ix = buy(&setup, 1_000, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        0
+    );
+
+    // Nothing is left for a buy to take
+    let ix = buy(&setup, 1, u64::MAX);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+
+    let ix = buy_exact_in(&setup, u64::MAX / 2, 0);
+    let result = send(&mut setup, ix).await;
+    assert_custom_error(result, ExchangeError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn exact_in_is_capped_by_remaining_supply() {
+    let mut setup = setup(1_000).await;
+    let ix = buy(&setup, 900, u64::MAX);
+    send(&mut setup, ix).await.unwrap();
+
+    let ix = buy_exact_in(&setup, 2 * cost(900, 1_000), 0);
+    send(&mut setup, ix).await.unwrap();
+
+    assert_eq!(
+        token_balance(&mut setup.context, setup.user_token_account).await,
+        1_000
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        0
+    );
+}
@@ -0,0 +1,346 @@
+mod common;
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::Mint;
+use celebrity_exchange::{
+    instruction::CreateMarket, CurveType, ExchangeError, GraduationThreshold, Market,
+    VestedTokensClaimed, VestingParams, VestingSchedule, VestingScheduleCreated,
+    CLOSE_GRACE_PERIOD,
+};
+use celebrity_exchange_sdk::instructions;
+use common::*;
+use solana_program_test::BanksClientError;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::signature::{Keypair, Signer};
+
+const SUPPLY: u64 = 1_000_000;
+const ALLOCATION: u64 = 100_000;
+const DAY: i64 = 24 * 60 * 60;
+const CLIFF: i64 = 90 * DAY;
+const DURATION: i64 = 360 * DAY;
+// Tokens sold when markets made by `create_with_allocation` graduate
+const GRADUATION: u64 = 1_000;
+
+// A market the test creates itself
+async fn setup() -> MarketSetup {
+    MarketSetup::start_with(0, |_, _| {}).await
+}
+
+fn create_ix(
+    setup: &MarketSetup,
+    creator_allocation: Option<VestingParams>,
+    graduation_threshold: Option<GraduationThreshold>,
+) -> Instruction {
+    setup.market.create_market_with(CreateMarket {
+        initial_price_lamports: 1_000_000,
+        initial_supply: SUPPLY,
+        name: "Celebrity".to_string(),
+        symbol: "CELEB".to_string(),
+        uri: "https://example.com/celebrity.json".to_string(),
+        curve_type: CurveType::Exponential,
+        max_price_lamports: None,
+        graduation_threshold,
+        creator_allocation,
+        curve_growth: None,
+    })
+}
+
+fn allocation(amount: u64) -> VestingParams {
+    VestingParams {
+        amount,
+        cliff_seconds: CLIFF,
+        duration_seconds: DURATION,
+    }
+}
+
+// Creates the market with ALLOCATION vesting over DURATION, graduating once GRADUATION
+// tokens have sold, and returns its schedule
+async fn create_with_allocation(setup: &mut MarketSetup) -> VestingSchedule {
+    let graduation = GraduationThreshold::SoldSupply(GRADUATION);
+    let ix = create_ix(setup, Some(allocation(ALLOCATION)), Some(graduation));
+    let signers = [&setup.owner, &setup.mint];
+    process(&mut setup.context, &[ix], &signers).await.unwrap();
+
+    // The owner claims into their associated token account
+    let payer = setup.context.payer.pubkey();
+    let ix =
+        instructions::create_user_token_account(payer, setup.owner.pubkey(), setup.mint.pubkey());
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+
+    fetch(&mut setup.context, setup.addresses.vesting_schedule).await
+}
+
+// The user buys `amount` tokens off the curve
+async fn buy(setup: &mut MarketSetup, amount: u64) {
+    let ix = buy_ix(setup.user_accounts(), amount, u64::MAX);
+    setup.send_as_user(&[ix]).await.unwrap();
+}
+
+async fn graduate(setup: &mut MarketSetup) {
+    buy(setup, GRADUATION).await;
+    let ix = instructions::graduate_market(setup.user.pubkey(), setup.mint.pubkey());
+    setup.send_as_user(&[ix]).await.unwrap();
+}
+
+async fn claim(setup: &mut MarketSetup, beneficiary: &Keypair) -> Result<(), BanksClientError> {
+    let ix = instructions::claim_vested(beneficiary.pubkey(), setup.mint.pubkey());
+    setup.send(&[ix], beneficiary).await
+}
+
+async fn warp_to(setup: &mut MarketSetup, unix_timestamp: i64) {
+    warp_to_timestamp(&mut setup.context, unix_timestamp).await;
+}
+
+async fn owner_balance(setup: &mut MarketSetup) -> u64 {
+    let account = get_associated_token_address(&setup.owner.pubkey(), &setup.mint.pubkey());
+    token_balance(&mut setup.context, account).await
+}
+
+#[tokio::test]
+async fn locks_the_allocation_out_of_the_curve_supply() {
+    let mut setup = setup().await;
+    let ix = create_ix(&setup, Some(allocation(ALLOCATION)), None);
+    let signers = [&setup.owner, &setup.mint];
+    let events: Vec<VestingScheduleCreated> =
+        process_with_events(&mut setup.context, &[ix], &signers).await;
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    assert_eq!(market.total_supply, SUPPLY);
+    assert_eq!(market.creator_allocation, ALLOCATION);
+    assert_eq!(market.curve_supply(), SUPPLY - ALLOCATION);
+    // The default graduation threshold only counts the curve's share of the supply
+    assert_eq!(
+        market.graduation,
+        GraduationThreshold::SoldSupply((SUPPLY - ALLOCATION) * 8 / 10)
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.market.escrow).await,
+        SUPPLY - ALLOCATION
+    );
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.vesting_vault).await,
+        ALLOCATION
+    );
+
+    let schedule: VestingSchedule =
+        fetch(&mut setup.context, setup.addresses.vesting_schedule).await;
+    assert_eq!(schedule.market, setup.market.market);
+    assert_eq!(schedule.beneficiary, setup.owner.pubkey());
+    assert_eq!(schedule.vault, setup.addresses.vesting_vault);
+    assert_eq!(schedule.total_amount, ALLOCATION);
+    assert_eq!(schedule.claimed_amount, 0);
+    assert_eq!(schedule.cliff_time, schedule.start_time + CLIFF);
+    assert_eq!(schedule.end_time, schedule.start_time + DURATION);
+
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.schedule, setup.addresses.vesting_schedule);
+    assert_eq!(event.beneficiary, setup.owner.pubkey());
+    assert_eq!(event.total_amount, ALLOCATION);
+    assert_eq!(
+        (event.start_time, event.cliff_time, event.end_time),
+        (schedule.start_time, schedule.cliff_time, schedule.end_time)
+    );
+}
+
+#[tokio::test]
+async fn releases_on_a_cliff_then_linearly() {
+    let mut setup = setup().await;
+    let schedule = create_with_allocation(&mut setup).await;
+    graduate(&mut setup).await;
+    let owner = setup.owner.insecure_clone();
+
+    let result = claim(&mut setup, &owner).await;
+    assert_custom_error(result, ExchangeError::NothingToClaim);
+
+    // The cliff releases everything that accrued before it
+    warp_to(&mut setup, schedule.cliff_time).await;
+    let ix = instructions::claim_vested(owner.pubkey(), setup.mint.pubkey());
+    let events: Vec<VestedTokensClaimed> =
+        process_with_events(&mut setup.context, &[ix], &[&owner]).await;
+    assert_eq!(owner_balance(&mut setup).await, ALLOCATION / 4);
+    assert_eq!(events[0].amount, ALLOCATION / 4);
+    assert_eq!(events[0].claimed_amount, ALLOCATION / 4);
+    assert_eq!(events[0].locked_amount, ALLOCATION * 3 / 4);
+
+    // A new slot at the same time, so the repeated claim is a distinct transaction
+    warp_to(&mut setup, schedule.cliff_time).await;
+    let result = claim(&mut setup, &owner).await;
+    assert_custom_error(result, ExchangeError::NothingToClaim);
+
+    warp_to(&mut setup, schedule.start_time + DURATION / 2).await;
+    claim(&mut setup, &owner).await.unwrap();
+    assert_eq!(owner_balance(&mut setup).await, ALLOCATION / 2);
+
+    warp_to(&mut setup, schedule.end_time + DAY).await;
+    claim(&mut setup, &owner).await.unwrap();
+    assert_eq!(owner_balance(&mut setup).await, ALLOCATION);
+    assert_eq!(
+        token_balance(&mut setup.context, setup.addresses.vesting_vault).await,
+        0
+    );
+    let schedule: VestingSchedule =
+        fetch(&mut setup.context, setup.addresses.vesting_schedule).await;
+    assert_eq!(schedule.claimed_amount, ALLOCATION);
+}
+
+#[tokio::test]
+async fn only_the_creator_claims_once_the_market_graduates() {
+    let mut setup = setup().await;
+    let schedule = create_with_allocation(&mut setup).await;
+    warp_to(&mut setup, schedule.end_time).await;
+
+    // Fully vested, but the curve still trades
+    let owner = setup.owner.insecure_clone();
+    let result = claim(&mut setup, &owner).await;
+    assert_custom_error(result, ExchangeError::MarketNotGraduated);
+
+    graduate(&mut setup).await;
+    let stranger = Keypair::new();
+    let payer = setup.context.payer.pubkey();
+    let ixs = [
+        instructions::create_user_token_account(payer, stranger.pubkey(), setup.mint.pubkey()),
+        instructions::claim_vested(stranger.pubkey(), setup.mint.pubkey()),
+    ];
+    let result = process(&mut setup.context, &ixs, &[&stranger]).await;
+    assert_custom_error(result, ExchangeError::Unauthorized);
+
+    claim(&mut setup, &owner).await.unwrap();
+    assert_eq!(owner_balance(&mut setup).await, ALLOCATION);
+}
+
+#[tokio::test]
+async fn the_allocation_never_drains_a_closing_treasury() {
+    let mut setup = setup().await;
+    let schedule = create_with_allocation(&mut setup).await;
+    buy(&mut setup, 100).await;
+    warp_to(&mut setup, schedule.end_time).await;
+
+    // The creator has nothing to sell back to the curve, nor to redeem once it closes
+    let owner = setup.owner.insecure_clone();
+    let result = claim(&mut setup, &owner).await;
+    assert_custom_error(result, ExchangeError::MarketNotGraduated);
+    let ix = instructions::close_market(owner.pubkey(), setup.market.market);
+    setup.send(&[ix], &owner).await.unwrap();
+    let result = claim(&mut setup, &owner).await;
+    assert_custom_error(result, ExchangeError::MarketNotGraduated);
+
+    // so the buyer redeems everything the treasury holds above rent
+    let treasury = lamports(&mut setup.context, setup.market.treasury).await;
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let mut accounts =
+        instructions::redeem_accounts(setup.user.pubkey(), setup.market.market, &market);
+    accounts.user_token_account = setup.user_token_account;
+    let ix = instructions::redeem_tokens(accounts, 100);
+    let user_before = lamports(&mut setup.context, setup.user.pubkey()).await;
+    setup.send_as_user(&[ix]).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, setup.user.pubkey()).await - user_before,
+        treasury - rent_exempt(0)
+    );
+}
+
+#[tokio::test]
+async fn finalizing_a_close_burns_the_allocation() {
+    let mut setup = setup().await;
+    create_with_allocation(&mut setup).await;
+    let owner = setup.owner.insecure_clone();
+    let ix = instructions::close_market(owner.pubkey(), setup.market.market);
+    setup.send(&[ix], &owner).await.unwrap();
+
+    let vault = setup.addresses.vesting_vault;
+    let schedule_address = setup.addresses.vesting_schedule;
+    let rent = lamports(&mut setup.context, vault).await
+        + lamports(&mut setup.context, schedule_address).await;
+    let owner_before = lamports(&mut setup.context, owner.pubkey()).await;
+
+    // The vesting accounts are required while the market has an allocation
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let mut ix = instructions::finalize_close(setup.market.market, &market);
+    let program = celebrity_exchange::ID;
+    let len = ix.accounts.len();
+    ix.accounts[len - 2] = AccountMeta::new_readonly(program, false);
+    ix.accounts[len - 1] = AccountMeta::new_readonly(program, false);
+    let result = process(&mut setup.context, &[ix], &[]).await;
+    assert_custom_error(result, ExchangeError::InvalidVestingSchedule);
+
+    let ix = instructions::finalize_close(setup.market.market, &market);
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+    assert_eq!(lamports(&mut setup.context, vault).await, 0);
+    assert_eq!(lamports(&mut setup.context, schedule_address).await, 0);
+    assert!(lamports(&mut setup.context, owner.pubkey()).await - owner_before > rent);
+    let mint: Mint = fetch(&mut setup.context, setup.mint.pubkey()).await;
+    assert_eq!(mint.supply, 0);
+}
+
+#[tokio::test]
+async fn later_finalize_runs_skip_the_spent_allocation() {
+    let mut setup = setup().await;
+    create_with_allocation(&mut setup).await;
+    buy(&mut setup, 100).await;
+    let owner = setup.owner.insecure_clone();
+    let ix = instructions::close_market(owner.pubkey(), setup.market.market);
+    setup.send(&[ix], &owner).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    warp_to(&mut setup, market.closing_started_at + CLOSE_GRACE_PERIOD).await;
+
+    let ix = instructions::finalize_close(setup.market.market, &market);
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+    assert_eq!(
+        lamports(&mut setup.context, setup.addresses.vesting_schedule).await,
+        0
+    );
+
+    // A recreated escrow doesn't make the next run look for the burned allocation
+    let payer = setup.context.payer.pubkey();
+    let ix = instructions::create_user_token_account(
+        payer,
+        setup.market.escrow_authority,
+        setup.mint.pubkey(),
+    );
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let mut accounts =
+        instructions::redeem_accounts(setup.user.pubkey(), setup.market.market, &market);
+    accounts.user_token_account = setup.user_token_account;
+    let ix = instructions::redeem_tokens(accounts, 100);
+    setup.send_as_user(&[ix]).await.unwrap();
+
+    let market: Market = fetch(&mut setup.context, setup.market.market).await;
+    let ix = instructions::finalize_close(setup.market.market, &market);
+    process(&mut setup.context, &[ix], &[]).await.unwrap();
+    assert_eq!(lamports(&mut setup.context, setup.market.market).await, 0);
+}
+
+#[tokio::test]
+async fn rejects_invalid_allocations() {
+    let mut setup = setup().await;
+    let invalid = [
+        allocation(0),
+        // Above MAX_CREATOR_ALLOCATION_BPS of the supply
+        allocation(SUPPLY / 5 + 1),
+        VestingParams {
+            cliff_seconds: DURATION + 1,
+            ..allocation(ALLOCATION)
+        },
+        VestingParams {
+            cliff_seconds: 0,
+            duration_seconds: 0,
+            ..allocation(ALLOCATION)
+        },
+    ];
+
+    for params in invalid {
+        let ix = create_ix(&setup, Some(params), None);
+        let signers = [&setup.owner, &setup.mint];
+        let result = process(&mut setup.context, &[ix], &signers).await;
+        assert_custom_error(result, ExchangeError::InvalidVestingSchedule);
+    }
+
+    // The vesting accounts only come with an allocation
+    let mut ix = create_ix(&setup, None, None);
+    ix.accounts = create_ix(&setup, Some(allocation(ALLOCATION)), None).accounts;
+    let signers = [&setup.owner, &setup.mint];
+    let result = process(&mut setup.context, &[ix], &signers).await;
+    assert_custom_error(result, ExchangeError::InvalidVestingSchedule);
+}